@@ -0,0 +1,148 @@
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, DeriveInput, Error, Expr, ExprLit, ExprRange, Lit, LitStr, Meta, Token};
+
+/// 结构体上的 `#[task(name = "...", task_type = "...")]` 属性
+pub struct TaskAttrs {
+    pub name: String,
+    pub task_type: String
+}
+
+impl TaskAttrs {
+    pub fn parse(input: &DeriveInput) -> Result<Self, Error> {
+        let attr = input
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("task"))
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    &input.ident,
+                    "必须提供 #[task(name = \"...\", task_type = \"...\")] 属性"
+                )
+            })?;
+
+        let mut name = None;
+        let mut task_type = None;
+        for meta in parse_metas(attr)? {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("name") => name = Some(lit_str(&nv.value)?.value()),
+                Meta::NameValue(nv) if nv.path.is_ident("task_type") => {
+                    task_type = Some(lit_str(&nv.value)?.value())
+                },
+                _ => return Err(Error::new_spanned(meta, "task 属性只支持 name 和 task_type 参数"))
+            }
+        }
+
+        Ok(TaskAttrs {
+            name: name.ok_or_else(|| Error::new_spanned(attr, "必须提供 task name"))?,
+            task_type: task_type.ok_or_else(|| Error::new_spanned(attr, "必须提供 task task_type"))?
+        })
+    }
+}
+
+/// 字段上的 `#[task(default = ..., range = ..., rename = "...", values = [...], skip)]` 属性
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// 未设置时使用的默认值
+    pub default: Option<Expr>,
+    /// `validate` 检查的取值范围
+    pub range: Option<ExprRange>,
+    /// builder 中 setter 的名字
+    pub rename: Option<LitStr>,
+    /// 不生成 setter，构建时使用默认值
+    pub skip: bool,
+    /// 可选值，用于生成描述
+    pub values: Vec<String>,
+    /// 可选值的字面量，`validate` 检查取值是否在其中
+    pub value_exprs: Vec<Expr>
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self, Error> {
+        let mut field_attrs = FieldAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("task")) {
+            for meta in parse_metas(attr)? {
+                match &meta {
+                    Meta::NameValue(nv) if nv.path.is_ident("default") => {
+                        field_attrs.default = Some(nv.value.clone());
+                    },
+                    Meta::NameValue(nv) if nv.path.is_ident("range") => match &nv.value {
+                        Expr::Range(range) if range.start.is_some() || range.end.is_some() => {
+                            field_attrs.range = Some(range.clone());
+                        },
+                        value => return Err(Error::new_spanned(value, "range 必须是范围，如 0..=999"))
+                    },
+                    Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                        let rename = lit_str(&nv.value)?;
+                        syn::parse_str::<syn::Ident>(&rename.value())
+                            .map_err(|_| Error::new(rename.span(), "rename 必须是合法的标识符"))?;
+                        field_attrs.rename = Some(rename);
+                    },
+                    Meta::NameValue(nv) if nv.path.is_ident("values") => match &nv.value {
+                        Expr::Array(array) => {
+                            field_attrs.values =
+                                array.elems.iter().map(literal_value).collect::<Result<_, _>>()?;
+                            field_attrs.value_exprs = array.elems.iter().cloned().collect();
+                        },
+                        value => {
+                            return Err(Error::new_spanned(
+                                value,
+                                "values 必须是字面量数组，如 [\"Official\"]"
+                            ))
+                        },
+                    },
+                    Meta::Path(path) if path.is_ident("skip") => field_attrs.skip = true,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            meta,
+                            "字段的 task 属性只支持 default、range、rename、values 和 skip 参数"
+                        ));
+                    }
+                }
+            }
+        }
+        if field_attrs.skip {
+            if let Some(rename) = &field_attrs.rename {
+                return Err(Error::new(
+                    rename.span(),
+                    "skip 的字段不会生成 setter，不能同时使用 rename"
+                ));
+            }
+        }
+        Ok(field_attrs)
+    }
+}
+
+fn parse_metas(attr: &Attribute) -> Result<Punctuated<Meta, Token![,]>, Error> {
+    attr.meta
+        .require_list()?
+        .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+}
+
+fn lit_str(expr: &Expr) -> Result<LitStr, Error> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Ok(lit.clone()),
+        expr => Err(Error::new(expr.span(), "必须是字符串字面量"))
+    }
+}
+
+/// 字面量的值，字符串取内容，数字取原文，支持负数
+pub fn literal_value(expr: &Expr) -> Result<String, Error> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Ok(lit.value()),
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(_) | Lit::Float(_) | Lit::Bool(_),
+            ..
+        }) => Ok(quote::quote!(#expr).to_string()),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr: inner,
+            ..
+        }) if matches!(**inner, Expr::Lit(_)) => Ok(format!("-{}", literal_value(inner)?)),
+        expr => Err(Error::new(expr.span(), "必须是字面量"))
+    }
+}
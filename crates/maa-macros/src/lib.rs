@@ -1,10 +1,22 @@
+mod attrs;
 mod task;
 mod utils;
 
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
-/// 为结构体自动实现 Task trait 和 Builder 模式
+/// 为结构体自动实现 Task trait 和 Builder 模式，并生成描述任务参数的 `DESCRIPTOR`
+///
+/// 结构体上需要 `#[task(name = "...", task_type = "...")]` 属性，字段上可以使用：
+/// - `#[task(default = ...)]` - 未设置时使用的默认值，有默认值的字段在 builder 中不再是必选字段。
+///   反序列化不使用这个默认值，需要省略非 `Option` 字段时另外加上 `#[serde(default)]`
+/// - `#[task(range = 0..=999)]` - `validate` 检查的取值范围
+/// - `#[task(rename = "...")]` - builder 中 setter 的名字
+/// - `#[task(values = ["Official", "Bilibili"])]` - 可选值，`validate` 检查取值是否在其中
+/// - `#[task(skip)]` - 不生成 setter，构建时使用默认值
+///
+/// 字段的类型除了基本类型、`String`、`Vec`、`HashMap` 和 `serde_json::Value` 外，需要实现 `FieldEnum`，
+/// 如 `ClientType`，setter 接受 `impl Into<T>`，没有声明 `values` 时使用枚举的所有取值
 #[proc_macro_derive(GenerateTask, attributes(task))]
 pub fn derive_generate_task(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -1,56 +1,20 @@
 use quote::{format_ident, quote};
-use syn::{Data, DeriveInput, Error, Fields};
+use syn::{Data, DeriveInput, Error, Expr, ExprRange, Fields, RangeLimits, Type};
 
-use crate::utils::{get_doc_attrs, get_inner_type, is_option_type, is_string_type};
+use crate::attrs::{literal_value, FieldAttrs, TaskAttrs};
+use crate::utils::{
+    field_kind, get_doc_attrs, get_doc_string, get_enum_type, get_inner_type, has_serde_default,
+    is_enum_type, is_option_type, is_string_type, type_name
+};
 
 pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
     let name = &input.ident;
     let builder_name = format_ident!("{}Builder", name);
 
-    // 获取 task 属性
-    let task_attr = input
-        .attrs
-        .iter()
-        .find(|attr| attr.path().is_ident("task"))
-        .expect("必须提供 #[task(name = \"...\", task_type = \"...\")] 属性");
-
-    let task_meta = task_attr.meta.require_list().expect("task 属性必须是一个列表");
-
-    let mut task_name = None;
-    let mut task_type = None;
-
-    for nested in task_meta
-        .parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
-        .expect("task 属性格式错误")
-    {
-        match nested {
-            syn::Meta::NameValue(nv) => {
-                if nv.path.is_ident("name") {
-                    if let syn::Expr::Lit(syn::ExprLit {
-                        lit: syn::Lit::Str(lit),
-                        ..
-                    }) = &nv.value
-                    {
-                        task_name = Some(lit.value());
-                    }
-                } else if nv.path.is_ident("task_type") {
-                    if let syn::Expr::Lit(syn::ExprLit {
-                        lit: syn::Lit::Str(lit),
-                        ..
-                    }) = &nv.value
-                    {
-                        task_type = Some(lit.value());
-                    }
-                }
-            },
-            _ => {
-                return Err(Error::new_spanned(nested, "task 属性只支持 name 和 type 参数"));
-            }
-        }
-    }
-
-    let task_name = task_name.expect("必须提供 task name");
-    let task_type = task_type.expect("必须提供 task type");
+    let TaskAttrs {
+        name: task_name,
+        task_type
+    } = TaskAttrs::parse(&input)?;
 
     // 获取结构体的字段
     let fields = match &input.data {
@@ -61,8 +25,14 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
         _ => return Err(Error::new_spanned(&input, "MAATask 只支持结构体"))
     };
 
-    // 生成 builder 字段（与结构体字段相同，但必选字段使用 Option 包装）
-    let builder_fields = fields.iter().map(|f| {
+    // 解析字段上的 task 属性
+    let fields = fields
+        .iter()
+        .map(|f| Ok((f, FieldAttrs::parse(&f.attrs)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // 生成 builder 字段（与结构体字段相同，但必选字段使用 Option 包装），skip 的字段不在 builder 中
+    let builder_fields = fields.iter().filter(|(_, attrs)| !attrs.skip).map(|(f, _)| {
         let name = &f.ident;
         let ty = &f.ty;
         if is_option_type(ty) {
@@ -76,35 +46,20 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
         }
     });
 
-    // 生成 builder 方法
-    let builder_methods = fields.iter().map(|f| {
+    // 生成 builder 方法，方法名可以通过 rename 修改
+    let builder_methods = fields.iter().filter(|(_, attrs)| !attrs.skip).map(|(f, attrs)| {
         let name = &f.ident;
-        let ty = &f.ty;
-        let inner_ty = get_inner_type(ty);
+        let method = match &attrs.rename {
+            Some(rename) => format_ident!("{}", rename.value(), span = rename.span()),
+            None => f.ident.clone().unwrap()
+        };
+        let inner_ty = get_inner_type(&f.ty);
         let doc = get_doc_attrs(&f.attrs);
 
-        if is_option_type(ty) {
-            if is_string_type(&inner_ty) {
-                quote! {
-                    #(#doc)*
-                    pub fn #name(mut self, #name: impl Into<String>) -> Self {
-                        self.#name = Some(#name.into());
-                        self
-                    }
-                }
-            } else {
-                quote! {
-                    #(#doc)*
-                    pub fn #name(mut self, #name: #inner_ty) -> Self {
-                        self.#name = Some(#name);
-                        self
-                    }
-                }
-            }
-        } else if is_string_type(ty) {
+        if is_string_type(&inner_ty) || is_enum_type(&inner_ty) {
             quote! {
                 #(#doc)*
-                pub fn #name(mut self, #name: impl Into<String>) -> Self {
+                pub fn #method(mut self, #name: impl Into<#inner_ty>) -> Self {
                     self.#name = Some(#name.into());
                     self
                 }
@@ -112,7 +67,7 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
         } else {
             quote! {
                 #(#doc)*
-                pub fn #name(mut self, #name: #ty) -> Self {
+                pub fn #method(mut self, #name: #inner_ty) -> Self {
                     self.#name = Some(#name);
                     self
                 }
@@ -120,18 +75,177 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
         }
     });
 
-    // 生成 build 方法中的字段初始化
-    let build_fields = fields.iter().map(|f| {
+    // 必选字段，即没有默认值、没有 skip 的非 Option 字段，try_build 时先取出并检查是否都已设置
+    let required_fields: Vec<_> = fields
+        .iter()
+        .filter(|(f, attrs)| !is_option_type(&f.ty) && !attrs.skip && attrs.default.is_none())
+        .map(|(f, _)| &f.ident)
+        .collect();
+
+    // 生成 try_build 方法中的字段初始化
+    let build_fields = fields.iter().map(|(f, attrs)| {
         let name = &f.ident;
         let ty = &f.ty;
+        // 字符串和枚举字段的默认值可以直接写字符串字面量
+        let inner_ty = get_inner_type(ty);
+        let default = attrs.default.as_ref().map(|default| {
+            if is_string_type(&inner_ty) {
+                quote! { String::from(#default) }
+            } else if is_enum_type(&inner_ty) {
+                quote! { <#inner_ty>::from(#default) }
+            } else {
+                quote! { #default }
+            }
+        });
 
-        if is_option_type(ty) {
+        match (is_option_type(ty), attrs.skip, default) {
+            (true, true, Some(default)) => quote! { #name: Some(#default) },
+            (true, true, None) => quote! { #name: None },
+            (true, false, Some(default)) => quote! { #name: self.#name.or_else(|| Some(#default)) },
+            (true, false, None) => quote! { #name: self.#name.or_else(Default::default) },
+            (false, true, Some(default)) => quote! { #name: #default },
+            (false, true, None) => quote! { #name: Default::default() },
+            (false, false, Some(default)) => quote! { #name: self.#name.unwrap_or_else(|| #default) },
+            (false, false, None) => quote! { #name }
+        }
+    });
+
+    // 没有必选字段时直接构建，否则所有必选字段都已设置才构建，并列出所有缺少的字段
+    let try_build_body = if required_fields.is_empty() {
+        quote! {
+            Ok(#name {
+                #(#build_fields,)*
+            })
+        }
+    } else {
+        quote! {
+            match (#(self.#required_fields,)*) {
+                (#(Some(#required_fields),)*) => Ok(#name {
+                    #(#build_fields,)*
+                }),
+                (#(#required_fields,)*) => {
+                    let mut fields = Vec::new();
+                    #(
+                        if #required_fields.is_none() {
+                            fields.push(stringify!(#required_fields));
+                        }
+                    )*
+                    Err(MissingFields {
+                        task_type: #task_type,
+                        fields
+                    })
+                }
+            }
+        }
+    };
+
+    // 生成 validate 中的范围检查和可选值检查，范围的类型与字段一致，Option 字段只检查已设置的值
+    let checks = fields.iter().filter_map(|(f, attrs)| {
+        let name = &f.ident;
+        let inner_ty = get_inner_type(&f.ty);
+        let range_check = attrs.range.as_ref().map(|range| {
+            let range_ty = range_type(range, &inner_ty);
+            quote! {
+                let range: #range_ty = #range;
+                if !range.contains(value) {
+                    fields.push(InvalidField {
+                        field: stringify!(#name),
+                        message: format!("{value:?} 超出范围 {range:?}")
+                    });
+                }
+            }
+        });
+        let values_check = (!attrs.value_exprs.is_empty()).then(|| {
+            let values = &attrs.value_exprs;
+            // 枚举字段的可选值与默认值一样写字符串字面量
+            let candidates = if is_enum_type(&inner_ty) {
+                quote! { [#(<#inner_ty>::from(#values)),*] }
+            } else {
+                quote! { [#(#values),*] }
+            };
+            quote! {
+                let values = #candidates;
+                if !values.iter().any(|candidate| value == candidate) {
+                    fields.push(InvalidField {
+                        field: stringify!(#name),
+                        message: format!("{value:?} 不是可选值 {values:?} 之一")
+                    });
+                }
+            }
+        });
+        if range_check.is_none() && values_check.is_none() {
+            return None;
+        }
+        let check = quote! {
+            #range_check
+            #values_check
+        };
+        Some(if is_option_type(&f.ty) {
             quote! {
-                #name: self.#name.or_else(Default::default)
+                if let Some(value) = &self.#name {
+                    #check
+                }
             }
         } else {
             quote! {
-                #name: self.#name.expect(concat!("必选字段 ", stringify!(#name), " 未设置"))
+                let value = &self.#name;
+                #check
+            }
+        })
+    });
+
+    // 生成字段的描述，结构体上有 #[serde(default)] 时所有字段都可以省略
+    let serde_default = has_serde_default(&input.attrs);
+    let field_descriptors = fields.iter().map(|(f, attrs)| {
+        let name = f.ident.as_ref().unwrap().to_string();
+        let inner_ty = get_inner_type(&f.ty);
+        let ty = type_name(&inner_ty);
+        let kind = field_kind(&inner_ty);
+        // 与反序列化一致，builder 中的默认值不影响任务参数 JSON 中是否必须包含该字段
+        let required = !is_option_type(&f.ty) && !serde_default && !has_serde_default(&f.attrs);
+        let doc = get_doc_string(&f.attrs);
+        // 没有声明 values 时使用枚举类型的所有取值，枚举可以表示其他取值
+        let (values, open) = match get_enum_type(&inner_ty) {
+            Some(enum_ty) if attrs.values.is_empty() => (quote! { <#enum_ty as FieldEnum>::VALUES }, true),
+            _ => {
+                let values = &attrs.values;
+                (quote! { &[#(#values),*] }, false)
+            }
+        };
+        let default = match &attrs.default {
+            Some(default) => {
+                // 字面量取值，字符串不包含引号，其他表达式使用原文
+                let default = literal_value(default).unwrap_or_else(|_| quote!(#default).to_string());
+                quote! { Some(#default) }
+            },
+            None => quote! { None }
+        };
+        let range = match &attrs.range {
+            Some(range) => {
+                let start = range_bound(range.start.as_deref());
+                let end = range_bound(range.end.as_deref());
+                let inclusive = matches!(range.limits, RangeLimits::Closed(_));
+                quote! {
+                    Some(FieldRange {
+                        start: #start,
+                        end: #end,
+                        inclusive: #inclusive
+                    })
+                }
+            },
+            None => quote! { None }
+        };
+        quote! {
+            FieldDescriptor {
+                name: #name,
+                ty: #ty,
+                kind: #kind,
+                required: #required,
+                doc: #doc,
+                values: #values,
+                open: #open,
+                default: #default,
+                range: #range
             }
         }
     });
@@ -153,6 +267,24 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
             fn from_json(json: &str) -> Result<Self, serde_json::Error> {
                 serde_json::from_str(json)
             }
+
+            fn descriptor(&self) -> &'static TaskDescriptor {
+                &Self::DESCRIPTOR
+            }
+
+            #[allow(unused_mut)]
+            fn validate(&self) -> Result<(), InvalidFields> {
+                let mut fields = Vec::new();
+                #(#checks)*
+                if fields.is_empty() {
+                    Ok(())
+                } else {
+                    Err(InvalidFields {
+                        task_type: #task_type,
+                        fields
+                    })
+                }
+            }
         }
 
         #[derive(Default)]
@@ -161,6 +293,13 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
         }
 
         impl #name {
+            /// 任务参数的描述
+            pub const DESCRIPTOR: TaskDescriptor = TaskDescriptor {
+                task_type: #task_type,
+                name: #task_name,
+                fields: &[#(#field_descriptors),*]
+            };
+
             pub fn new() -> Self {
                 Self::builder().build()
             }
@@ -173,11 +312,38 @@ pub fn generate_task(input: DeriveInput) -> Result<proc_macro2::TokenStream, Err
         impl #builder_name {
             #(#builder_methods)*
 
+            /// 构建任务，缺少必选字段时返回 [`MissingFields`]，列出所有未设置的必选字段
+            pub fn try_build(self) -> Result<#name, MissingFields> {
+                #try_build_body
+            }
+
+            /// 构建任务
+            ///
+            /// # Panics
+            ///
+            /// 缺少必选字段时 panic，需要处理错误时使用 `try_build`
             pub fn build(self) -> #name {
-                #name {
-                    #(#build_fields,)*
-                }
+                self.try_build().unwrap_or_else(|err| panic!("{err}"))
             }
         }
     })
 }
+
+/// 根据范围的形式和字段类型得到范围的类型，如 `0..=999` 对应 `RangeInclusive<i32>`
+fn range_type(range: &ExprRange, ty: &Type) -> proc_macro2::TokenStream {
+    match (&range.start, &range.limits, &range.end) {
+        (Some(_), RangeLimits::HalfOpen(_), Some(_)) => quote! { ::core::ops::Range<#ty> },
+        (Some(_), RangeLimits::Closed(_), Some(_)) => quote! { ::core::ops::RangeInclusive<#ty> },
+        (Some(_), _, None) => quote! { ::core::ops::RangeFrom<#ty> },
+        (None, RangeLimits::HalfOpen(_), _) => quote! { ::core::ops::RangeTo<#ty> },
+        (None, RangeLimits::Closed(_), _) => quote! { ::core::ops::RangeToInclusive<#ty> }
+    }
+}
+
+/// 范围的边界转换为 `Option<f64>`
+fn range_bound(bound: Option<&Expr>) -> proc_macro2::TokenStream {
+    match bound {
+        Some(bound) => quote! { Some((#bound) as f64) },
+        None => quote! { None }
+    }
+}
@@ -1,4 +1,6 @@
-use syn::{Attribute, Type};
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, ExprLit, Lit, Meta, Token, Type};
 
 /// 检查类型是否为 Option<T>
 pub fn is_option_type(ty: &Type) -> bool {
@@ -43,3 +45,107 @@ pub fn get_doc_attrs(attrs: &[Attribute]) -> Vec<syn::Attribute> {
         .cloned()
         .collect()
 }
+
+/// 获取类型最后一段的名字和泛型参数中的类型，如 `Vec<String>` 得到 `Vec` 和 `[String]`
+fn type_segment(ty: &Type) -> Option<(String, Vec<&Type>)> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    let args = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None
+            })
+            .collect(),
+        _ => Vec::new()
+    };
+    Some((segment.ident.to_string(), args))
+}
+
+/// 生成类型对应的 `FieldKind`，其他类型使用 `FieldEnum::KIND`
+pub fn field_kind(ty: &Type) -> proc_macro2::TokenStream {
+    let Some((ident, args)) = type_segment(ty) else {
+        return quote! { FieldKind::Any };
+    };
+    match (ident.as_str(), args.as_slice()) {
+        ("bool", _) => quote! { FieldKind::Boolean },
+        ("i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize", _) => {
+            quote! { FieldKind::Integer }
+        },
+        ("f32" | "f64", _) => quote! { FieldKind::Number },
+        ("String", _) => quote! { FieldKind::String },
+        ("Vec", [item]) => {
+            let item = field_kind(item);
+            quote! { FieldKind::Array(&#item) }
+        },
+        ("HashMap" | "BTreeMap", [_, value]) => {
+            let value = field_kind(value);
+            quote! { FieldKind::Map(&#value) }
+        },
+        ("Value", _) => quote! { FieldKind::Any },
+        _ => quote! { <#ty as FieldEnum>::KIND }
+    }
+}
+
+/// 获取类型中的枚举类型，即 `field_kind` 中使用 `FieldEnum` 的类型，如 `Vec<Facility>` 得到 `Facility`
+pub fn get_enum_type(ty: &Type) -> Option<&Type> {
+    let (ident, args) = type_segment(ty)?;
+    match (ident.as_str(), args.as_slice()) {
+        ("bool" | "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize", _) => None,
+        ("f32" | "f64" | "String" | "Value", _) => None,
+        ("Vec", [item]) => get_enum_type(item),
+        ("HashMap" | "BTreeMap", [_, value]) => get_enum_type(value),
+        _ => Some(ty)
+    }
+}
+
+/// 检查类型是否为枚举类型，枚举字段的 setter 接受 `impl Into<T>`
+pub fn is_enum_type(ty: &Type) -> bool {
+    get_enum_type(ty).is_some_and(|enum_ty| std::ptr::eq(enum_ty, ty))
+}
+
+/// 类型的文本形式，如 `HashMap<String, i32>`
+pub fn type_name(ty: &Type) -> String {
+    quote!(#ty).to_string().replace(' ', "").replace(',', ", ")
+}
+
+/// 文档注释的文本，每行去掉开头的一个空格
+pub fn get_doc_string(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) => Some(lit.value()),
+                _ => None
+            },
+            _ => None
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// 是否有 `#[serde(default)]`、`#[serde(skip)]` 等属性，反序列化时可以省略字段
+pub fn has_serde_default(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .any(|meta| {
+            let path = meta.path();
+            path.is_ident("default") || path.is_ident("skip") || path.is_ident("skip_deserializing")
+        })
+}
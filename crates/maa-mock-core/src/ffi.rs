@@ -0,0 +1,323 @@
+//! 导出与 `AsstCaller.h` 一致的 C ABI
+
+#![allow(non_snake_case)]
+
+use std::ffi::{c_char, c_void, CStr};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex, MutexGuard, OnceLock, PoisonError};
+
+use serde_json::{json, Value};
+
+use crate::instance::{AsstApiCallback, Instance};
+use crate::script::Script;
+
+type AsstBool = u8;
+type AsstSize = u64;
+type AsstHandle = *mut c_void;
+
+const NULL_SIZE: AsstSize = AsstSize::MAX;
+
+/// 当前的资源目录和脚本，由 `AsstLoadResource` 设置
+struct Resource {
+    dir: Option<PathBuf>,
+    script: Script
+}
+
+static RESOURCE: LazyLock<Mutex<Resource>> = LazyLock::new(|| {
+    Mutex::new(Resource {
+        dir: None,
+        script: Script::default()
+    })
+});
+
+fn resource() -> MutexGuard<'static, Resource> {
+    RESOURCE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn script() -> Script {
+    resource().script.clone()
+}
+
+fn fails(function: &str) -> bool {
+    resource().script.fails(function)
+}
+
+fn to_bool(value: bool) -> AsstBool {
+    value as AsstBool
+}
+
+unsafe fn to_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        None
+    } else {
+        CStr::from_ptr(ptr).to_str().ok()
+    }
+}
+
+unsafe fn instance<'a>(handle: AsstHandle) -> Option<&'a Instance> {
+    (handle as *const Instance).as_ref()
+}
+
+/// 把数据复制到调用方提供的缓冲区，缓冲区不足时返回 `NULL_SIZE`
+unsafe fn copy_to(buff: *mut u8, buff_size: AsstSize, data: &[u8]) -> AsstSize {
+    if buff.is_null() || (data.len() as AsstSize) > buff_size {
+        return NULL_SIZE;
+    }
+    std::ptr::copy_nonoverlapping(data.as_ptr(), buff, data.len());
+    data.len() as AsstSize
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstSetUserDir(path: *const c_char) -> AsstBool {
+    to_bool(!fails("AsstSetUserDir") && to_str(path).is_some())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstLoadResource(path: *const c_char) -> AsstBool {
+    if fails("AsstLoadResource") {
+        return 0;
+    }
+    let Some(dir) = to_str(path).map(PathBuf::from) else {
+        return 0;
+    };
+    // 脚本格式错误时加载失败，方便在测试中发现问题
+    let Ok(script) = Script::load(&dir) else {
+        return 0;
+    };
+
+    let mut resource = resource();
+    resource.dir = Some(dir);
+    resource.script = script;
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstSetStaticOption(_key: i32, value: *const c_char) -> AsstBool {
+    to_bool(!fails("AsstSetStaticOption") && to_str(value).is_some())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstCreate() -> AsstHandle {
+    AsstCreateEx(None, std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstCreateEx(callback: AsstApiCallback, custom_arg: *mut c_void) -> AsstHandle {
+    if fails("AsstCreateEx") {
+        return std::ptr::null_mut();
+    }
+    Box::into_raw(Box::new(Instance::new(callback, custom_arg))) as AsstHandle
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstDestroy(handle: AsstHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle as *mut Instance));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstSetInstanceOption(
+    handle: AsstHandle,
+    _key: i32,
+    value: *const c_char
+) -> AsstBool {
+    to_bool(instance(handle).is_some() && !fails("AsstSetInstanceOption") && to_str(value).is_some())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstAppendTask(
+    handle: AsstHandle,
+    type_: *const c_char,
+    params: *const c_char
+) -> i32 {
+    let (Some(instance), Some(task_type)) = (instance(handle), to_str(type_)) else {
+        return 0;
+    };
+    let Some(params) = to_str(params).and_then(|params| serde_json::from_str::<Value>(params).ok()) else {
+        return 0;
+    };
+    if fails("AsstAppendTask") {
+        return 0;
+    }
+    instance.append_task(task_type.to_string(), params)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstSetTaskParams(handle: AsstHandle, id: i32, params: *const c_char) -> AsstBool {
+    let Some(instance) = instance(handle) else {
+        return 0;
+    };
+    let Some(params) = to_str(params).and_then(|params| serde_json::from_str::<Value>(params).ok()) else {
+        return 0;
+    };
+    to_bool(!fails("AsstSetTaskParams") && instance.set_task_params(id, params))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstStart(handle: AsstHandle) -> AsstBool {
+    match instance(handle) {
+        Some(instance) if !fails("AsstStart") => to_bool(instance.start(script())),
+        _ => 0
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstStop(handle: AsstHandle) -> AsstBool {
+    match instance(handle) {
+        Some(instance) if !fails("AsstStop") => {
+            instance.stop();
+            1
+        },
+        _ => 0
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstRunning(handle: AsstHandle) -> AsstBool {
+    to_bool(instance(handle).is_some_and(Instance::running))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstConnected(handle: AsstHandle) -> AsstBool {
+    to_bool(
+        instance(handle).is_some_and(|instance| instance.connected.load(std::sync::atomic::Ordering::SeqCst))
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstBackToHome(handle: AsstHandle) -> AsstBool {
+    to_bool(instance(handle).is_some() && !fails("AsstBackToHome"))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstAsyncConnect(
+    handle: AsstHandle,
+    adb_path: *const c_char,
+    address: *const c_char,
+    config: *const c_char,
+    block: AsstBool
+) -> i32 {
+    let Some(instance) = instance(handle) else {
+        return 0;
+    };
+    if fails("AsstAsyncConnect") {
+        return 0;
+    }
+    let (Some(adb_path), Some(address)) = (to_str(adb_path), to_str(address)) else {
+        return 0;
+    };
+    let target = json!({ "adb": adb_path, "address": address, "config": to_str(config).unwrap_or_default() });
+    instance.connect(script(), target, block != 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstSetConnectionExtras(_name: *const c_char, _extras: *const c_char) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstAsyncClick(handle: AsstHandle, _x: i32, _y: i32, block: AsstBool) -> i32 {
+    let Some(instance) = instance(handle) else {
+        return 0;
+    };
+    if fails("AsstAsyncClick") {
+        return 0;
+    }
+    instance.async_call("Click", true, block != 0, script().uuid, Vec::new())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstAsyncScreencap(handle: AsstHandle, block: AsstBool) -> i32 {
+    let Some(instance) = instance(handle) else {
+        return 0;
+    };
+    if fails("AsstAsyncScreencap") {
+        return 0;
+    }
+    let script = script();
+    let ret = script.image.is_some();
+    instance.async_call("Screencap", ret, block != 0, script.uuid, Vec::new())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstGetImage(
+    handle: AsstHandle,
+    buff: *mut c_void,
+    buff_size: AsstSize
+) -> AsstSize {
+    if instance(handle).is_none() || fails("AsstGetImage") {
+        return NULL_SIZE;
+    }
+    let path = {
+        let resource = resource();
+        match (&resource.dir, &resource.script.image) {
+            (Some(dir), Some(image)) => dir.join(image),
+            _ => return NULL_SIZE
+        }
+    };
+    match fs::read(path) {
+        Ok(image) => copy_to(buff as *mut u8, buff_size, &image),
+        Err(_) => NULL_SIZE
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstGetUUID(handle: AsstHandle, buff: *mut c_char, buff_size: AsstSize) -> AsstSize {
+    if instance(handle).is_none() || fails("AsstGetUUID") {
+        return NULL_SIZE;
+    }
+    copy_to(buff as *mut u8, buff_size, script().uuid.as_bytes())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn AsstGetTasksList(
+    handle: AsstHandle,
+    buff: *mut i32,
+    buff_size: AsstSize
+) -> AsstSize {
+    let Some(instance) = instance(handle) else {
+        return NULL_SIZE;
+    };
+    if buff.is_null() || fails("AsstGetTasksList") {
+        return NULL_SIZE;
+    }
+    let ids = instance.task_ids();
+    if ids.len() as AsstSize > buff_size {
+        return NULL_SIZE;
+    }
+    std::ptr::copy_nonoverlapping(ids.as_ptr(), buff, ids.len());
+    ids.len() as AsstSize
+}
+
+#[no_mangle]
+pub extern "C" fn AsstGetNullSize() -> AsstSize {
+    NULL_SIZE
+}
+
+#[no_mangle]
+pub extern "C" fn AsstGetVersion() -> *const c_char {
+    // 返回的指针需要一直有效，所以版本号只在第一次调用时读取
+    static VERSION: OnceLock<std::ffi::CString> = OnceLock::new();
+    VERSION
+        .get_or_init(|| std::ffi::CString::new(script().version).unwrap_or_default())
+        .as_ptr()
+}
+
+/// 日志会追加到资源目录下的 `mock.log` 中，方便在测试中检查
+#[no_mangle]
+pub unsafe extern "C" fn AsstLog(level: *const c_char, message: *const c_char) {
+    let (Some(level), Some(message)) = (to_str(level), to_str(message)) else {
+        return;
+    };
+    let Some(dir) = resource().dir.clone() else {
+        return;
+    };
+    if let Ok(mut file) = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("mock.log"))
+    {
+        let _ = writeln!(file, "[{level}] {message}");
+    }
+}
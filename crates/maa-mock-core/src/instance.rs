@@ -0,0 +1,274 @@
+use std::ffi::{c_char, c_void, CString};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+
+use serde_json::{json, Value};
+
+use crate::script::{Callback, Script};
+
+/// MaaCore 的回调函数类型
+pub(crate) type AsstApiCallback = Option<unsafe extern "C" fn(i32, *const c_char, *mut c_void)>;
+
+type Job = Box<dyn FnOnce(&Sink) + Send>;
+
+/// 在工作线程上调用回调函数，和 MaaCore 一样所有回调都来自同一个线程
+pub(crate) struct Sink {
+    callback: AsstApiCallback,
+    custom_arg: *mut c_void
+}
+
+// 回调函数和参数只会在工作线程上使用
+unsafe impl Send for Sink {}
+
+impl Sink {
+    fn emit(&self, msg: i32, details: &str) {
+        let Some(callback) = self.callback else {
+            return;
+        };
+        // 脚本中的 details 不会包含 NUL，包含时截断即可
+        let details = CString::new(details)
+            .unwrap_or_else(|err| CString::new(&details[..err.nul_position()]).unwrap_or_default());
+        unsafe { callback(msg, details.as_ptr(), self.custom_arg) };
+    }
+
+    fn emit_json(&self, msg: i32, details: Value) {
+        self.emit(msg, &details.to_string());
+    }
+
+    fn emit_callback(&self, callback: &Callback, fill: impl FnOnce(&mut Value)) {
+        match &callback.raw {
+            Some(raw) => self.emit(callback.msg, raw),
+            None => {
+                let mut details = callback.details.clone();
+                fill(&mut details);
+                self.emit_json(callback.msg, details);
+            }
+        }
+    }
+}
+
+/// 已添加的任务
+pub(crate) struct Task {
+    pub(crate) id: i32,
+    pub(crate) task_type: String,
+    pub(crate) params: Value
+}
+
+/// 模拟的 MaaCore 实例，`AsstHandle` 指向的就是这个结构体
+pub(crate) struct Instance {
+    jobs: Mutex<Option<mpsc::Sender<Job>>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    tasks: Arc<Mutex<Vec<Task>>>,
+    next_task_id: AtomicI32,
+    next_call_id: AtomicI32,
+    pub(crate) connected: AtomicBool,
+    running: Arc<AtomicBool>,
+    stopping: Arc<AtomicBool>
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn fill_task(details: &mut Value, task: &Task, uuid: &str) {
+    if let Value::Object(map) = details {
+        map.entry("taskchain").or_insert_with(|| json!(task.task_type));
+        map.entry("taskid").or_insert_with(|| json!(task.id));
+        map.entry("uuid").or_insert_with(|| json!(uuid));
+    }
+}
+
+impl Instance {
+    pub(crate) fn new(callback: AsstApiCallback, custom_arg: *mut c_void) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let sink = Sink { callback, custom_arg };
+        let worker = std::thread::spawn(move || {
+            for job in receiver {
+                job(&sink);
+            }
+        });
+
+        Self {
+            jobs: Mutex::new(Some(sender)),
+            worker: Mutex::new(Some(worker)),
+            tasks: Arc::default(),
+            next_task_id: AtomicI32::new(1),
+            next_call_id: AtomicI32::new(1),
+            connected: AtomicBool::new(false),
+            running: Arc::default(),
+            stopping: Arc::default()
+        }
+    }
+
+    /// 把任务交给工作线程，`block` 为 true 时等待任务执行完
+    fn submit(&self, block: bool, job: impl FnOnce(&Sink) + Send + 'static) {
+        let Some(jobs) = lock(&self.jobs).clone() else {
+            return;
+        };
+        if !block {
+            let _ = jobs.send(Box::new(job));
+            return;
+        }
+
+        let (done, wait) = mpsc::channel();
+        let sent = jobs.send(Box::new(move |sink: &Sink| {
+            job(sink);
+            let _ = done.send(());
+        }));
+        if sent.is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    pub(crate) fn append_task(&self, task_type: String, params: Value) -> i32 {
+        let id = self.next_task_id.fetch_add(1, Ordering::SeqCst);
+        lock(&self.tasks).push(Task {
+            id,
+            task_type,
+            params
+        });
+        id
+    }
+
+    pub(crate) fn set_task_params(&self, id: i32, params: Value) -> bool {
+        match lock(&self.tasks).iter_mut().find(|task| task.id == id) {
+            Some(task) => {
+                task.params = params;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub(crate) fn task_ids(&self) -> Vec<i32> {
+        lock(&self.tasks).iter().map(|task| task.id).collect()
+    }
+
+    pub(crate) fn running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// 按脚本依次执行任务队列中的任务
+    pub(crate) fn start(&self, script: Script) -> bool {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.stopping.store(false, Ordering::SeqCst);
+
+        let tasks = self.tasks.clone();
+        let running = self.running.clone();
+        let stopping = self.stopping.clone();
+        self.submit(false, move |sink| {
+            let queue: Vec<(i32, String, Value)> = lock(&tasks)
+                .iter()
+                .map(|task| (task.id, task.task_type.clone(), task.params.clone()))
+                .collect();
+
+            let mut finished = Vec::new();
+            let mut last_chain = String::new();
+            for (id, task_type, params) in queue {
+                // 和 MaaCore 一样，未启用的任务不会产生任何消息
+                if params.get("enable") == Some(&Value::Bool(false)) {
+                    continue;
+                }
+                let task = Task {
+                    id,
+                    task_type,
+                    params
+                };
+                let mut chain = json!({});
+                fill_task(&mut chain, &task, &script.uuid);
+
+                if stopping.load(Ordering::SeqCst) {
+                    sink.emit_json(10004, chain);
+                    break;
+                }
+
+                sink.emit_json(10001, chain.clone());
+                let task_script = script.tasks.get(&task.task_type).cloned().unwrap_or_default();
+                for callback in &task_script.events {
+                    sink.emit_callback(callback, |details| fill_task(details, &task, &script.uuid));
+                }
+                sink.emit_json(task_script.result.msg(), chain);
+
+                finished.push(task.id);
+                last_chain = task.task_type;
+            }
+
+            lock(&tasks).retain(|task| !finished.contains(&task.id));
+            if !stopping.load(Ordering::SeqCst) {
+                sink.emit_json(
+                    3,
+                    json!({ "taskchain": last_chain, "uuid": script.uuid, "finished_tasks": finished })
+                );
+            }
+            running.store(false, Ordering::SeqCst);
+        });
+        true
+    }
+
+    pub(crate) fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        lock(&self.tasks).clear();
+    }
+
+    /// 发出连接相关的回调以及异步调用结果，返回异步调用 ID
+    ///
+    /// 连接失败时和 MaaCore 一样，在异步调用结果之前发出 `ConnectFailed`
+    pub(crate) fn connect(&self, script: Script, target: Value, block: bool) -> i32 {
+        let ret = !script.disconnected;
+        self.connected.store(ret, Ordering::SeqCst);
+        let mut before = script.connect;
+        if !ret {
+            before.push(Callback::new(
+                2,
+                json!({ "what": "ConnectFailed", "why": "Connection command failed to exec", "details": target })
+            ));
+        }
+        self.async_call("Connect", ret, block, script.uuid, before)
+    }
+
+    /// 发出异步调用结果，返回异步调用 ID
+    pub(crate) fn async_call(
+        &self,
+        what: &'static str,
+        ret: bool,
+        block: bool,
+        uuid: String,
+        before: Vec<Callback>
+    ) -> i32 {
+        let async_call_id = self.next_call_id.fetch_add(1, Ordering::SeqCst);
+        self.submit(block, move |sink| {
+            for callback in &before {
+                sink.emit_callback(callback, |details| {
+                    if let Value::Object(map) = details {
+                        map.entry("uuid").or_insert_with(|| json!(uuid));
+                    }
+                });
+            }
+            sink.emit_json(
+                4,
+                json!({
+                    "uuid": uuid,
+                    "what": what,
+                    "async_call_id": async_call_id,
+                    "details": { "ret": ret, "cost": 0 }
+                })
+            );
+        });
+        async_call_id
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // 和 MaaCore 一样，在返回之前发出 Destroyed 并等待工作线程退出
+        self.submit(false, |sink| sink.emit(5, "{}"));
+        lock(&self.jobs).take();
+        if let Some(worker) = lock(&self.worker).take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! 用于测试的 MaaCore 模拟运行库
+//!
+//! 编译为 cdylib，导出和 `AsstCaller.h` 一致的 C ABI，可以直接通过 `maa_sys::Assistant::load_library`
+//! 加载，不需要真实的 MaaCore 和设备。
+//!
+//! 模拟运行库的行为由资源目录下的 [`SCRIPT_FILE`] 控制，可以：
+//!
+//! - 在连接和执行任务时发出预设的回调序列
+//! - 让指定的函数调用失败
+//! - 通过 `AsstGetImage` 返回预设的图片
+//!
+//! 运行库的全局状态（资源目录、脚本）在同一个进程中是共享的，
+//! 测试时应该通过 [`install`] 把运行库复制到各自的目录中，每份副本会被单独加载，互不影响。
+//!
+//! # Example
+//! ```rust,ignore
+//! let dir = tempfile::tempdir()?;
+//! maa_mock_core::install(dir.path(), &Script::new().fail("AsstStart"))?;
+//!
+//! let mut assistant = Assistant::init(dir.path())?;
+//! assert!(assistant.start().is_err());
+//! ```
+
+mod ffi;
+mod instance;
+mod script;
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+pub use script::*;
+
+/// 模拟运行库文件的路径
+///
+/// 作为依赖构建时，cdylib 会和测试可执行文件一起生成在 `target/<profile>/deps` 目录下
+pub fn library_path() -> io::Result<PathBuf> {
+    let name = format!("{DLL_PREFIX}maa_mock_core{DLL_SUFFIX}");
+    let exe = env::current_exe()?;
+    exe.ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.exists())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("找不到模拟运行库 {name}")))
+}
+
+/// 把模拟运行库以 MaaCore 的文件名复制到指定目录，并写入脚本
+///
+/// 安装后的目录可以同时作为 library 和 resource 路径使用
+pub fn install(dir: impl AsRef<Path>, script: &Script) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    fs::copy(
+        library_path()?,
+        dir.join(format!("{DLL_PREFIX}MaaCore{DLL_SUFFIX}"))
+    )?;
+    script.write(dir)
+}
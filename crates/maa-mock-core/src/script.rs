@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// 脚本文件名，放在资源目录下，在 `AsstLoadResource` 时读取
+pub const SCRIPT_FILE: &str = "mock.json";
+
+/// 模拟运行库的行为脚本
+///
+/// 所有字段都可以省略，省略时模拟运行库的行为和一个连接正常、任务全部成功的 MaaCore 一致。
+///
+/// # Example
+/// ```json
+/// {
+///     "version": "v5.16.10",
+///     "fail": ["AsstStart"],
+///     "connect": [{ "msg": 2, "details": { "what": "Connected", "why": "", "details": {} } }],
+///     "tasks": {
+///         "Fight": { "events": [{ "msg": 20003, "details": { "what": "PenguinId", "details": { "id": "1" } } }] }
+///     },
+///     "image": "screen.png"
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Script {
+    /// `AsstGetVersion` 返回的版本号
+    pub version: String,
+    /// `AsstGetUUID` 返回的 UUID，同时会填入回调消息中
+    pub uuid: String,
+    /// 调用时直接返回失败的函数名，如 `AsstStart`
+    pub fail: Vec<String>,
+    /// 模拟连接失败，`AsstAsyncConnect` 会发出 `ConnectFailed`，异步调用结果为失败，`AsstConnected` 返回 false
+    pub disconnected: bool,
+    /// `AsstAsyncConnect` 时、异步调用结果之前发出的回调
+    pub connect: Vec<Callback>,
+    /// 按任务类型设置 `AsstStart` 之后发出的回调，没有设置的任务类型直接完成
+    pub tasks: HashMap<String, TaskScript>,
+    /// `AsstGetImage` 返回的图片文件，相对路径基于资源目录
+    pub image: Option<PathBuf>
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Self {
+            version: "v0.0.0-mock".to_string(),
+            uuid: "mock-uuid".to_string(),
+            fail: Vec::new(),
+            disconnected: false,
+            connect: Vec::new(),
+            tasks: HashMap::new(),
+            image: None
+        }
+    }
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从资源目录读取脚本，脚本文件不存在时使用默认脚本
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read(dir.as_ref().join(SCRIPT_FILE)) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// 将脚本写入资源目录
+    pub fn write(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(dir.as_ref().join(SCRIPT_FILE), serde_json::to_vec_pretty(self)?)
+    }
+
+    /// 设置版本号
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// 设置 UUID
+    pub fn uuid(mut self, uuid: impl Into<String>) -> Self {
+        self.uuid = uuid.into();
+        self
+    }
+
+    /// 让指定的函数返回失败
+    pub fn fail(mut self, function: impl Into<String>) -> Self {
+        self.fail.push(function.into());
+        self
+    }
+
+    /// 模拟连接失败
+    pub fn disconnected(mut self) -> Self {
+        self.disconnected = true;
+        self
+    }
+
+    /// 添加连接时发出的回调
+    pub fn on_connect(mut self, callback: Callback) -> Self {
+        self.connect.push(callback);
+        self
+    }
+
+    /// 设置指定类型任务的脚本
+    pub fn task(mut self, task_type: impl Into<String>, task: TaskScript) -> Self {
+        self.tasks.insert(task_type.into(), task);
+        self
+    }
+
+    /// 设置 `AsstGetImage` 返回的图片文件
+    pub fn image(mut self, path: impl Into<PathBuf>) -> Self {
+        self.image = Some(path.into());
+        self
+    }
+
+    /// 指定的函数是否应该返回失败
+    pub fn fails(&self, function: &str) -> bool {
+        self.fail.iter().any(|f| f == function)
+    }
+}
+
+/// 单个任务的脚本
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskScript {
+    /// 任务链开始之后发出的回调，`details` 中缺少的 `taskchain`、`taskid`、`uuid` 会被自动补全
+    pub events: Vec<Callback>,
+    /// 任务链的最终结果
+    pub result: TaskResult
+}
+
+impl TaskScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加任务执行期间发出的回调
+    pub fn event(mut self, callback: Callback) -> Self {
+        self.events.push(callback);
+        self
+    }
+
+    /// 设置任务链的最终结果
+    pub fn result(mut self, result: TaskResult) -> Self {
+        self.result = result;
+        self
+    }
+}
+
+/// 任务链的最终结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskResult {
+    /// 发出 `TaskChainCompleted`
+    #[default]
+    Completed,
+    /// 发出 `TaskChainError`
+    Error,
+    /// 发出 `TaskChainStopped`
+    Stopped
+}
+
+impl TaskResult {
+    /// 对应的消息 ID
+    pub fn msg(self) -> i32 {
+        match self {
+            TaskResult::Completed => 10002,
+            TaskResult::Error => 10000,
+            TaskResult::Stopped => 10004
+        }
+    }
+}
+
+/// 一条回调消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Callback {
+    /// 消息 ID
+    pub msg: i32,
+    /// 消息详情
+    #[serde(default = "empty_details")]
+    pub details: Value,
+    /// 原样发出的 details 字符串，用于模拟无法解析的消息，优先于 `details`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>
+}
+
+fn empty_details() -> Value {
+    json!({})
+}
+
+impl Callback {
+    pub fn new(msg: i32, details: Value) -> Self {
+        Self {
+            msg,
+            details,
+            raw: None
+        }
+    }
+
+    /// 原样发出 details 字符串的回调
+    pub fn raw(msg: i32, raw: impl Into<String>) -> Self {
+        Self {
+            msg,
+            details: empty_details(),
+            raw: Some(raw.into())
+        }
+    }
+}
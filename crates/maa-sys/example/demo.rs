@@ -1,7 +1,9 @@
 use std::env;
+use std::time::Duration;
 
+use maa_sys::discovery::Discovery;
 use maa_sys::task::{FightTask, StartUpTask};
-use maa_sys::{Assistant, Connection, InstanceOptionKey};
+use maa_sys::{Assistant, InstanceOption, TouchMode};
 
 fn pause() {
     println!("按任意键继续...");
@@ -14,14 +16,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut assistant = Assistant::init(resource_path)?;
 
     // 未 Root 的设备使用 adb 模式
-    assistant.set_instance_option(InstanceOptionKey::TouchMode, "adb")?;
-    assistant.connect(Connection::adb("adb", "192.168.20.29:40351"), None)?;
-
-    if !assistant.is_connected() {
-        println!("connect failed");
-        drop(assistant);
+    assistant.set_option(InstanceOption::TouchMode(TouchMode::Adb))?;
+    // 使用找到的第一个可用设备，adb 执行失败时仍然可以使用探测到的端口
+    let discovered = Discovery::new().discover();
+    if let Some(err) = &discovered.adb_error {
+        println!("adb failed: {err}");
+    }
+    let Some(device) = discovered
+        .devices
+        .into_iter()
+        .find(|device| device.is_available())
+    else {
+        println!("no device found");
         pause();
         return Ok(());
+    };
+    match assistant.connect_and_wait(device.connection, None, Duration::from_secs(60)) {
+        Ok(report) => println!("connected: {:?} {:?}", report.uuid, report.resolution),
+        Err(err) => {
+            println!("connect failed: {err}");
+            drop(assistant);
+            pause();
+            return Ok(());
+        }
     }
 
     assistant.append_task(
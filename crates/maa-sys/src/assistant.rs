@@ -1,27 +1,29 @@
-use std::env::consts::OS;
-use std::ffi::{CStr, CString};
-use std::path::Path;
-use std::ptr::NonNull;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use hashbrown::HashMap;
 
-use crate::protocol::{message, task};
+use crate::profile::Profile;
+use crate::protocol::message;
+use crate::protocol::task::{self, Task};
+use crate::resource::{ItemIndex, StageCatalog};
+use crate::tracker::{
+    AsyncCall, AsyncCallTracker, ConnectionOutcome, ConnectionReport, ConnectionTracker, TaskHandle,
+    TaskTracker
+};
 use crate::types::*;
-use crate::{binding, Connection};
-
-// 一张 720p 图像，24位色深，原始大小为 1280 * 720 * 3（2.7 MB）
-// 压缩后的图像数据应小于原始大小。
-// 在大多数情况下，4MB 应该足够
-const INIT_SIZE: usize = 1024 * 1024 * 4;
-// 32MB 应该足够用于 4K 原始图像，但实际使用中可能不需要这么大
-const MAX_SIZE: usize = 1024 * 1024 * 32;
+use crate::{Connection, ConnectionConfig, CoreApi, InstanceHandle, MaaCoreLibrary};
 
 /// Assistant 构建器，用于分离 library 和 resource 的加载
 pub struct AssistantBuilder {
     library_path: Option<String>,
     resource_path: Option<String>,
-    callback: Option<Box<dyn FnMut(message::Message, serde_json::Value) + Send + 'static>>,
+    processor: Option<message::Processor>,
+    listeners: Vec<message::Listener>,
+    core: Option<Arc<dyn CoreApi>>,
+    #[cfg(feature = "tokio")]
+    event_capacity: usize
 }
 
 impl AssistantBuilder {
@@ -30,7 +32,11 @@ impl AssistantBuilder {
         Self {
             library_path: None,
             resource_path: None,
-            callback: None,
+            processor: None,
+            listeners: Vec::new(),
+            core: None,
+            #[cfg(feature = "tokio")]
+            event_capacity: crate::stream::DEFAULT_EVENT_CAPACITY
         }
     }
 
@@ -46,75 +52,172 @@ impl AssistantBuilder {
         self
     }
 
+    /// 使用自定义的 MaaCore 实现，设置后不再从 library 路径加载运行库
+    ///
+    /// resource 路径可选，设置时仍会通过 [`CoreApi::load_resource`] 加载
+    pub fn with_core(mut self, core: Arc<dyn CoreApi>) -> Self {
+        self.core = Some(core);
+        self
+    }
+
     /// 设置回调函数
+    ///
+    /// 回调函数中的 panic 会被捕获并写入 MaaCore 的日志，监听器会收到 [`message::Event::CallbackPanicked`]
     pub fn with_callback<F>(mut self, callback: F) -> Self
     where
         F: FnMut(message::Message, serde_json::Value) + Send + 'static
     {
-        self.callback = Some(Box::new(callback));
+        self.processor = Some(message::Processor::from(callback));
+        self
+    }
+
+    /// 设置事件回调函数，回调收到的是解析后的 [`message::Event`]
+    ///
+    /// 与 [`AssistantBuilder::with_callback`] 只能二选一，后设置的会覆盖先设置的
+    pub fn with_event_callback<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(message::Event) + Send + 'static
+    {
+        self.processor = Some(message::Processor::from_event(callback));
+        self
+    }
+
+    /// 添加事件监听器，如 [`crate::report::FightReporter::listener`]
+    ///
+    /// 可以添加多个，与回调函数互不影响。监听器先于回调函数和 [`TaskHandle`] 收到事件，
+    /// 因此任务结束后等待 [`TaskHandle`] 返回时，监听器已经处理过任务链结束的事件。
+    /// 监听器中的 panic 会被捕获并写入 MaaCore 的日志
+    pub fn with_listener<F>(mut self, listener: F) -> Self
+    where
+        F: FnMut(&message::Event) + Send + 'static
+    {
+        self.listeners.push(Box::new(listener));
+        self
+    }
+
+    /// 设置事件流每个订阅者的缓冲区大小，默认为 [`crate::DEFAULT_EVENT_CAPACITY`]
+    ///
+    /// 订阅者消费过慢时最旧的事件会被跳过，详见 [`crate::EventStream`]。
+    /// 缓冲区至少需要容纳一个事件，`capacity` 为 0 时按 1 处理
+    #[cfg(feature = "tokio")]
+    pub fn with_event_stream(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity.max(1);
         self
     }
 
     /// 初始化 Assistant 实例
     pub fn init(self) -> Result<Assistant, Error> {
-        let library_path = self.library_path.ok_or(Error::LibraryLoadFailed)?;
-        let resource_path = self.resource_path.ok_or(Error::ResourceLoadFailed)?;
-
-        // 加载 library
-        let core = Assistant::load_library(&library_path)?;
-        
-        // 加载 resource
-        Assistant::load_resource(&resource_path, &core)?;
-
-        // 创建 Assistant 实例
-        let handle = if let Some(callback) = self.callback {
-            let processor = message::Processor::from(callback);
-            let processor_ptr = Box::into_raw(Box::new(processor));
-            unsafe { core.AsstCreateEx(Some(callback_wrapper), processor_ptr as *mut _) }
-        } else {
-            unsafe { core.AsstCreate() }
+        let core = match self.core {
+            Some(core) => {
+                if let Some(resource_path) = &self.resource_path {
+                    Assistant::load_resource(resource_path, core.as_ref())?;
+                }
+                core
+            },
+            None => {
+                let library_path = self.library_path.ok_or(Error::PathNotSet("library"))?;
+                let resource_path = self
+                    .resource_path
+                    .as_deref()
+                    .ok_or(Error::PathNotSet("resource"))?;
+
+                // 加载 library
+                let core = Assistant::load_library(&library_path)?;
+
+                // 加载 resource
+                Assistant::load_resource(resource_path, core.as_ref())?;
+                core
+            }
         };
 
-        NonNull::new(handle)
-            .map(|handle| Assistant {
-                handle,
-                target: None,
-                tasks: HashMap::new(),
-                core
+        let tracker = TaskTracker::default();
+        let connection = ConnectionTracker::default();
+        let calls = AsyncCallTracker::default();
+        let processor = self
+            .listeners
+            .into_iter()
+            .fold(self.processor.unwrap_or_default(), |processor, listener| {
+                processor.with_listener(listener)
+            })
+            .with_listener({
+                let tracker = tracker.clone();
+                move |event| tracker.handle(event)
+            })
+            .with_listener({
+                let connection = connection.clone();
+                move |event| connection.handle(event)
+            })
+            .with_listener({
+                let calls = calls.clone();
+                move |event| calls.handle(event)
             })
-            .ok_or(Error::CreateFailed)
+            .with_logger({
+                // core 持有处理器，这里使用弱引用避免循环引用
+                let core = Arc::downgrade(&core);
+                move |message| {
+                    if let Some(core) = core.upgrade() {
+                        let _ = core.log("ERR", message);
+                    }
+                }
+            });
+
+        #[cfg(feature = "tokio")]
+        let (events, processor) = {
+            let (events, _) = tokio::sync::broadcast::channel(self.event_capacity);
+            let sender = events.clone();
+            let processor = processor.with_listener(move |event| {
+                // 没有订阅者时发送会失败，直接丢弃即可
+                let _ = sender.send(event.clone());
+            });
+            (events, processor)
+        };
+
+        // 创建 Assistant 实例，处理器的所有权交给 core，在实例销毁之后才释放
+        let handle = core.create(processor)?.ok_or(Error::CreateFailed)?;
+
+        Ok(Assistant {
+            handle,
+            target: None,
+            tasks: HashMap::new(),
+            tracker,
+            connection,
+            calls,
+            options: InstanceOptions::default(),
+            resource_path: self.resource_path.map(PathBuf::from),
+            items: OnceLock::new(),
+            core,
+            #[cfg(feature = "tokio")]
+            events
+        })
     }
 }
 
 /// MAA助手的主要结构体
 /// 负责管理与设备的连接、任务执行和资源控制
 pub struct Assistant {
-    /// 指向底层C++ API的指针
-    handle: NonNull<binding::AsstExtAPI>,
+    /// MaaCore 实例句柄
+    handle: InstanceHandle,
     /// 当前连接的设备地址，如果未连接则为None
     target: Option<String>,
     /// 存储所有已添加的任务，键为任务ID
-    tasks: HashMap<i32, Box<dyn task::Task>>,
+    tasks: HashMap<i32, task::AnyTask>,
+    /// 跟踪通过 `append_task_with_handle` 添加的任务
+    tracker: TaskTracker,
+    /// 收集 `connect_and_wait` 期间的连接信息
+    connection: ConnectionTracker,
+    /// 跟踪尚未结束的外部异步调用
+    calls: AsyncCallTracker,
+    /// 已经设置成功的实例选项
+    options: InstanceOptions,
+    /// 加载的资源路径，使用外部 core 且没有设置资源路径时为 `None`
+    resource_path: Option<PathBuf>,
+    /// 转换掉落物品名时使用的物品数据，第一次用到时读取，资源目录中没有 `item_index.json` 时为 `None`
+    items: OnceLock<Option<ItemIndex>>,
     /// MAA核心库实例
-    core: Arc<binding::MaaCore>
-}
-
-/// 将Rust的回调函数转换为C的回调函数
-///
-/// # Arguments
-/// * `msg_id` - 消息ID
-/// * `details_json` - JSON格式的消息详情
-/// * `user_data` - 用户数据指针，指向消息处理器
-pub unsafe extern "C" fn callback_wrapper(
-    msg_id: i32,
-    details_json: *const ::std::os::raw::c_char,
-    user_data: *mut ::std::os::raw::c_void
-) {
-    let json_str = std::ffi::CStr::from_ptr(details_json).to_str().unwrap();
-    let details: serde_json::Value = serde_json::from_str(json_str).unwrap();
-    let processor = &mut *(user_data as *mut message::Processor);
-    let msg = message::Message::from(msg_id);
-    (processor.callback)(msg, details);
+    core: Arc<dyn CoreApi>,
+    /// 事件流的发送端
+    #[cfg(feature = "tokio")]
+    events: tokio::sync::broadcast::Sender<message::Event>
 }
 
 impl Assistant {
@@ -124,17 +227,10 @@ impl Assistant {
     /// * `path` - 运行库文件的路径
     ///
     /// # Returns
-    /// * `Ok(Arc<binding::MaaCore>)` - 加载成功
+    /// * `Ok(Arc<dyn CoreApi>)` - 加载成功
     /// * `Err(Error::LibraryLoadFailed)` - 加载失败
-    pub fn load_library<P: AsRef<Path>>(path: P) -> Result<Arc<binding::MaaCore>, Error> {
-        let dylib_path = path.as_ref().join(match OS {
-            "macos" => "libMaaCore.dylib",
-            "windows" => "MaaCore.dll",
-            "linux" => "libMaaCore.so",
-            _ => return Err(Error::LibraryLoadFailed)
-        });
-        let lib = unsafe { binding::MaaCore::new(dylib_path).map_err(|_| Error::LibraryLoadFailed)? };
-        Ok(Arc::new(lib))
+    pub fn load_library<P: AsRef<Path>>(path: P) -> Result<Arc<dyn CoreApi>, Error> {
+        Ok(Arc::new(MaaCoreLibrary::load(path)?))
     }
 
     /// 加载MAA助手所需的资源文件
@@ -146,15 +242,14 @@ impl Assistant {
     /// # Returns
     /// * `Ok(())` - 资源加载成功
     /// * `Err(Error::ResourceLoadFailed)` - 资源加载失败
-    pub fn load_resource<P: AsRef<Path>>(path: P, core: &binding::MaaCore) -> Result<(), Error> {
-        let path = path.as_ref();
-        let resource_path = CString::new(path.to_string_lossy().as_ref()).unwrap();
-        let ret = unsafe { core.AsstLoadResource(resource_path.as_ptr()) };
-
-        if ret != 0 {
+    /// * `Err(Error::InvalidString)` - 路径中包含 NUL 字符
+    pub fn load_resource<P: AsRef<Path>>(path: P, core: &dyn CoreApi) -> Result<(), Error> {
+        if core.load_resource(path.as_ref())? {
             Ok(())
         } else {
-            Err(Error::ResourceLoadFailed)
+            Err(Error::ResourceLoadFailed {
+                path: path.as_ref().to_path_buf()
+            })
         }
     }
 
@@ -190,6 +285,27 @@ impl Assistant {
             .init()
     }
 
+    /// 创建一个带有事件回调函数的Assistant实例
+    ///
+    /// # Arguments
+    /// * `path` - 同时包含库文件和资源文件的路径
+    /// * `callback` - 回调函数，用于处理解析后的事件
+    ///
+    /// # Returns
+    /// * `Ok(Assistant)` - 创建成功
+    /// * `Err(Error::CreateFailed)` - 创建失败
+    /// * `Err(Error::ResourceLoadFailed)` - 资源加载失败
+    pub fn init_with_event_callback<P: AsRef<Path>, F: FnMut(message::Event) + Send + 'static>(
+        path: P,
+        callback: F
+    ) -> Result<Self, Error> {
+        Self::registry()
+            .with_library(path.as_ref())
+            .with_resource(path.as_ref())
+            .with_event_callback(callback)
+            .init()
+    }
+
     /// 创建一个带有回调函数的Assistant实例（向后兼容）
     ///
     /// # Arguments
@@ -214,6 +330,15 @@ impl Assistant {
             .init()
     }
 
+    /// 订阅异步事件流
+    ///
+    /// 只能收到订阅之后产生的事件，所以应该在 `connect`、`start` 之前订阅。
+    /// 背压和消费过慢时的行为见 [`crate::EventStream`]
+    #[cfg(feature = "tokio")]
+    pub fn events(&self) -> crate::EventStream {
+        crate::EventStream::new(self.events.subscribe())
+    }
+
     /// 设置实例级别的选项
     ///
     /// # Arguments
@@ -228,18 +353,34 @@ impl Assistant {
         key: InstanceOptionKey,
         value: impl Into<String>
     ) -> Result<(), Error> {
-        let value_str = CString::new(value.into()).unwrap();
-        let ret = unsafe {
-            self.core
-                .AsstSetInstanceOption(self.handle.as_ptr(), key as i32, value_str.as_ptr())
-        };
-        if ret != 0 {
+        let value = value.into();
+        if self.core.set_instance_option(self.handle, key, &value)? {
+            if let Some(option) = InstanceOption::parse(key, &value) {
+                self.options.apply(option);
+            }
             Ok(())
         } else {
-            Err(Error::SetInstanceOptionFailed)
+            Err(Error::SetInstanceOptionFailed { key, value })
         }
     }
 
+    /// 设置带有类型的实例选项
+    ///
+    /// # Arguments
+    /// * `option` - 选项及其取值
+    ///
+    /// # Returns
+    /// * `Ok(())` - 设置成功，可以通过 [`Assistant::instance_options`] 获取
+    /// * `Err(Error::SetInstanceOptionFailed)` - 设置失败
+    pub fn set_option(&mut self, option: InstanceOption) -> Result<(), Error> {
+        self.set_instance_option(option.key(), option.value())
+    }
+
+    /// 获取已经设置成功的实例选项
+    pub fn instance_options(&self) -> &InstanceOptions {
+        &self.options
+    }
+
     /// 设置全局静态选项
     ///
     /// # Arguments
@@ -250,13 +391,11 @@ impl Assistant {
     /// * `Ok(())` - 设置成功
     /// * `Err(Error::SetStaticOptionFailed)` - 设置失败
     pub fn set_static_option(&self, key: StaticOptionKey, value: impl Into<String>) -> Result<(), Error> {
-        let value_str = CString::new(value.into()).unwrap();
-        let ret = unsafe { self.core.AsstSetStaticOption(key as i32, value_str.as_ptr()) };
-
-        if ret != 0 {
+        let value = value.into();
+        if self.core.set_static_option(key, &value)? {
             Ok(())
         } else {
-            Err(Error::SetStaticOptionFailed)
+            Err(Error::SetStaticOptionFailed { key, value })
         }
     }
 
@@ -265,57 +404,181 @@ impl Assistant {
     /// # Arguments
     /// * `adb_path` - ADB可执行文件的路径
     /// * `address` - 设备地址（如：127.0.0.1:5555）
-    /// * `config` - 可选的连接配置
+    /// * `config` - 可选的连接配置预设，为 `None` 时由 MaaCore 使用默认配置
     ///
     /// # Returns
-    /// * `Ok(())` - 连接成功
-    /// * `Err(Error::ConnectFailed)` - 连接失败
-    pub fn connect(&mut self, connection: Connection, config: Option<&str>) -> Result<(), Error> {
-        let adb_path = CString::new(connection.adb_path().unwrap()).unwrap();
-        let address_cstr = CString::new(connection.address().unwrap()).unwrap();
-        let config_str = config.map(|c| CString::new(c).unwrap());
-
-        let ret = unsafe {
-            self.core.AsstAsyncConnect(
-                self.handle.as_ptr(),
-                adb_path.as_ptr(),
-                address_cstr.as_ptr(),
-                config_str.as_ref().map_or(std::ptr::null(), |cs| cs.as_ptr()),
-                1
-            )
-        };
-        if ret != 0 {
-            self.target = connection.address();
-            Ok(())
-        } else {
-            Err(Error::ConnectFailed)
+    /// * `Ok(AsyncCall)` - 已经开始连接，可以阻塞等待或者 `.await` 连接结果
+    /// * `Err(Error::ConnectFailed)` - 无法开始连接
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let result = assistant.connect(Connection::adb("adb", "127.0.0.1:5555"), None)?.wait();
+    /// assert!(result.ret);
+    /// ```
+    pub fn connect(
+        &mut self,
+        connection: Connection,
+        config: Option<ConnectionConfig>
+    ) -> Result<AsyncCall, Error> {
+        // PlayCover 不需要 adb，传入空字符串即可
+        let adb_path = connection.adb_path().unwrap_or_default();
+        let address = connection.address().unwrap_or_default();
+        let config = config.as_ref().map(ConnectionConfig::as_str);
+        let (core, handle) = (&self.core, self.handle);
+        match self
+            .calls
+            .track(|| core.connect(handle, &adb_path, &address, config, false))?
+        {
+            Some(call) => {
+                self.target = connection.address();
+                Ok(call)
+            },
+            None => Err(Error::ConnectFailed { adb_path, address })
+        }
+    }
+
+    /// 连接到指定的设备，并等待连接完成
+    ///
+    /// 与 [`Assistant::connect`] 不同，连接期间 MaaCore 发出的 `ConnectionInfo` 会被收集到结果中，
+    /// 连接失败时返回的错误包含 MaaCore 给出的 `what` 和 `why`
+    ///
+    /// # Arguments
+    /// * `connection` - 连接方式
+    /// * `config` - 可选的连接配置预设
+    /// * `timeout` - 等待连接完成的最长时间
+    ///
+    /// # Returns
+    /// * `Ok(ConnectionReport)` - 连接成功，包含设备唯一码、分辨率和截图方式
+    /// * `Err(Error::ConnectAborted)` - 连接失败或者连接断开
+    /// * `Err(Error::ConnectFailed)` - 连接失败，没有收到具体原因
+    /// * `Err(Error::ConnectTimeout)` - 超时
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let report = assistant.connect_and_wait(
+    ///     Connection::adb("adb", "127.0.0.1:16384"),
+    ///     Some(ConnectionConfig::MuMuEmulator12),
+    ///     Duration::from_secs(30)
+    /// )?;
+    /// println!("{:?} {:?}", report.uuid, report.resolution);
+    /// ```
+    pub fn connect_and_wait(
+        &mut self,
+        connection: Connection,
+        config: Option<ConnectionConfig>,
+        timeout: Duration
+    ) -> Result<ConnectionReport, Error> {
+        let adb_path = connection.adb_path().unwrap_or_default();
+        let address = connection.address().unwrap_or_default();
+        let config = config.as_ref().map(ConnectionConfig::as_str);
+
+        self.connection.begin();
+        let call_id = self
+            .core
+            .connect(self.handle, &adb_path, &address, config, false)?;
+        if call_id == 0 {
+            self.connection.cancel();
+            return Err(Error::ConnectFailed { adb_path, address });
+        }
+
+        match self.connection.wait(call_id, timeout) {
+            ConnectionOutcome::Connected(report) => {
+                self.target = connection.address();
+                Ok(report)
+            },
+            ConnectionOutcome::Aborted(what, why) => Err(Error::ConnectAborted { address, what, why }),
+            ConnectionOutcome::Failed => Err(Error::ConnectFailed { adb_path, address }),
+            ConnectionOutcome::Timeout => Err(Error::ConnectTimeout { address, timeout })
         }
     }
 
     /// 添加新的任务到任务队列
     ///
     /// # Arguments
-    /// * `task` - 实现了Task trait的任务实例
+    /// * `task` - 具体的任务或者 [`task::AnyTask`]
+    ///
+    /// Fight 任务指定掉落中的物品名会根据资源路径中的物品数据转换为物品 ID，
+    /// 见 [`task::FightTask::resolve_drops`]
     ///
     /// # Returns
     /// * `Ok(i32)` - 任务ID
+    /// * `Err(Error::InvalidFields)` - 任务参数不合法，见 [`task::Task::validate`]，或者有找不到的掉落物品
+    /// * `Err(Error::ResourceIoFailed)` - 掉落中有物品名时读取 `item_index.json` 失败
     /// * `Err(Error::TaskAppendFailed)` - 任务添加失败
-    pub fn append_task<T: task::Task + 'static>(&mut self, task: T) -> Result<i32, Error> {
-        let type_str = CString::new(task.task_type()).unwrap();
-        let params_str = CString::new(task.to_json()).unwrap();
-
-        unsafe {
-            let task_id = {
-                let asst_append_task = self.core.AsstAppendTask.as_ref().unwrap();
-                asst_append_task(self.handle.as_ptr(), type_str.as_ptr(), params_str.as_ptr())
-            };
-            if task_id != 0 {
-                self.tasks.insert(task_id, Box::from(task));
-                Ok(task_id)
-            } else {
-                Err(Error::TaskAppendFailed)
-            }
+    pub fn append_task(&mut self, task: impl Into<task::AnyTask>) -> Result<i32, Error> {
+        let mut task = task.into();
+        self.resolve_drops(&mut task)?;
+        task.validate()?;
+        let params = task.to_json();
+        let task_id = self.core.append_task(self.handle, task.task_type(), &params)?;
+        if task_id != 0 {
+            self.tasks.insert(task_id, task);
+            Ok(task_id)
+        } else {
+            Err(Error::TaskAppendFailed {
+                task_type: task.task_type().to_string(),
+                params
+            })
+        }
+    }
+
+    /// 把 Fight 任务指定掉落中的物品名转换为物品 ID
+    ///
+    /// 掉落全部是物品 ID、没有设置资源路径或者资源目录中没有 `item_index.json` 时保持不变
+    fn resolve_drops(&self, task: &mut task::AnyTask) -> Result<(), Error> {
+        let task::AnyTask::Fight(fight) = task else {
+            return Ok(());
+        };
+        let Some(drops) = &fight.drops else {
+            return Ok(());
+        };
+        // 物品 ID 都是数字，如 `30011`
+        if drops.keys().all(|item| item.bytes().all(|b| b.is_ascii_digit())) {
+            return Ok(());
+        }
+        if let Some(index) = self.drop_items()? {
+            fight.resolve_drops(index)?;
         }
+        Ok(())
+    }
+
+    /// 转换掉落物品名使用的物品数据，只在第一次调用时读取
+    fn drop_items(&self) -> Result<Option<&ItemIndex>, Error> {
+        if let Some(items) = self.items.get() {
+            return Ok(items.as_ref());
+        }
+        let items = match self.resource_path().map(ItemIndex::load) {
+            Some(Ok(items)) => Some(items),
+            Some(Err(Error::ResourceIoFailed { source, .. }))
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                None
+            },
+            Some(Err(err)) => return Err(err),
+            None => None
+        };
+        Ok(self.items.get_or_init(|| items).as_ref())
+    }
+
+    /// 添加新的任务到任务队列，并返回用于等待任务结束的句柄
+    ///
+    /// # Arguments
+    /// * `task` - 具体的任务或者 [`task::AnyTask`]
+    ///
+    /// # Returns
+    /// * `Ok(TaskHandle)` - 任务句柄，可以阻塞等待或者 `.await` 任务结束
+    /// * `Err(Error::TaskAppendFailed)` - 任务添加失败
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let handle = assistant.append_task_with_handle(FightTask::builder().stage("1-7").build())?;
+    /// assistant.start()?;
+    /// let outcome = handle.wait();
+    /// println!("{:?}: {:?}", outcome.status, outcome.extra_info);
+    /// ```
+    pub fn append_task_with_handle(&mut self, task: impl Into<task::AnyTask>) -> Result<TaskHandle, Error> {
+        let tracker = self.tracker.clone();
+        tracker.track(|| self.append_task(task))
     }
 
     /// 更新已存在任务的参数
@@ -326,36 +589,79 @@ impl Assistant {
     ///
     /// # Returns
     /// * `Ok(())` - 更新成功
+    /// * `Err(Error::InvalidFields)` - 任务参数不合法，见 [`task::Task::validate`]，或者有找不到的掉落物品
     /// * `Err(Error::TaskParamsSetFailed)` - 更新失败
-    pub fn set_task_params<T: task::Task + 'static>(&mut self, task_id: i32, task: T) -> Result<(), Error> {
-        let params_str = CString::new(task.to_json()).unwrap();
-        unsafe {
-            let ret = self
-                .core
-                .AsstSetTaskParams(self.handle.as_ptr(), task_id, params_str.as_ptr());
-            if ret != 0 {
-                if let Some(old_task) = self.tasks.get_mut(&task_id) {
-                    *old_task = Box::new(task);
-                }
-                Ok(())
-            } else {
-                Err(Error::TaskParamsSetFailed)
+    pub fn set_task_params(&mut self, task_id: i32, task: impl Into<task::AnyTask>) -> Result<(), Error> {
+        let mut task = task.into();
+        self.resolve_drops(&mut task)?;
+        task.validate()?;
+        let params = task.to_json();
+        if self.core.set_task_params(self.handle, task_id, &params)? {
+            if let Some(old_task) = self.tasks.get_mut(&task_id) {
+                *old_task = task;
             }
+            Ok(())
+        } else {
+            Err(Error::TaskParamsSetFailed {
+                task_id,
+                task_type: task.task_type().to_string(),
+                params
+            })
         }
     }
 
+    /// 应用任务配置
+    ///
+    /// 依次设置实例选项，连接设备并等待连接结束（配置了连接方式时），最后按顺序添加所有任务
+    ///
+    /// 连接通过 [`Assistant::connect_and_wait`] 进行，超时时间见 [`crate::profile::ConnectionProfile::timeout`]
+    ///
+    /// # Arguments
+    /// * `profile` - 任务配置
+    ///
+    /// # Returns
+    /// * `Ok(Vec<i32>)` - 按配置中的顺序添加的任务ID
+    /// * `Err(Error::ProfileInvalid)` - 配置无效，此时不会对实例做任何修改
+    /// * `Err(Error::SetInstanceOptionFailed)` - 设置实例选项失败
+    /// * `Err(Error::ConnectFailed)` - 连接失败
+    /// * `Err(Error::ConnectAborted)` - 连接失败，包含 MaaCore 给出的原因
+    /// * `Err(Error::ConnectTimeout)` - 超时时间内连接没有结束
+    /// * `Err(Error::TaskAppendFailed)` - 任务添加失败，之前的任务已经添加到队列中
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let task_ids = assistant.apply_profile(&Profile::load("daily.toml")?)?;
+    /// assistant.start()?;
+    /// ```
+    pub fn apply_profile(&mut self, profile: &Profile) -> Result<Vec<i32>, Error> {
+        profile.validate()?;
+        for option in profile.options.to_vec() {
+            self.set_option(option)?;
+        }
+        if let Some(connection) = &profile.connection {
+            self.connect_and_wait(
+                connection.connection(),
+                connection.config.clone(),
+                connection.timeout()
+            )?;
+        }
+        profile
+            .tasks
+            .iter()
+            .map(|task| self.append_task(task.clone()))
+            .collect()
+    }
+
     /// 启动助手开始执行任务
     ///
     /// # Returns
     /// * `Ok(())` - 启动成功
     /// * `Err(Error::StartFailed)` - 启动失败
     pub fn start(&mut self) -> Result<(), Error> {
-        unsafe {
-            if self.core.AsstStart(self.handle.as_ptr()) != 0 {
-                Ok(())
-            } else {
-                Err(Error::StartFailed)
-            }
+        if self.core.start(self.handle)? {
+            Ok(())
+        } else {
+            Err(Error::StartFailed)
         }
     }
 
@@ -365,12 +671,12 @@ impl Assistant {
     /// * `Ok(())` - 停止成功
     /// * `Err(Error::StopFailed)` - 停止失败
     pub fn stop(&mut self) -> Result<(), Error> {
-        unsafe {
-            if self.core.AsstStop(self.handle.as_ptr()) != 0 {
-                Ok(())
-            } else {
-                Err(Error::StopFailed)
-            }
+        if self.core.stop(self.handle)? {
+            // 停止后队列中尚未执行的任务不会再有回调
+            self.tracker.stop_all();
+            Ok(())
+        } else {
+            Err(Error::StopFailed)
         }
     }
 
@@ -381,77 +687,61 @@ impl Assistant {
     /// * `y` - 点击的Y坐标
     ///
     /// # Returns
-    /// * `Ok(())` - 点击成功
-    /// * `Err(Error::ClickFailed)` - 点击失败
-    pub fn click(&mut self, x: i32, y: i32) -> Result<(), Error> {
-        unsafe {
-            if self.core.AsstAsyncClick(self.handle.as_ptr(), x, y, 1) != 0 {
-                Ok(())
-            } else {
-                Err(Error::ClickFailed)
-            }
-        }
+    /// * `Ok(AsyncCall)` - 已经开始点击，可以阻塞等待或者 `.await` 点击结果
+    /// * `Err(Error::ClickFailed)` - 无法开始点击
+    pub fn click(&mut self, x: i32, y: i32) -> Result<AsyncCall, Error> {
+        let (core, handle) = (&self.core, self.handle);
+        self.calls
+            .track(|| core.click(handle, x, y, false))?
+            .ok_or(Error::ClickFailed)
     }
 
     /// 捕获当前屏幕截图
     ///
+    /// 截图结束后才能通过 [`Assistant::get_image`] 获取图像
+    ///
     /// # Returns
-    /// * `Ok(())` - 截图成功
-    /// * `Err(Error::CaptureFailed)` - 截图失败
-    pub fn capture_screenshot(&self) -> Result<(), Error> {
-        unsafe {
-            if {
-                let asst_async_screencap = self.core.AsstAsyncScreencap.as_ref().unwrap();
-                asst_async_screencap(self.handle.as_ptr(), 1)
-            } != 0
-            {
-                Ok(())
-            } else {
-                Err(Error::CaptureFailed)
-            }
-        }
+    /// * `Ok(AsyncCall)` - 已经开始截图，可以阻塞等待或者 `.await` 截图结果
+    /// * `Err(Error::CaptureFailed)` - 无法开始截图
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// if assistant.capture_screenshot()?.wait().ret {
+    ///     let image = assistant.get_image()?;
+    /// }
+    /// ```
+    pub fn capture_screenshot(&self) -> Result<AsyncCall, Error> {
+        self.calls
+            .track(|| self.core.screencap(self.handle, false))?
+            .ok_or(Error::CaptureFailed)
     }
 
-    fn get_image_with_buf(&self, buf: *mut u8, size: usize) -> Result<binding::AsstSize, Error> {
-        unsafe {
-            let ret = {
-                let asst_get_image = self.core.AsstGetImage.as_ref().unwrap();
-                asst_get_image(
-                    self.handle.as_ptr(),
-                    buf as *mut std::os::raw::c_void,
-                    size as binding::AsstSize
-                )
-            };
-
-            if ret != 0 {
-                Ok(ret)
-            } else {
-                Err(Error::CaptureFailed)
-            }
-        }
+    /// 获取最近一次截图的编码后数据
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - 图像数据
+    /// * `Err(Error::CaptureFailed)` - 没有可用的图像，图像超过 32MB 时也会返回该错误
+    pub fn get_image(&self) -> Result<Vec<u8>, Error> {
+        self.core.image(self.handle)?.ok_or(Error::CaptureFailed)
     }
 
-    pub fn get_image(&self) -> Result<Vec<u8>, Error> {
-        let mut buf_size = INIT_SIZE;
-        let mut buf = Vec::with_capacity(buf_size);
-
-        loop {
-            match self.get_image_with_buf(buf.as_mut_ptr(), buf_size) {
-                Ok(size) => {
-                    // Safety: the buffer is initialized by FFI, the size is the actual size
-                    unsafe { buf.set_len(size as usize) };
-                    return Ok(buf);
-                },
-                Err(_) => {
-                    if buf_size > MAX_SIZE {
-                        return Err(Error::ContentTooLarge(MAX_SIZE));
-                    }
-                    // Double the buffer size if it's not enough
-                    buf_size *= 2;
-                    buf.reserve(buf_size);
-                }
-            }
-        }
+    /// 获取最近一次截图并解码
+    ///
+    /// # Returns
+    /// * `Ok(Frame)` - 解码后的图像
+    /// * `Err(Error::CaptureFailed)` - 没有可用的图像
+    /// * `Err(Error::ImageDecodeFailed)` - 图像数据无法解码
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// if assistant.capture_screenshot()?.wait().ret {
+    ///     let frame = assistant.get_frame()?;
+    ///     frame.thumbnail(320, 180).save_png("thumbnail.png")?;
+    /// }
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn get_frame(&self) -> Result<crate::frame::Frame, Error> {
+        crate::frame::Frame::decode(&self.get_image()?)
     }
 
     /// 返回游戏主页
@@ -460,105 +750,54 @@ impl Assistant {
     /// * `Ok(())` - 返回成功
     /// * `Err(Error::BackToHomeFailed)` - 返回失败
     pub fn back_to_home(&mut self) -> Result<(), Error> {
-        unsafe {
-            if {
-                let asst_back_to_home = self.core.AsstBackToHome.as_ref().unwrap();
-                asst_back_to_home(self.handle.as_ptr())
-            } != 0
-            {
-                Ok(())
-            } else {
-                Err(Error::BackToHomeFailed)
-            }
+        if self.core.back_to_home(self.handle)? {
+            Ok(())
+        } else {
+            Err(Error::BackToHomeFailed)
         }
     }
 
-    /// 获取空值的大小
-    pub fn get_null_size(&self) -> Result<u64, Error> {
-        Ok(unsafe { self.core.AsstGetNullSize() })
-    }
-
     /// 获取当前实例的UUID
     ///
     /// # Returns
     /// * `Ok(String)` - UUID字符串
     /// * `Err(Error::Unknown)` - 获取失败
     pub fn get_uuid(&self) -> Result<String, Error> {
-        unsafe {
-            let mut buff_size = 1024;
-            loop {
-                if buff_size > 1024 * 1024 {
-                    return Err(Error::Unknown);
-                }
-                let mut buff: Vec<u8> = Vec::with_capacity(buff_size);
-                let data_size = {
-                    let asst_get_uuid = self.core.AsstGetUUID.as_ref().unwrap();
-                    asst_get_uuid(
-                        self.handle.as_ptr(),
-                        buff.as_mut_ptr() as *mut i8,
-                        buff_size as u64
-                    )
-                };
-                if data_size == self.get_null_size()? {
-                    buff_size = 2 * buff_size;
-                    continue;
-                }
-                buff.set_len(data_size as usize);
-                let ret = String::from_utf8_lossy(&buff).to_string();
-
-                return Ok(ret);
-            }
-        }
+        self.core.uuid(self.handle)?.ok_or(Error::Unknown)
     }
 
     /// 获取当前所有任务的列表
     ///
     /// # Returns
-    /// * `Ok(Vec<&dyn task::Task>)` - 任务列表
+    /// * `Ok(Vec<&task::AnyTask>)` - 任务列表，可以序列化后保存，再通过 [`Assistant::append_task`] 恢复
     /// * `Err(Box<dyn std::error::Error>)` - 获取失败
-    pub fn get_tasks_list(&self) -> Result<Vec<&dyn task::Task>, Box<dyn std::error::Error>> {
-        let mut list: Vec<i32> = Vec::with_capacity(1000);
-        unsafe {
-            let buff = list.as_mut_ptr();
-            let data_size = {
-                let asst_get_tasks_list = self.core.AsstGetTasksList.as_ref().unwrap();
-                asst_get_tasks_list(self.handle.as_ptr(), buff, list.capacity().try_into().unwrap())
-            };
-            list.set_len(data_size.try_into()?);
-            list.shrink_to_fit();
-
-            let ret = list
-                .iter()
-                .filter_map(|id| self.tasks.get(id))
-                .map(|task| task.as_ref())
-                .collect();
-
-            Ok(ret)
-        }
+    pub fn get_tasks_list(&self) -> Result<Vec<&task::AnyTask>, Box<dyn std::error::Error>> {
+        let ret = self
+            .core
+            .tasks_list(self.handle)?
+            .iter()
+            .filter_map(|id| self.tasks.get(id))
+            .collect();
+
+        Ok(ret)
     }
 
     /// 检查助手是否正在运行
     ///
     /// # Returns
     /// * `true` - 正在运行
-    /// * `false` - 未运行
+    /// * `false` - 未运行，或者运行库中缺少对应的函数
     pub fn is_running(&self) -> bool {
-        unsafe {
-            let asst_running = self.core.AsstRunning.as_ref().unwrap();
-            asst_running(self.handle.as_ptr()) != 0
-        }
+        self.core.running(self.handle).unwrap_or(false)
     }
 
     /// 检查是否已连接到设备
     ///
     /// # Returns
     /// * `true` - 已连接
-    /// * `false` - 未连接
+    /// * `false` - 未连接，或者运行库中缺少对应的函数
     pub fn is_connected(&self) -> bool {
-        unsafe {
-            let asst_connected = self.core.AsstConnected.as_ref().unwrap();
-            asst_connected(self.handle.as_ptr()) != 0
-        }
+        self.core.connected(self.handle).unwrap_or(false)
     }
 
     /// 打印日志信息
@@ -567,13 +806,36 @@ impl Assistant {
     /// * `level` - 日志级别
     /// * `message` - 日志消息
     pub fn log(&self, level: &str, message: &str) -> Result<(), Error> {
-        let level_cstr = CString::new(level).unwrap();
-        let message_cstr = CString::new(message).unwrap();
-        unsafe {
-            let asst_log = self.core.AsstLog.as_ref().unwrap();
-            asst_log(level_cstr.as_ptr(), message_cstr.as_ptr());
-            Ok(())
-        }
+        self.core.log(level, message)
+    }
+
+    /// 加载的资源路径
+    pub fn resource_path(&self) -> Option<&Path> {
+        self.resource_path.as_deref()
+    }
+
+    /// 从加载的资源路径读取关卡数据
+    ///
+    /// # Returns
+    /// * `Ok(StageCatalog)` - 关卡数据
+    /// * `Err(Error::PathNotSet)` - 没有设置资源路径
+    /// * `Err(Error::ResourceIoFailed)` - 读取 `stages.json` 失败
+    /// * `Err(Error::ResourceParseFailed)` - `stages.json` 格式错误
+    pub fn stage_catalog(&self) -> Result<StageCatalog, Error> {
+        let path = self.resource_path().ok_or(Error::PathNotSet("resource"))?;
+        StageCatalog::load(path)
+    }
+
+    /// 从加载的资源路径读取物品数据
+    ///
+    /// # Returns
+    /// * `Ok(ItemIndex)` - 物品数据
+    /// * `Err(Error::PathNotSet)` - 没有设置资源路径
+    /// * `Err(Error::ResourceIoFailed)` - 读取 `item_index.json` 失败
+    /// * `Err(Error::ResourceParseFailed)` - `item_index.json` 格式错误
+    pub fn item_index(&self) -> Result<ItemIndex, Error> {
+        let path = self.resource_path().ok_or(Error::PathNotSet("resource"))?;
+        ItemIndex::load(path)
     }
 
     /// 获取MAA助手的版本信息
@@ -582,26 +844,19 @@ impl Assistant {
     /// * `Ok(String)` - 版本号
     /// * `Err(Error::Unknown)` - 获取失败
     pub fn version(&self) -> Result<String, Error> {
-        unsafe {
-            let asst_get_version = self.core.AsstGetVersion.as_ref().unwrap();
-            CStr::from_ptr(asst_get_version())
-                .to_str()
-                .map(|s| s.to_string())
-                .map_err(|_| Error::Unknown)
-        }
+        self.core.version()
     }
 }
 
 /// 实现Drop trait，确保资源正确释放
 impl Drop for Assistant {
     fn drop(&mut self) {
-        unsafe {
-            let asst_destroy = self.core.AsstDestroy.as_ref().unwrap();
-            asst_destroy(self.handle.as_ptr());
-        }
+        // 处理器由 core 负责在实例销毁之后释放
+        let _ = self.core.destroy(self.handle);
+        // 自定义的 core 可能不会发出 Destroyed 消息，结束所有尚未结束的任务句柄、异步调用和连接等待
+        let event = message::Event::Destroyed;
+        self.tracker.handle(&event);
+        self.calls.handle(&event);
+        self.connection.handle(&event);
     }
 }
-
-/// 确保Assistant可以安全地在线程间传递
-unsafe impl Send for Assistant {}
-unsafe impl Sync for Assistant {}
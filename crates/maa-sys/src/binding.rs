@@ -9,12 +9,16 @@ include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 #[cfg(test)]
 mod tests {
     use std::ffi::CStr;
-    use std::path::Path;
+
+    use tempfile::TempDir;
 
     use crate::binding::MaaCore;
 
-    fn get_test_lib() -> MaaCore {
-        let path = Path::new(env!("MAA_LIB_PATH")).join(
+    /// 加载模拟运行库，返回的目录需要在使用期间保持存在
+    fn get_test_lib() -> (MaaCore, TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        maa_mock_core::install(dir.path(), &maa_mock_core::Script::new()).unwrap();
+        let path = dir.path().join(
             #[cfg(target_os = "macos")]
             "libMaaCore.dylib",
             #[cfg(target_os = "windows")]
@@ -22,13 +26,13 @@ mod tests {
             #[cfg(target_os = "linux")]
             "libMaaCore.so"
         );
-        unsafe { MaaCore::new(path).unwrap() }
+        (unsafe { MaaCore::new(path).unwrap() }, dir)
     }
 
     #[test]
     fn test_asst_get_null_size() {
         unsafe {
-            let lib = get_test_lib();
+            let (lib, _dir) = get_test_lib();
             let ret = lib.AsstGetNullSize();
             // -1 in cpp, which becomes u64::MAX when cast to unsigned
             let expected = u64::MAX;
@@ -39,7 +43,7 @@ mod tests {
     #[test]
     fn test_asst_get_version() {
         unsafe {
-            let lib = get_test_lib();
+            let (lib, _dir) = get_test_lib();
             let version_ptr = lib.AsstGetVersion();
             let version = CStr::from_ptr(version_ptr).to_str().unwrap_or("unknown");
             assert_ne!(version, "unknown");
@@ -0,0 +1,450 @@
+use std::env::consts::OS;
+use std::ffi::{CStr, CString};
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use hashbrown::HashMap;
+
+use crate::binding;
+use crate::protocol::message::Processor;
+use crate::types::*;
+
+// 一张 720p 图像，24位色深，原始大小为 1280 * 720 * 3（2.7 MB）
+// 压缩后的图像数据应小于原始大小。
+// 在大多数情况下，4MB 应该足够
+const INIT_SIZE: usize = 1024 * 1024 * 4;
+// 32MB 应该足够用于 4K 原始图像，但实际使用中可能不需要这么大
+const MAX_SIZE: usize = 1024 * 1024 * 32;
+
+/// 获取运行库中的函数，缺失时返回 [`Error::SymbolNotFound`]
+macro_rules! symbol {
+    ($core:expr, $name:ident) => {
+        $core
+            .$name
+            .as_ref()
+            .map_err(|_| Error::SymbolNotFound(stringify!($name)))
+    };
+}
+
+/// MaaCore 实例句柄，由 [`CoreApi::create`] 创建，具体含义由实现决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceHandle(u64);
+
+impl InstanceHandle {
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub fn id(self) -> u64 {
+        self.0
+    }
+}
+
+/// MaaCore 的 API 抽象
+///
+/// [`crate::Assistant`] 通过这个 trait 调用 MaaCore，默认的实现是通过动态链接库加载的 [`MaaCoreLibrary`]，
+/// 也可以实现为进程内的假实现、记录调用的代理或者远程后端，再通过 [`crate::AssistantBuilder::with_core`] 使用。
+///
+/// 与 C API 一致，操作是否成功通过返回值表示：`bool` 表示是否成功，任务 ID 和异步调用 ID 为 `0` 表示失败。
+/// `Err` 只用于无法完成调用的情况，例如运行库缺少函数、参数中包含 NUL 字符或者句柄无效。
+pub trait CoreApi: Send + Sync {
+    /// 加载资源
+    fn load_resource(&self, path: &Path) -> Result<bool, Error>;
+
+    /// 设置全局静态选项
+    fn set_static_option(&self, key: StaticOptionKey, value: &str) -> Result<bool, Error>;
+
+    /// 创建实例，创建失败时返回 `None`
+    ///
+    /// 实例的所有回调消息都需要交给 `processor` 分发，实现需要持有 `processor` 直到 [`CoreApi::destroy`]
+    /// 返回，并保证之后不会再使用它
+    fn create(&self, processor: Processor) -> Result<Option<InstanceHandle>, Error>;
+
+    /// 销毁实例
+    fn destroy(&self, handle: InstanceHandle) -> Result<(), Error>;
+
+    /// 设置实例级别的选项
+    fn set_instance_option(
+        &self,
+        handle: InstanceHandle,
+        key: InstanceOptionKey,
+        value: &str
+    ) -> Result<bool, Error>;
+
+    /// 连接设备，返回异步调用 ID
+    fn connect(
+        &self,
+        handle: InstanceHandle,
+        adb_path: &str,
+        address: &str,
+        config: Option<&str>,
+        block: bool
+    ) -> Result<i32, Error>;
+
+    /// 添加任务，返回任务 ID
+    fn append_task(&self, handle: InstanceHandle, task_type: &str, params: &str) -> Result<i32, Error>;
+
+    /// 设置任务参数
+    fn set_task_params(&self, handle: InstanceHandle, task_id: i32, params: &str) -> Result<bool, Error>;
+
+    /// 开始执行任务
+    fn start(&self, handle: InstanceHandle) -> Result<bool, Error>;
+
+    /// 停止执行任务
+    fn stop(&self, handle: InstanceHandle) -> Result<bool, Error>;
+
+    /// 是否正在运行
+    fn running(&self, handle: InstanceHandle) -> Result<bool, Error>;
+
+    /// 是否已连接
+    fn connected(&self, handle: InstanceHandle) -> Result<bool, Error>;
+
+    /// 返回游戏主页
+    fn back_to_home(&self, handle: InstanceHandle) -> Result<bool, Error>;
+
+    /// 点击，返回异步调用 ID
+    fn click(&self, handle: InstanceHandle, x: i32, y: i32, block: bool) -> Result<i32, Error>;
+
+    /// 截图，返回异步调用 ID
+    fn screencap(&self, handle: InstanceHandle, block: bool) -> Result<i32, Error>;
+
+    /// 获取最近一次截图的编码后数据，没有图像时返回 `None`
+    ///
+    /// MaaCore 在没有图像和缓冲区不足时返回相同的结果，
+    /// 内置实现在缓冲区增长到 32MB 仍然不足时同样返回 `None`
+    fn image(&self, handle: InstanceHandle) -> Result<Option<Vec<u8>>, Error>;
+
+    /// 获取设备的 UUID，获取失败时返回 `None`
+    fn uuid(&self, handle: InstanceHandle) -> Result<Option<String>, Error>;
+
+    /// 获取任务队列中的任务 ID
+    fn tasks_list(&self, handle: InstanceHandle) -> Result<Vec<i32>, Error>;
+
+    /// 获取版本号
+    fn version(&self) -> Result<String, Error>;
+
+    /// 打印日志
+    fn log(&self, level: &str, message: &str) -> Result<(), Error>;
+}
+
+/// 将Rust的回调函数转换为C的回调函数
+///
+/// # Arguments
+/// * `msg_id` - 消息ID
+/// * `details_json` - JSON格式的消息详情
+/// * `user_data` - 用户数据指针，指向消息处理器
+///
+/// 无法解析为 JSON 的消息会作为 [`crate::Event::Malformed`] 分发，回调函数中的 panic
+/// 会被捕获、写入日志并以 [`crate::Event::CallbackPanicked`] 通知监听器，不会跨越 FFI 边界。
+///
+/// # Safety
+///
+/// `details_json` 必须为空指针或者以 NUL 结尾的有效 C 字符串，`user_data` 必须为空指针或者指向一个有效的
+/// [`Processor`]，并且在回调期间不能被其他地方访问。
+pub unsafe extern "C" fn callback_wrapper(
+    msg_id: i32,
+    details_json: *const ::std::os::raw::c_char,
+    user_data: *mut ::std::os::raw::c_void
+) {
+    if user_data.is_null() {
+        return;
+    }
+    // panic 跨越 extern "C" 会直接终止进程，这里兜底捕获
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let details = if details_json.is_null() {
+            &[][..]
+        } else {
+            CStr::from_ptr(details_json).to_bytes()
+        };
+        let processor = &mut *(user_data as *mut Processor);
+        processor.dispatch_bytes(msg_id, details);
+    }));
+}
+
+/// 通过动态链接库加载的 MaaCore
+pub struct MaaCoreLibrary {
+    lib: binding::MaaCore,
+    /// 已创建的实例，句柄的 ID 为实例指针的地址
+    instances: Mutex<HashMap<InstanceHandle, RawInstance>>
+}
+
+struct RawInstance {
+    handle: NonNull<binding::AsstExtAPI>,
+    /// 回调消息处理器，作为 user_data 传给 MaaCore，在 AsstDestroy 之后才释放
+    processor: NonNull<Processor>
+}
+
+// MaaCore 的实例可以在线程间传递，处理器只会在回调线程上使用
+unsafe impl Send for RawInstance {}
+
+impl MaaCoreLibrary {
+    /// 从目录中加载当前平台对应的运行库文件
+    ///
+    /// # Returns
+    /// * `Ok(MaaCoreLibrary)` - 加载成功
+    /// * `Err(Error::LibraryLoadFailed)` - 加载失败，包含运行库文件的路径和底层错误
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let dylib_path = path.as_ref().join(match OS {
+            "macos" => "libMaaCore.dylib",
+            "windows" => "MaaCore.dll",
+            "linux" => "libMaaCore.so",
+            _ => {
+                return Err(Error::LibraryLoadFailed {
+                    path: path.as_ref().to_path_buf(),
+                    source: None
+                })
+            },
+        });
+        let lib = unsafe { binding::MaaCore::new(&dylib_path) }.map_err(|err| Error::LibraryLoadFailed {
+            path: dylib_path,
+            source: Some(err)
+        })?;
+        Ok(Self {
+            lib,
+            instances: Mutex::new(HashMap::new())
+        })
+    }
+
+    fn instances(&self) -> MutexGuard<'_, HashMap<InstanceHandle, RawInstance>> {
+        self.instances.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn raw(&self, handle: InstanceHandle) -> Result<*mut binding::AsstExtAPI, Error> {
+        self.instances()
+            .get(&handle)
+            .map(|instance| instance.handle.as_ptr())
+            .ok_or(Error::InvalidHandle)
+    }
+
+    fn null_size(&self) -> Result<binding::AsstSize, Error> {
+        let asst_get_null_size = symbol!(self.lib, AsstGetNullSize)?;
+        Ok(unsafe { asst_get_null_size() })
+    }
+}
+
+impl CoreApi for MaaCoreLibrary {
+    fn load_resource(&self, path: &Path) -> Result<bool, Error> {
+        let resource_path = CString::new(path.to_string_lossy().as_ref())?;
+        let asst_load_resource = symbol!(self.lib, AsstLoadResource)?;
+        Ok(unsafe { asst_load_resource(resource_path.as_ptr()) } != 0)
+    }
+
+    fn set_static_option(&self, key: StaticOptionKey, value: &str) -> Result<bool, Error> {
+        let value = CString::new(value)?;
+        let asst_set_static_option = symbol!(self.lib, AsstSetStaticOption)?;
+        Ok(unsafe { asst_set_static_option(key as i32, value.as_ptr()) } != 0)
+    }
+
+    fn create(&self, processor: Processor) -> Result<Option<InstanceHandle>, Error> {
+        let asst_create_ex = symbol!(self.lib, AsstCreateEx)?;
+        let processor = unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(processor))) };
+        let handle = unsafe { asst_create_ex(Some(callback_wrapper), processor.as_ptr() as *mut _) };
+
+        let Some(handle) = NonNull::new(handle) else {
+            // 创建失败时 MaaCore 不会持有处理器，可以直接释放
+            drop(unsafe { Box::from_raw(processor.as_ptr()) });
+            return Ok(None);
+        };
+
+        let id = InstanceHandle::new(handle.as_ptr() as u64);
+        self.instances().insert(id, RawInstance { handle, processor });
+        Ok(Some(id))
+    }
+
+    fn destroy(&self, handle: InstanceHandle) -> Result<(), Error> {
+        // 缺少 AsstDestroy 时实例无法销毁，MaaCore 仍可能回调，只能泄漏处理器
+        let asst_destroy = symbol!(self.lib, AsstDestroy)?;
+        let instance = self.instances().remove(&handle).ok_or(Error::InvalidHandle)?;
+        unsafe {
+            // AsstDestroy 会等待 MaaCore 的回调线程退出，`Destroyed` 消息也在返回之前发出，
+            // 因此返回之后不会再有回调访问处理器
+            asst_destroy(instance.handle.as_ptr());
+            drop(Box::from_raw(instance.processor.as_ptr()));
+        }
+        Ok(())
+    }
+
+    fn set_instance_option(
+        &self,
+        handle: InstanceHandle,
+        key: InstanceOptionKey,
+        value: &str
+    ) -> Result<bool, Error> {
+        let value = CString::new(value)?;
+        let asst_set_instance_option = symbol!(self.lib, AsstSetInstanceOption)?;
+        Ok(unsafe { asst_set_instance_option(self.raw(handle)?, key as i32, value.as_ptr()) } != 0)
+    }
+
+    fn connect(
+        &self,
+        handle: InstanceHandle,
+        adb_path: &str,
+        address: &str,
+        config: Option<&str>,
+        block: bool
+    ) -> Result<i32, Error> {
+        let adb_path = CString::new(adb_path)?;
+        let address = CString::new(address)?;
+        let config = config.map(CString::new).transpose()?;
+        let asst_async_connect = symbol!(self.lib, AsstAsyncConnect)?;
+        Ok(unsafe {
+            asst_async_connect(
+                self.raw(handle)?,
+                adb_path.as_ptr(),
+                address.as_ptr(),
+                config.as_ref().map_or(std::ptr::null(), |config| config.as_ptr()),
+                block as binding::AsstBool
+            )
+        })
+    }
+
+    fn append_task(&self, handle: InstanceHandle, task_type: &str, params: &str) -> Result<i32, Error> {
+        let task_type = CString::new(task_type)?;
+        let params = CString::new(params)?;
+        let asst_append_task = symbol!(self.lib, AsstAppendTask)?;
+        Ok(unsafe { asst_append_task(self.raw(handle)?, task_type.as_ptr(), params.as_ptr()) })
+    }
+
+    fn set_task_params(&self, handle: InstanceHandle, task_id: i32, params: &str) -> Result<bool, Error> {
+        let params = CString::new(params)?;
+        let asst_set_task_params = symbol!(self.lib, AsstSetTaskParams)?;
+        Ok(unsafe { asst_set_task_params(self.raw(handle)?, task_id, params.as_ptr()) } != 0)
+    }
+
+    fn start(&self, handle: InstanceHandle) -> Result<bool, Error> {
+        let asst_start = symbol!(self.lib, AsstStart)?;
+        Ok(unsafe { asst_start(self.raw(handle)?) } != 0)
+    }
+
+    fn stop(&self, handle: InstanceHandle) -> Result<bool, Error> {
+        let asst_stop = symbol!(self.lib, AsstStop)?;
+        Ok(unsafe { asst_stop(self.raw(handle)?) } != 0)
+    }
+
+    fn running(&self, handle: InstanceHandle) -> Result<bool, Error> {
+        let asst_running = symbol!(self.lib, AsstRunning)?;
+        Ok(unsafe { asst_running(self.raw(handle)?) } != 0)
+    }
+
+    fn connected(&self, handle: InstanceHandle) -> Result<bool, Error> {
+        let asst_connected = symbol!(self.lib, AsstConnected)?;
+        Ok(unsafe { asst_connected(self.raw(handle)?) } != 0)
+    }
+
+    fn back_to_home(&self, handle: InstanceHandle) -> Result<bool, Error> {
+        let asst_back_to_home = symbol!(self.lib, AsstBackToHome)?;
+        Ok(unsafe { asst_back_to_home(self.raw(handle)?) } != 0)
+    }
+
+    fn click(&self, handle: InstanceHandle, x: i32, y: i32, block: bool) -> Result<i32, Error> {
+        let asst_async_click = symbol!(self.lib, AsstAsyncClick)?;
+        Ok(unsafe { asst_async_click(self.raw(handle)?, x, y, block as binding::AsstBool) })
+    }
+
+    fn screencap(&self, handle: InstanceHandle, block: bool) -> Result<i32, Error> {
+        let asst_async_screencap = symbol!(self.lib, AsstAsyncScreencap)?;
+        Ok(unsafe { asst_async_screencap(self.raw(handle)?, block as binding::AsstBool) })
+    }
+
+    fn image(&self, handle: InstanceHandle) -> Result<Option<Vec<u8>>, Error> {
+        let asst_get_image = symbol!(self.lib, AsstGetImage)?;
+        let raw = self.raw(handle)?;
+        let null_size = self.null_size()?;
+
+        let mut buf_size = INIT_SIZE;
+        let mut buf: Vec<u8> = Vec::with_capacity(buf_size);
+        loop {
+            let size = unsafe {
+                asst_get_image(
+                    raw,
+                    buf.as_mut_ptr() as *mut std::os::raw::c_void,
+                    buf_size as binding::AsstSize
+                )
+            };
+            // 缓冲区不足或者没有图像时返回 NullSize
+            if size != 0 && size != null_size {
+                // Safety: the buffer is initialized by FFI, the size is the actual size
+                unsafe { buf.set_len(size as usize) };
+                return Ok(Some(buf));
+            }
+            // 两种情况无法区分，缓冲区达到上限时仍然失败则视为没有图像
+            if buf_size >= MAX_SIZE {
+                return Ok(None);
+            }
+            // Double the buffer size if it's not enough
+            buf_size = (buf_size * 2).min(MAX_SIZE);
+            buf.reserve(buf_size);
+        }
+    }
+
+    fn uuid(&self, handle: InstanceHandle) -> Result<Option<String>, Error> {
+        let asst_get_uuid = symbol!(self.lib, AsstGetUUID)?;
+        let raw = self.raw(handle)?;
+        let null_size = self.null_size()?;
+
+        let mut buff_size = 1024;
+        while buff_size <= 1024 * 1024 {
+            let mut buff: Vec<u8> = Vec::with_capacity(buff_size);
+            let data_size = unsafe {
+                asst_get_uuid(
+                    raw,
+                    buff.as_mut_ptr() as *mut std::os::raw::c_char,
+                    buff_size as binding::AsstSize
+                )
+            };
+            if data_size == null_size {
+                buff_size *= 2;
+                continue;
+            }
+            unsafe { buff.set_len(data_size as usize) };
+            return Ok(Some(String::from_utf8_lossy(&buff).to_string()));
+        }
+        Ok(None)
+    }
+
+    fn tasks_list(&self, handle: InstanceHandle) -> Result<Vec<i32>, Error> {
+        let asst_get_tasks_list = symbol!(self.lib, AsstGetTasksList)?;
+        let raw = self.raw(handle)?;
+        let null_size = self.null_size()?;
+
+        let mut list: Vec<i32> = Vec::with_capacity(1000);
+        let data_size =
+            unsafe { asst_get_tasks_list(raw, list.as_mut_ptr(), list.capacity() as binding::AsstSize) };
+        if data_size != null_size {
+            unsafe { list.set_len((data_size as usize).min(list.capacity())) };
+        }
+        Ok(list)
+    }
+
+    fn version(&self) -> Result<String, Error> {
+        let asst_get_version = symbol!(self.lib, AsstGetVersion)?;
+        let version = unsafe { asst_get_version() };
+        if version.is_null() {
+            return Err(Error::Unknown);
+        }
+        unsafe { CStr::from_ptr(version) }
+            .to_str()
+            .map(|s| s.to_string())
+            .map_err(|_| Error::Unknown)
+    }
+
+    fn log(&self, level: &str, message: &str) -> Result<(), Error> {
+        let level = CString::new(level)?;
+        let message = CString::new(message)?;
+        let asst_log = symbol!(self.lib, AsstLog)?;
+        unsafe { asst_log(level.as_ptr(), message.as_ptr()) };
+        Ok(())
+    }
+}
+
+impl Drop for MaaCoreLibrary {
+    fn drop(&mut self) {
+        // 正常情况下实例都已经由 Assistant 销毁，这里兜底，避免卸载运行库之后 MaaCore 的线程仍在运行
+        let handles: Vec<InstanceHandle> = self.instances().keys().copied().collect();
+        for handle in handles {
+            let _ = self.destroy(handle);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! 设备发现
+//!
+//! 通过 `adb devices -l` 列出已连接的设备，并探测本机上常见模拟器的 adb 端口，
+//! 得到可以直接传给 [`crate::Assistant::connect`] 的 [`Connection`]。
+//!
+//! # Example
+//! ```rust,ignore
+//! let discovered = Discovery::new().with_adb("/path/to/adb").discover();
+//! if let Some(err) = &discovered.adb_error {
+//!     println!("adb 执行失败，只使用探测到的端口：{err}");
+//! }
+//! for device in discovered.devices.iter().filter(|device| device.is_available()) {
+//!     println!("{} {:?} {:?}", device.serial, device.state, device.model);
+//! }
+//! ```
+
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::{Connection, Error};
+
+/// 常见模拟器的 adb 端口
+///
+/// - 5555、5557：蓝叠、雷电等模拟器的第一、二个实例
+/// - 7555：MuMu 模拟器 6
+/// - 16384、16416、16448：MuMu 模拟器 12 的前三个实例
+/// - 21503：逍遥模拟器
+/// - 62001、62025：夜神模拟器
+pub const DEFAULT_EMULATOR_PORTS: &[u16] = &[5555, 5557, 7555, 16384, 16416, 16448, 21503, 62001, 62025];
+
+/// 端口探测的默认超时时间
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// 设备状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceState {
+    /// 已连接，可以使用
+    Device,
+    /// 已断开或者没有响应
+    Offline,
+    /// 设备上没有授权 USB 调试
+    Unauthorized,
+    /// 没有访问 USB 设备的权限
+    NoPermissions,
+    /// 端口可以连接，但是还没有通过 `adb connect` 连接
+    PortOpen,
+    /// 其他状态，如 `bootloader`、`recovery`
+    Other(String)
+}
+
+impl DeviceState {
+    fn parse(state: &str) -> Self {
+        match state {
+            "device" => DeviceState::Device,
+            "offline" => DeviceState::Offline,
+            "unauthorized" => DeviceState::Unauthorized,
+            // `no permissions (...)`，只取第一个单词
+            "no" => DeviceState::NoPermissions,
+            state => DeviceState::Other(state.to_string())
+        }
+    }
+}
+
+/// 发现的设备
+#[derive(Debug, Clone)]
+pub struct Device {
+    /// 设备序列号，即 adb 的连接地址，如 `emulator-5554`、`127.0.0.1:16384`
+    pub serial: String,
+    /// 设备状态
+    pub state: DeviceState,
+    /// 设备型号
+    pub model: Option<String>,
+    /// 产品名
+    pub product: Option<String>,
+    /// 设备名
+    pub device: Option<String>,
+    /// adb 的传输 ID
+    pub transport_id: Option<u32>,
+    /// 连接这个设备使用的 [`Connection`]
+    pub connection: Connection
+}
+
+impl Device {
+    /// 设备是否可以直接连接
+    ///
+    /// 只开放了端口的设备 MaaCore 连接时会自动执行 `adb connect`，也视为可用
+    pub fn is_available(&self) -> bool {
+        matches!(self.state, DeviceState::Device | DeviceState::PortOpen)
+    }
+}
+
+/// 设备发现
+pub struct Discovery {
+    adb_path: String,
+    host: String,
+    ports: Vec<u16>,
+    timeout: Duration
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self {
+            adb_path: "adb".to_string(),
+            host: "127.0.0.1".to_string(),
+            ports: DEFAULT_EMULATOR_PORTS.to_vec(),
+            timeout: DEFAULT_PROBE_TIMEOUT
+        }
+    }
+}
+
+impl Discovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置 adb 可执行文件的路径，默认为 `adb`，即从 `PATH` 中查找
+    pub fn with_adb(mut self, path: impl Into<String>) -> Self {
+        self.adb_path = path.into();
+        self
+    }
+
+    /// 设置探测端口的主机，默认为 `127.0.0.1`
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// 设置探测的端口，默认为 [`DEFAULT_EMULATOR_PORTS`]
+    pub fn with_ports(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        self.ports = ports.into_iter().collect();
+        self
+    }
+
+    /// 设置每个端口的连接超时时间，默认为 [`DEFAULT_PROBE_TIMEOUT`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 执行 `adb devices -l` 列出 adb 已知的设备
+    ///
+    /// # Returns
+    /// * `Ok(Vec<Device>)` - 设备列表
+    /// * `Err(Error::AdbFailed)` - adb 无法执行或者执行失败
+    pub fn adb_devices(&self) -> Result<Vec<Device>, Error> {
+        let output = Command::new(&self.adb_path)
+            .args(["devices", "-l"])
+            .output()
+            .map_err(|err| Error::AdbFailed {
+                adb_path: self.adb_path.clone(),
+                message: err.to_string()
+            })?;
+        if !output.status.success() {
+            return Err(Error::AdbFailed {
+                adb_path: self.adb_path.clone(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string()
+            });
+        }
+        Ok(parse_devices(&String::from_utf8_lossy(&output.stdout))
+            .into_iter()
+            .map(|mut device| {
+                device.connection = Connection::adb(&self.adb_path, &device.serial);
+                device
+            })
+            .collect())
+    }
+
+    /// 探测端口，返回可以连接的端口对应的设备，状态为 [`DeviceState::PortOpen`]
+    pub fn probe_ports(&self) -> Vec<Device> {
+        self.ports
+            .iter()
+            .filter(|port| self.probe(**port))
+            .map(|port| {
+                let serial = format!("{}:{port}", self.host);
+                Device {
+                    connection: Connection::adb(&self.adb_path, &serial),
+                    serial,
+                    state: DeviceState::PortOpen,
+                    model: None,
+                    product: None,
+                    device: None,
+                    transport_id: None
+                }
+            })
+            .collect()
+    }
+
+    fn probe(&self, port: u16) -> bool {
+        let addrs: Vec<SocketAddr> = match (self.host.as_str(), port).to_socket_addrs() {
+            Ok(addrs) => addrs.collect(),
+            Err(_) => return false
+        };
+        addrs
+            .iter()
+            .any(|addr| TcpStream::connect_timeout(addr, self.timeout).is_ok())
+    }
+
+    /// 列出 adb 已知的设备，再加上探测到的、adb 还不知道的端口
+    ///
+    /// adb 无法执行或者执行失败时仍然会探测端口，错误记录在 [`Discovered::adb_error`] 中
+    pub fn discover(&self) -> Discovered {
+        let (mut devices, adb_error) = match self.adb_devices() {
+            Ok(devices) => (devices, None),
+            Err(err) => (Vec::new(), Some(err))
+        };
+        let probed: Vec<Device> = self
+            .probe_ports()
+            .into_iter()
+            .filter(|probed| {
+                !devices
+                    .iter()
+                    .any(|device| same_address(&device.serial, &probed.serial))
+            })
+            .collect();
+        devices.extend(probed);
+        Discovered { devices, adb_error }
+    }
+}
+
+/// [`Discovery::discover`] 的结果
+#[derive(Debug)]
+pub struct Discovered {
+    /// 设备列表，adb 已知的设备在前
+    pub devices: Vec<Device>,
+    /// 执行 `adb devices -l` 失败的原因，此时 `devices` 中只有探测到的端口
+    pub adb_error: Option<Error>
+}
+
+/// `localhost:5555` 和 `127.0.0.1:5555` 视为同一个地址
+fn same_address(a: &str, b: &str) -> bool {
+    let normalize = |serial: &str| serial.replacen("localhost:", "127.0.0.1:", 1);
+    normalize(a) == normalize(b)
+}
+
+/// 解析 `adb devices -l` 的输出，`connection` 使用 `adb` 作为 adb 路径
+pub fn parse_devices(output: &str) -> Vec<Device> {
+    output
+        .lines()
+        .map(str::trim)
+        // 跳过标题和 adb server 启动时的提示
+        .filter(|line| !line.is_empty() && !line.starts_with("List of devices") && !line.starts_with('*'))
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let serial = tokens.next()?.to_string();
+            let state = DeviceState::parse(tokens.next()?);
+
+            let mut device = Device {
+                connection: Connection::adb("adb", &serial),
+                serial,
+                state,
+                model: None,
+                product: None,
+                device: None,
+                transport_id: None
+            };
+            // `no permissions` 后面跟着一段说明，只取认识的属性
+            for (key, value) in tokens.filter_map(|token| token.split_once(':')) {
+                match key {
+                    "model" => device.model = Some(value.to_string()),
+                    "product" => device.product = Some(value.to_string()),
+                    "device" => device.device = Some(value.to_string()),
+                    "transport_id" => device.transport_id = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            Some(device)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_devices() {
+        let output = "\
+* daemon not running; starting now at tcp:5037
+* daemon started successfully
+List of devices attached
+emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64x transport_id:1
+127.0.0.1:16384        offline transport_id:2
+R58M123ABC             unauthorized usb:1-1 transport_id:3
+0123456789ABCDEF       no permissions (missing udev rules? user is in the plugdev group); see [http://developer.android.com/tools/device.html] usb:1-2 transport_id:4
+
+";
+        let devices = parse_devices(output);
+        assert_eq!(devices.len(), 4);
+
+        assert_eq!(devices[0].serial, "emulator-5554");
+        assert_eq!(devices[0].state, DeviceState::Device);
+        assert_eq!(devices[0].model.as_deref(), Some("sdk_gphone64_x86_64"));
+        assert_eq!(devices[0].device.as_deref(), Some("emu64x"));
+        assert_eq!(devices[0].transport_id, Some(1));
+        assert!(devices[0].is_available());
+
+        assert_eq!(devices[1].state, DeviceState::Offline);
+        assert_eq!(
+            devices[1].connection.address().as_deref(),
+            Some("127.0.0.1:16384")
+        );
+        assert_eq!(devices[2].state, DeviceState::Unauthorized);
+        assert_eq!(devices[3].state, DeviceState::NoPermissions);
+        assert_eq!(devices[3].transport_id, Some(4));
+        assert!(!devices[3].is_available());
+    }
+
+    #[test]
+    fn test_same_address() {
+        assert!(same_address("localhost:5555", "127.0.0.1:5555"));
+        assert!(!same_address("127.0.0.1:5555", "127.0.0.1:5556"));
+    }
+}
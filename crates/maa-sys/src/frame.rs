@@ -0,0 +1,236 @@
+//! 解码后的截图
+//!
+//! MaaCore 通过 `AsstGetImage` 返回编码后的图像数据（通常是 PNG），
+//! 启用 `image` feature 后可以通过 [`crate::Assistant::get_frame`] 直接得到解码后的 [`Frame`]。
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ImageError, ImageFormat, RgbImage};
+
+use crate::Error;
+
+/// JPEG 编码的默认质量
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// 解码后的截图，像素格式为 RGB8
+///
+/// 数据长度在创建时检查，之后转换和保存都不会因为尺寸不一致而失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    stride: usize,
+    data: Vec<u8>
+}
+
+impl Frame {
+    /// 根据原始像素数据创建
+    ///
+    /// # Arguments
+    /// * `width` - 宽度（像素）
+    /// * `height` - 高度（像素）
+    /// * `stride` - 每行的字节数，不小于 `width * 3`
+    /// * `data` - 按行排列的像素数据，每个像素依次为 R、G、B，最后一行可以不包含填充
+    ///
+    /// # Returns
+    /// * `Ok(Frame)` - 创建成功
+    /// * `Err(Error::InvalidFrame)` - `stride` 小于一行像素的长度，或者数据长度不足
+    pub fn new(width: u32, height: u32, stride: usize, data: Vec<u8>) -> Result<Self, Error> {
+        let row = width as usize * 3;
+        let required = match height as usize {
+            0 => Some(0),
+            height => stride
+                .checked_mul(height - 1)
+                .and_then(|size| size.checked_add(row))
+        };
+        if stride < row || required.is_none_or(|required| data.len() < required) {
+            return Err(Error::InvalidFrame {
+                width,
+                height,
+                stride,
+                len: data.len()
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            stride,
+            data
+        })
+    }
+
+    /// 解码图像数据，支持 PNG 和 JPEG
+    ///
+    /// # Returns
+    /// * `Ok(Frame)` - 解码后的图像
+    /// * `Err(Error::ImageDecodeFailed)` - 数据无法识别或者已经损坏
+    pub fn decode(buffer: &[u8]) -> Result<Self, Error> {
+        let image = image::load_from_memory(buffer).map_err(Error::ImageDecodeFailed)?;
+        Ok(image.into_rgb8().into())
+    }
+
+    /// 宽度（像素）
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// 高度（像素）
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 每行的字节数
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// 按行排列的像素数据，每个像素依次为 R、G、B
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 获取指定坐标的像素，超出范围时返回 `None`
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let offset = y as usize * self.stride + x as usize * 3;
+        self.data
+            .get(offset..offset + 3)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+    }
+
+    /// 转换为 [`RgbImage`]
+    pub fn to_rgb_image(&self) -> RgbImage {
+        let row = self.width as usize * 3;
+        let size = row * self.height as usize;
+        let data = if self.stride == row {
+            self.data[..size].to_vec()
+        } else {
+            self.data
+                .chunks(self.stride)
+                .take(self.height as usize)
+                .flat_map(|line| &line[..row])
+                .copied()
+                .collect()
+        };
+        // Frame::new 已经检查过数据长度
+        RgbImage::from_raw(self.width, self.height, data).expect("Frame 的数据长度与尺寸不一致")
+    }
+
+    /// 生成缩略图，保持宽高比，缩放到不超过 `max_width` x `max_height`
+    pub fn thumbnail(&self, max_width: u32, max_height: u32) -> Frame {
+        let image = self.to_rgb_image();
+        let (width, height) = fit(self.width, self.height, max_width, max_height);
+        image::imageops::resize(&image, width, height, FilterType::Triangle).into()
+    }
+
+    /// 保存为 PNG 文件
+    ///
+    /// # Returns
+    /// * `Ok(())` - 保存成功
+    /// * `Err(Error::ImageSaveFailed)` - 编码或者写入文件失败
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        self.to_rgb_image()
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(|source| save_failed(path, source))
+    }
+
+    /// 保存为 JPEG 文件
+    ///
+    /// # Arguments
+    /// * `quality` - 编码质量，范围为 1 到 100，参考 [`DEFAULT_JPEG_QUALITY`]
+    ///
+    /// # Returns
+    /// * `Ok(())` - 保存成功
+    /// * `Err(Error::ImageSaveFailed)` - 编码或者写入文件失败
+    pub fn save_jpeg(&self, path: impl AsRef<Path>, quality: u8) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|err| save_failed(path, err.into()))?;
+        JpegEncoder::new_with_quality(BufWriter::new(file), quality.clamp(1, 100))
+            .encode_image(&self.to_rgb_image())
+            .map_err(|source| save_failed(path, source))
+    }
+}
+
+impl From<RgbImage> for Frame {
+    fn from(image: RgbImage) -> Self {
+        let (width, height) = image.dimensions();
+        Frame {
+            width,
+            height,
+            stride: width as usize * 3,
+            data: image.into_raw()
+        }
+    }
+}
+
+impl From<&Frame> for RgbImage {
+    fn from(frame: &Frame) -> Self {
+        frame.to_rgb_image()
+    }
+}
+
+fn save_failed(path: &Path, source: ImageError) -> Error {
+    Error::ImageSaveFailed {
+        path: path.to_path_buf(),
+        source
+    }
+}
+
+/// 在不超过给定范围的前提下按比例缩放，结果至少为 1x1
+fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    if width <= max_width && height <= max_height {
+        return (width, height);
+    }
+    let scale = f64::min(max_width as f64 / width as f64, max_height as f64 / height as f64);
+    let scaled = |size: u32| ((size as f64 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit(1280, 720, 320, 320), (320, 180));
+        assert_eq!(fit(720, 1280, 320, 320), (180, 320));
+        assert_eq!(fit(100, 50, 320, 320), (100, 50));
+        assert_eq!(fit(1000, 1, 10, 10), (10, 1));
+    }
+
+    #[test]
+    fn test_stride() {
+        // 每行末尾有 2 个字节的填充
+        let frame = Frame::new(2, 2, 8, vec![1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0]).unwrap();
+        assert_eq!(frame.pixel(1, 1), Some([10, 11, 12]));
+        assert_eq!(frame.pixel(2, 0), None);
+        assert_eq!(
+            frame.to_rgb_image().into_raw(),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+        );
+    }
+
+    #[test]
+    fn test_invalid_frame() {
+        // 最后一行可以不包含填充
+        assert!(Frame::new(2, 2, 8, vec![0; 14]).is_ok());
+        assert!(Frame::new(0, 0, 0, Vec::new()).is_ok());
+
+        // stride 小于一行像素的长度
+        let err = Frame::new(2, 2, 4, vec![0; 12]).unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::InvalidFrame);
+        // 数据长度不足
+        assert!(matches!(
+            Frame::new(2, 2, 8, vec![0; 13]),
+            Err(Error::InvalidFrame { len: 13, .. })
+        ));
+        assert!(Frame::new(u32::MAX, 2, usize::MAX, Vec::new()).is_err());
+    }
+}
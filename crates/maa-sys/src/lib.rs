@@ -1,10 +1,24 @@
 mod assistant;
 mod binding;
-mod protocol;
+mod core_api;
+pub mod discovery;
+#[cfg(feature = "image")]
+pub mod frame;
+pub mod profile;
+pub mod protocol;
+pub mod report;
+pub mod resource;
+#[cfg(feature = "tokio")]
+mod stream;
+mod tracker;
 mod types;
 
 pub use assistant::*;
+pub use core_api::*;
 pub use protocol::connection::*;
 pub use protocol::message::*;
 pub use protocol::task;
+#[cfg(feature = "tokio")]
+pub use stream::*;
+pub use tracker::*;
 pub use types::*;
@@ -0,0 +1,379 @@
+//! 任务配置文件
+//!
+//! 配置文件保存一组有序的任务，以及连接方式和实例选项，支持 TOML 和 JSON 两种格式，
+//! 任务参数的字段名与 MaaCore 文档中的一致。通过 [`crate::Assistant::apply_profile`] 应用到实例上。
+//!
+//! # Example
+//! ```toml
+//! name = "daily"
+//!
+//! [connection]
+//! adb_path = "adb"
+//! address = "127.0.0.1:16384"
+//! config = "MuMuEmulator12"
+//! timeout = 30
+//!
+//! [options]
+//! touch_mode = "maatouch"
+//!
+//! [[tasks]]
+//! type = "StartUp"
+//! params = { client_type = "Official", start_game_enabled = true }
+//!
+//! [[tasks]]
+//! type = "Fight"
+//! params = { stage = "1-7", medicine = 3 }
+//! ```
+//!
+//! ```rust,ignore
+//! let profile = Profile::load("daily.toml")?;
+//! let task_ids = assistant.apply_profile(&profile)?;
+//! assistant.start()?;
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::task::{AnyTask, Task};
+use crate::{Connection, ConnectionConfig, Error, InstanceOptions};
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProfileFormat {
+    Toml,
+    Json
+}
+
+impl ProfileFormat {
+    /// 根据文件扩展名判断格式，扩展名不区分大小写
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "toml" => Some(ProfileFormat::Toml),
+            "json" => Some(ProfileFormat::Json),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ProfileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileFormat::Toml => f.write_str("TOML"),
+            ProfileFormat::Json => f.write_str("JSON")
+        }
+    }
+}
+
+/// 配置文件中的 adb 连接
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    /// adb 可执行文件的路径，默认为 `adb`
+    #[serde(default = "default_adb_path")]
+    pub adb_path: String,
+    /// 设备地址，如 `127.0.0.1:16384`
+    pub address: String,
+    /// 连接配置，默认为 [`ConnectionConfig::General`]
+    pub config: Option<ConnectionConfig>,
+    /// 等待连接结束的超时时间，单位为秒，默认为 [`DEFAULT_CONNECT_TIMEOUT`]
+    pub timeout: Option<u64>
+}
+
+/// 应用配置时等待连接结束的默认超时时间
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn default_adb_path() -> String {
+    "adb".to_string()
+}
+
+impl ConnectionProfile {
+    pub fn new(adb_path: impl Into<String>, address: impl Into<String>) -> Self {
+        Self {
+            adb_path: adb_path.into(),
+            address: address.into(),
+            config: None,
+            timeout: None
+        }
+    }
+
+    /// 设置连接配置
+    pub fn with_config(mut self, config: ConnectionConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// 设置等待连接结束的超时时间，精确到秒
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout.as_secs());
+        self
+    }
+
+    /// 等待连接结束的超时时间
+    pub fn timeout(&self) -> Duration {
+        self.timeout.map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_secs)
+    }
+
+    /// 对应的 [`Connection`]
+    pub fn connection(&self) -> Connection {
+        Connection::adb(&self.adb_path, &self.address)
+    }
+}
+
+/// 任务配置
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// 配置名，如 `daily`、`weekend`
+    pub name: Option<String>,
+    /// 连接方式，为空时不连接，使用实例当前的连接
+    pub connection: Option<ConnectionProfile>,
+    /// 实例选项
+    #[serde(default)]
+    pub options: InstanceOptions,
+    /// 按顺序添加的任务
+    #[serde(default)]
+    pub tasks: Vec<AnyTask>
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置配置名
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// 设置连接方式
+    pub fn with_connection(mut self, connection: ConnectionProfile) -> Self {
+        self.connection = Some(connection);
+        self
+    }
+
+    /// 设置实例选项
+    pub fn with_options(mut self, options: InstanceOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 在末尾添加任务
+    pub fn with_task(mut self, task: impl Into<AnyTask>) -> Self {
+        self.tasks.push(task.into());
+        self
+    }
+
+    /// 从文件加载配置，根据扩展名（`.toml`、`.json`）判断格式
+    ///
+    /// # Returns
+    /// * `Ok(Profile)` - 配置
+    /// * `Err(Error::ProfileFormatUnknown)` - 无法根据扩展名判断格式
+    /// * `Err(Error::ProfileIoFailed)` - 读取文件失败
+    /// * `Err(Error::ProfileParseFailed)` - 文件内容格式错误，或者包含未知的任务类型
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let format =
+            ProfileFormat::from_path(path).ok_or_else(|| Error::ProfileFormatUnknown(path.to_path_buf()))?;
+        let content = std::fs::read_to_string(path).map_err(|source| io_failed(path, source))?;
+        Self::parse(&content, format)
+    }
+
+    /// 保存配置到文件，根据扩展名（`.toml`、`.json`）判断格式
+    ///
+    /// # Returns
+    /// * `Ok(())` - 保存成功
+    /// * `Err(Error::ProfileFormatUnknown)` - 无法根据扩展名判断格式
+    /// * `Err(Error::ProfileSerializeFailed)` - 配置无法使用该格式表示
+    /// * `Err(Error::ProfileIoFailed)` - 写入文件失败
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let format =
+            ProfileFormat::from_path(path).ok_or_else(|| Error::ProfileFormatUnknown(path.to_path_buf()))?;
+        let content = self.to_string(format)?;
+        std::fs::write(path, content).map_err(|source| io_failed(path, source))
+    }
+
+    /// 解析配置
+    pub fn parse(content: &str, format: ProfileFormat) -> Result<Self, Error> {
+        let parse_failed = |message: String| Error::ProfileParseFailed { format, message };
+        match format {
+            ProfileFormat::Toml => toml::from_str(content).map_err(|err| parse_failed(err.to_string())),
+            ProfileFormat::Json => serde_json::from_str(content).map_err(|err| parse_failed(err.to_string()))
+        }
+    }
+
+    /// 序列化配置
+    pub fn to_string(&self, format: ProfileFormat) -> Result<String, Error> {
+        let serialize_failed = |message: String| Error::ProfileSerializeFailed { format, message };
+        match format {
+            ProfileFormat::Toml => {
+                toml::to_string_pretty(self).map_err(|err| serialize_failed(err.to_string()))
+            },
+            ProfileFormat::Json => {
+                serde_json::to_string_pretty(self).map_err(|err| serialize_failed(err.to_string()))
+            },
+        }
+    }
+
+    /// 检查配置是否可以应用
+    ///
+    /// # Returns
+    /// * `Ok(())` - 配置有效
+    /// * `Err(Error::ProfileInvalid)` - 配置无效，包含所有发现的问题
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = Vec::new();
+        if let Some(connection) = &self.connection {
+            if connection.adb_path.trim().is_empty() {
+                problems.push("connection.adb_path 不能为空".to_string());
+            }
+            if connection.address.trim().is_empty() {
+                problems.push("connection.address 不能为空".to_string());
+            }
+            if connection.timeout == Some(0) {
+                problems.push("connection.timeout 必须大于 0".to_string());
+            }
+        }
+        for (index, task) in self.tasks.iter().enumerate() {
+            if let Err(err) = task.validate() {
+                problems.push(format!("tasks[{index}] {err}"));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ProfileInvalid { problems })
+        }
+    }
+}
+
+fn io_failed(path: &Path, source: std::io::Error) -> Error {
+    Error::ProfileIoFailed {
+        path: PathBuf::from(path),
+        source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{FightTask, RecruitTask, StartUpTask};
+    use crate::TouchMode;
+
+    const DAILY: &str = r#"
+name = "daily"
+
+[connection]
+address = "127.0.0.1:16384"
+config = "MuMuEmulator12"
+
+[options]
+touch_mode = "maatouch"
+kill_adb_on_exit = true
+
+[[tasks]]
+type = "StartUp"
+params = { client_type = "Official", start_game_enabled = true }
+
+[[tasks]]
+type = "Fight"
+params = { stage = "1-7", medicine = 3, drops = { "30011" = 10 } }
+
+[[tasks]]
+type = "Recruit"
+params = { select = [4], confirm = [3, 4], times = 4 }
+"#;
+
+    #[test]
+    fn test_parse_toml() {
+        let profile = Profile::parse(DAILY, ProfileFormat::Toml).unwrap();
+        assert_eq!(profile.name.as_deref(), Some("daily"));
+
+        let connection = profile.connection.as_ref().unwrap();
+        assert_eq!(connection.adb_path, "adb");
+        assert_eq!(connection.config, Some(ConnectionConfig::MuMuEmulator12));
+        assert_eq!(connection.timeout(), DEFAULT_CONNECT_TIMEOUT);
+        assert_eq!(profile.options.touch_mode, Some(TouchMode::MaaTouch));
+        assert_eq!(profile.options.kill_adb_on_exit, Some(true));
+
+        let task_types: Vec<_> = profile.tasks.iter().map(|task| task.task_type()).collect();
+        assert_eq!(task_types, ["StartUp", "Fight", "Recruit"]);
+        assert_eq!(
+            profile.tasks[1].to_json(),
+            r#"{"stage":"1-7","medicine":3,"drops":{"30011":10}}"#
+        );
+        assert!(profile.validate().is_ok());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let profile = Profile::new()
+            .with_name("weekend")
+            .with_connection(
+                ConnectionProfile::new("adb", "emulator-5554").with_config(ConnectionConfig::AVD)
+            )
+            .with_task(StartUpTask::builder().client_type("Official").build())
+            .with_task(FightTask::builder().stage("CE-6").times(3).build())
+            .with_task(RecruitTask::builder().select(vec![4]).confirm(vec![3, 4]).build());
+        let json = serde_json::to_string(&profile).unwrap();
+
+        for format in [ProfileFormat::Toml, ProfileFormat::Json] {
+            let content = profile.to_string(format).unwrap();
+            let parsed = Profile::parse(&content, format).unwrap();
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), json, "{format}");
+        }
+    }
+
+    #[test]
+    fn test_parse_failed() {
+        let err = Profile::parse("[[tasks]]\ntype = \"Unknown\"", ProfileFormat::Toml).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ProfileParseFailed {
+                format: ProfileFormat::Toml,
+                ..
+            }
+        ));
+
+        // Recruit 缺少必选字段
+        let err = Profile::parse(
+            r#"{ "tasks": [{ "type": "Recruit", "params": {} }] }"#,
+            ProfileFormat::Json
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("select"), "{err}");
+    }
+
+    #[test]
+    fn test_validate() {
+        let profile = Profile::new()
+            .with_connection(ConnectionProfile::new("", " ").with_timeout(Duration::ZERO))
+            .with_task(FightTask::builder().stage("1-7").build())
+            .with_task(FightTask::builder().medicine(1000).build());
+        let Err(Error::ProfileInvalid { problems }) = profile.validate() else {
+            panic!("配置应该无效");
+        };
+        assert_eq!(problems.len(), 4);
+        assert_eq!(problems[2], "connection.timeout 必须大于 0");
+        assert_eq!(
+            problems[3],
+            "tasks[1] Fight 任务参数不合法: medicine 1000 超出范围 0..=999"
+        );
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ProfileFormat::from_path("daily.TOML"), Some(ProfileFormat::Toml));
+        assert_eq!(
+            ProfileFormat::from_path("dir/event.json"),
+            Some(ProfileFormat::Json)
+        );
+        assert_eq!(ProfileFormat::from_path("daily.yaml"), None);
+        assert_eq!(ProfileFormat::from_path("daily"), None);
+    }
+}
@@ -1,5 +1,8 @@
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-#[derive(Clone)]
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Connection {
     /// 使用 ADB 连接
     Adb(String, String),
@@ -39,3 +42,138 @@ impl Connection {
         }
     }
 }
+
+/// MaaCore 的连接配置预设，对应资源目录下 `config.json` 中 `connection` 的配置名
+///
+/// 序列化为配置名字符串，未知的配置名会作为 [`ConnectionConfig::Custom`] 保留
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ConnectionConfig {
+    /// 通用配置
+    #[default]
+    General,
+    /// 兼容模式
+    Compatible,
+    /// 截图失败时不报错的通用配置
+    GeneralWithoutScreencapErr,
+    /// 使用第二分辨率
+    SecondResolution,
+    /// 兼容 POSIX shell
+    CompatPOSIXShell,
+    /// macOS 兼容模式
+    CompatMac,
+    /// 蓝叠模拟器
+    BlueStacks,
+    /// MuMu 模拟器
+    MuMuEmulator,
+    /// MuMu 模拟器 12
+    MuMuEmulator12,
+    /// 雷电模拟器
+    LDPlayer,
+    /// 夜神模拟器
+    Nox,
+    /// 逍遥模拟器
+    XYAZ,
+    /// Windows Subsystem for Android
+    WSA,
+    /// Android Virtual Device
+    AVD,
+    /// Waydroid
+    Waydroid,
+    /// 其他自定义的配置名
+    Custom(String)
+}
+
+impl ConnectionConfig {
+    /// 传给 MaaCore 的配置名
+    pub fn as_str(&self) -> &str {
+        match self {
+            ConnectionConfig::General => "General",
+            ConnectionConfig::Compatible => "Compatible",
+            ConnectionConfig::GeneralWithoutScreencapErr => "GeneralWithoutScreencapErr",
+            ConnectionConfig::SecondResolution => "SecondResolution",
+            ConnectionConfig::CompatPOSIXShell => "CompatPOSIXShell",
+            ConnectionConfig::CompatMac => "CompatMac",
+            ConnectionConfig::BlueStacks => "BlueStacks",
+            ConnectionConfig::MuMuEmulator => "MuMuEmulator",
+            ConnectionConfig::MuMuEmulator12 => "MuMuEmulator12",
+            ConnectionConfig::LDPlayer => "LDPlayer",
+            ConnectionConfig::Nox => "Nox",
+            ConnectionConfig::XYAZ => "XYAZ",
+            ConnectionConfig::WSA => "WSA",
+            ConnectionConfig::AVD => "AVD",
+            ConnectionConfig::Waydroid => "Waydroid",
+            ConnectionConfig::Custom(name) => name
+        }
+    }
+}
+
+impl From<&str> for ConnectionConfig {
+    fn from(name: &str) -> Self {
+        match name {
+            "General" => ConnectionConfig::General,
+            "Compatible" => ConnectionConfig::Compatible,
+            "GeneralWithoutScreencapErr" => ConnectionConfig::GeneralWithoutScreencapErr,
+            "SecondResolution" => ConnectionConfig::SecondResolution,
+            "CompatPOSIXShell" => ConnectionConfig::CompatPOSIXShell,
+            "CompatMac" => ConnectionConfig::CompatMac,
+            "BlueStacks" => ConnectionConfig::BlueStacks,
+            "MuMuEmulator" => ConnectionConfig::MuMuEmulator,
+            "MuMuEmulator12" => ConnectionConfig::MuMuEmulator12,
+            "LDPlayer" => ConnectionConfig::LDPlayer,
+            "Nox" => ConnectionConfig::Nox,
+            "XYAZ" => ConnectionConfig::XYAZ,
+            "WSA" => ConnectionConfig::WSA,
+            "AVD" => ConnectionConfig::AVD,
+            "Waydroid" => ConnectionConfig::Waydroid,
+            name => ConnectionConfig::Custom(name.to_string())
+        }
+    }
+}
+
+impl From<String> for ConnectionConfig {
+    fn from(name: String) -> Self {
+        ConnectionConfig::from(name.as_str())
+    }
+}
+
+impl From<ConnectionConfig> for String {
+    fn from(config: ConnectionConfig) -> Self {
+        match config {
+            ConnectionConfig::Custom(name) => name,
+            config => config.as_str().to_string()
+        }
+    }
+}
+
+impl fmt::Display for ConnectionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_config() {
+        assert_eq!(ConnectionConfig::from("BlueStacks"), ConnectionConfig::BlueStacks);
+        assert_eq!(
+            ConnectionConfig::from("Bluestacks"),
+            ConnectionConfig::Custom("Bluestacks".to_string())
+        );
+        assert_eq!(ConnectionConfig::MuMuEmulator12.to_string(), "MuMuEmulator12");
+
+        let configs = vec![
+            ConnectionConfig::CompatMac,
+            ConnectionConfig::Custom("MyEmulator".to_string()),
+        ];
+        let json = serde_json::to_string(&configs).unwrap();
+        assert_eq!(json, r#"["CompatMac","MyEmulator"]"#);
+        assert_eq!(
+            serde_json::from_str::<Vec<ConnectionConfig>>(&json).unwrap(),
+            configs
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! 任务参数的描述
+//!
+//! 由 `GenerateTask` 为每个任务生成，包含字段名、类型、是否必选、文档注释和可选值等信息，
+//! 可以用来生成任务配置的表单。启用 `schema` feature 后还可以生成 JSON Schema。
+
+use serde::Serialize;
+
+/// 字段的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "items", rename_all = "snake_case")]
+pub enum FieldKind {
+    Boolean,
+    Integer,
+    Number,
+    String,
+    /// 数组，包含元素的类型
+    Array(&'static FieldKind),
+    /// 以字符串为键的对象，包含值的类型
+    Map(&'static FieldKind),
+    /// 任意 JSON
+    Any
+}
+
+/// 序列化为字符串或整数的字段类型，如 [`crate::task::ClientType`]
+///
+/// `GenerateTask` 生成描述时，基本类型、`String`、`Vec`、`HashMap` 以外的字段类型都需要实现
+pub trait FieldEnum {
+    /// 序列化后的类型
+    const KIND: FieldKind;
+    /// 所有已知的取值，为空时不限制，字段也可以使用其他取值
+    const VALUES: &'static [&'static str];
+}
+
+/// 字段的取值范围，由 `#[task(range = ...)]` 声明
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FieldRange {
+    /// 下限（包含）
+    pub start: Option<f64>,
+    /// 上限
+    pub end: Option<f64>,
+    /// 上限是否包含在范围内
+    pub inclusive: bool
+}
+
+/// 任务参数中一个字段的描述
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FieldDescriptor {
+    /// 字段名，与任务参数 JSON 中的键相同
+    pub name: &'static str,
+    /// Rust 中的类型，不包含外层的 `Option`，如 `i32`、`Vec<String>`
+    pub ty: &'static str,
+    /// 字段的类型
+    pub kind: FieldKind,
+    /// 是否必选，即任务参数 JSON 中是否必须包含该字段
+    ///
+    /// 非 `Option` 字段即使有 `#[task(default = ...)]` 也是必选的，需要 `#[serde(default)]` 才可以省略
+    pub required: bool,
+    /// 文档注释
+    pub doc: &'static str,
+    /// 可选值，由 `#[task(values = [...])]` 声明，没有声明时为枚举类型的所有已知取值，为空时不限制
+    pub values: &'static [&'static str],
+    /// `values` 是否只是已知的取值，为 `true` 时也接受其他取值
+    ///
+    /// 枚举类型都可以通过 `Other` 表示其他取值，`#[task(values = [...])]` 声明的可选值会在
+    /// [`crate::task::Task::validate`] 中检查
+    pub open: bool,
+    /// builder 中的默认值，由 `#[task(default = ...)]` 声明，字符串不包含引号，如 `Official`、`3`
+    pub default: Option<&'static str>,
+    /// 取值范围
+    pub range: Option<FieldRange>
+}
+
+/// 任务参数的描述
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TaskDescriptor {
+    /// 任务类型，如 `Fight`
+    pub task_type: &'static str,
+    /// 任务名，如 `刷理智`
+    pub name: &'static str,
+    /// 所有字段，顺序与结构体中的一致
+    pub fields: &'static [FieldDescriptor]
+}
+
+impl TaskDescriptor {
+    /// 根据字段名查找字段
+    pub fn field(&self, name: &str) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// 所有必选字段
+    pub fn required_fields(&self) -> impl Iterator<Item = &'static FieldDescriptor> {
+        self.fields.iter().filter(|field| field.required)
+    }
+
+    /// 任务参数的 JSON Schema
+    #[cfg(feature = "schema")]
+    pub fn json_schema(&self) -> serde_json::Value {
+        let properties: serde_json::Map<String, serde_json::Value> = self
+            .fields
+            .iter()
+            .map(|field| (field.name.to_string(), field.json_schema()))
+            .collect();
+        let required: Vec<&str> = self.required_fields().map(|field| field.name).collect();
+        serde_json::json!({
+            "title": self.name,
+            "type": "object",
+            "properties": properties,
+            "required": required
+        })
+    }
+}
+
+#[cfg(feature = "schema")]
+impl FieldKind {
+    fn json_schema(&self) -> serde_json::Value {
+        use serde_json::json;
+
+        match self {
+            FieldKind::Boolean => json!({ "type": "boolean" }),
+            FieldKind::Integer => json!({ "type": "integer" }),
+            FieldKind::Number => json!({ "type": "number" }),
+            FieldKind::String => json!({ "type": "string" }),
+            FieldKind::Array(items) => json!({ "type": "array", "items": items.json_schema() }),
+            FieldKind::Map(values) => {
+                json!({ "type": "object", "additionalProperties": values.json_schema() })
+            },
+            FieldKind::Any => json!({})
+        }
+    }
+}
+
+#[cfg(feature = "schema")]
+impl FieldDescriptor {
+    fn json_schema(&self) -> serde_json::Value {
+        let mut schema = self.kind.json_schema();
+        let object = schema.as_object_mut().expect("字段的 schema 总是对象");
+        if !self.doc.is_empty() {
+            object.insert("description".to_string(), self.doc.into());
+        }
+        if !self.values.is_empty() {
+            // 数字类型的可选值按数字输出，也接受其他取值时只作为示例
+            let values = self
+                .values
+                .iter()
+                .map(|value| match self.kind {
+                    FieldKind::Integer | FieldKind::Number => {
+                        serde_json::from_str(value).unwrap_or_else(|_| (*value).into())
+                    },
+                    _ => (*value).into()
+                })
+                .collect();
+            let key = if self.open { "examples" } else { "enum" };
+            object.insert(key.to_string(), serde_json::Value::Array(values));
+        }
+        if let Some(default) = self.default {
+            let default = match self.kind {
+                FieldKind::String => default.into(),
+                _ => serde_json::from_str(default).unwrap_or_else(|_| default.into())
+            };
+            object.insert("default".to_string(), default);
+        }
+        if let Some(range) = &self.range {
+            if let Some(start) = range.start {
+                object.insert("minimum".to_string(), self.number(start));
+            }
+            if let Some(end) = range.end {
+                let key = if range.inclusive {
+                    "maximum"
+                } else {
+                    "exclusiveMaximum"
+                };
+                object.insert(key.to_string(), self.number(end));
+            }
+        }
+        schema
+    }
+
+    /// 整数字段的范围按整数输出
+    fn number(&self, value: f64) -> serde_json::Value {
+        match self.kind {
+            FieldKind::Integer => (value as i64).into(),
+            _ => value.into()
+        }
+    }
+}
@@ -1,6 +1,13 @@
 use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use hashbrown::HashMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Message {
     /* Global Info */
     InternalError = 0,     // 内部错误
@@ -31,42 +38,1137 @@ impl Display for Message {
     }
 }
 
+impl Message {
+    /// 根据消息 ID 获取消息类型，未知的消息 ID 返回 `None`
+    pub fn from_id(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Message::InternalError),
+            1 => Some(Message::InitFailed),
+            2 => Some(Message::ConnectionInfo),
+            3 => Some(Message::AllTasksCompleted),
+            4 => Some(Message::AsyncCallInfo),
+            5 => Some(Message::Destroyed),
+            10000 => Some(Message::TaskChainError),
+            10001 => Some(Message::TaskChainStart),
+            10002 => Some(Message::TaskChainCompleted),
+            10003 => Some(Message::TaskChainExtraInfo),
+            10004 => Some(Message::TaskChainStopped),
+            20000 => Some(Message::SubTaskError),
+            20001 => Some(Message::SubTaskStart),
+            20002 => Some(Message::SubTaskCompleted),
+            20003 => Some(Message::SubTaskExtraInfo),
+            20004 => Some(Message::SubTaskStopped),
+            _ => None
+        }
+    }
+}
+
 /// 回调处理器
 ///
 /// 因为 Rust 会对零大小的分配优化，直接使用 Rust 的函数类型会导致分配失败，指针永远只能得到 `0x1`
 /// 所以需要使用一个非空结构体来包装回调函数
+#[derive(Default)]
 pub struct Processor {
-    pub callback: Box<dyn FnMut(Message, serde_json::Value) + Send>
+    callback: Option<Callback>,
+    /// 内部使用的事件监听器，如事件流和任务跟踪
+    listeners: Vec<Listener>,
+    /// 记录回调函数和监听器中的 panic，由 Assistant 设置为 [`crate::CoreApi::log`]
+    logger: Option<Logger>
+}
+
+pub(crate) type Listener = Box<dyn FnMut(&Event) + Send>;
+pub(crate) type Logger = Box<dyn Fn(&str) + Send>;
+
+enum Callback {
+    /// 原始的 `(Message, serde_json::Value)` 形式回调
+    Raw(Box<dyn FnMut(Message, Value) + Send>),
+    /// 解析后的 [`Event`] 回调
+    Event(Box<dyn FnMut(Event) + Send>)
 }
 
 impl Processor {
-    pub fn from(callback: impl FnMut(Message, serde_json::Value) + Send + 'static) -> Self {
+    pub fn from(callback: impl FnMut(Message, Value) + Send + 'static) -> Self {
+        Self {
+            callback: Some(Callback::Raw(Box::new(callback))),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_event(callback: impl FnMut(Event) + Send + 'static) -> Self {
         Self {
-            callback: Box::new(callback)
+            callback: Some(Callback::Event(Box::new(callback))),
+            ..Default::default()
+        }
+    }
+
+    /// 添加事件监听器，监听器会先于回调函数收到事件
+    pub(crate) fn with_listener(mut self, listener: impl FnMut(&Event) + Send + 'static) -> Self {
+        self.listeners.push(Box::new(listener));
+        self
+    }
+
+    /// 设置记录 panic 的日志函数
+    pub(crate) fn with_logger(mut self, logger: impl Fn(&str) + Send + 'static) -> Self {
+        self.logger = Some(Box::new(logger));
+        self
+    }
+
+    /// 将一条回调消息分发给监听器和回调函数
+    ///
+    /// 回调函数中的 panic 会被捕获，写入 MaaCore 的日志，并以 [`Event::CallbackPanicked`] 通知监听器。
+    /// 监听器中的 panic 同样会被捕获并写入日志，不会影响其他监听器
+    pub fn dispatch(&mut self, msg_id: i32, details: Value) {
+        self.deliver(msg_id, details, None);
+    }
+
+    /// 将一条未经解析的回调消息分发给监听器和回调函数
+    ///
+    /// 无法解析为 JSON 的消息会作为 [`Event::Malformed`] 分发，
+    /// 原始形式的回调函数收到的 `details` 为按 UTF-8 有损转换后的字符串
+    pub fn dispatch_bytes(&mut self, msg_id: i32, details: &[u8]) {
+        match serde_json::from_slice(details) {
+            Ok(details) => self.dispatch(msg_id, details),
+            Err(_) => {
+                let lossy = Value::String(String::from_utf8_lossy(details).into_owned());
+                let event = Event::Malformed {
+                    msg_id,
+                    bytes: details.to_vec()
+                };
+                self.deliver(msg_id, lossy, Some(event));
+            }
+        }
+    }
+
+    fn deliver(&mut self, msg_id: i32, details: Value, mut event: Option<Event>) {
+        if !self.listeners.is_empty() {
+            let event = event.get_or_insert_with(|| Event::parse(msg_id, details.clone()));
+            self.notify(event);
+        }
+
+        let callback = &mut self.callback;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| match callback {
+            Some(Callback::Raw(callback)) => callback(Message::from(msg_id), details),
+            Some(Callback::Event(callback)) => {
+                callback(event.unwrap_or_else(|| Event::parse(msg_id, details)))
+            },
+            None => {}
+        }));
+
+        if let Err(payload) = result {
+            let message = panic_message(payload);
+            self.log(&format!("处理消息 {msg_id} 时回调函数 panic: {message}"));
+            self.notify(&Event::CallbackPanicked { msg_id, message });
         }
     }
+
+    fn notify(&mut self, event: &Event) {
+        for index in 0..self.listeners.len() {
+            // 内部监听器同样不能让 panic 跨越 FFI 边界
+            let listener = &mut self.listeners[index];
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| listener(event))) {
+                let message = panic_message(payload);
+                self.log(&format!(
+                    "处理 {:?} 事件时监听器 panic: {message}",
+                    event.message()
+                ));
+            }
+        }
+    }
+
+    fn log(&self, message: &str) {
+        if let Some(logger) = &self.logger {
+            // 日志函数本身的 panic 无法再报告，直接忽略
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| logger(message)));
+        }
+    }
+}
+
+/// panic 时传入的消息，不是字符串时为空
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
 }
 
 impl From<i32> for Message {
     fn from(value: i32) -> Self {
-        match value {
-            0 => Message::InternalError,
-            1 => Message::InitFailed,
-            2 => Message::ConnectionInfo,
-            3 => Message::AllTasksCompleted,
-            4 => Message::AsyncCallInfo,
-            5 => Message::Destroyed,
-            10000 => Message::TaskChainError,
-            10001 => Message::TaskChainStart,
-            10002 => Message::TaskChainCompleted,
-            10003 => Message::TaskChainExtraInfo,
-            10004 => Message::TaskChainStopped,
-            20000 => Message::SubTaskError,
-            20001 => Message::SubTaskStart,
-            20002 => Message::SubTaskCompleted,
-            20003 => Message::SubTaskExtraInfo,
-            20004 => Message::SubTaskStopped,
-            _ => Message::InternalError // 对于未知的消息ID，返回内部错误
+        // 对于未知的消息ID，返回内部错误
+        Message::from_id(value).unwrap_or(Message::InternalError)
+    }
+}
+
+/// 回调事件
+///
+/// 每种 [`Message`] 对应一个变体，`details` 会被解析为对应的结构体。
+/// 未知的消息 ID 或者无法解析的 `details` 会保留为 [`Event::Raw`]。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "msg", content = "details")]
+pub enum Event {
+    /// 内部错误
+    InternalError,
+    /// 初始化失败
+    InitFailed(InitFailed),
+    /// 连接相关信息
+    ConnectionInfo(ConnectionInfo),
+    /// 全部任务完成
+    AllTasksCompleted(AllTasksCompleted),
+    /// 外部异步调用信息
+    AsyncCallInfo(AsyncCallInfo),
+    /// 实例已销毁
+    Destroyed,
+    /// 任务链执行/识别错误
+    TaskChainError(TaskChainInfo),
+    /// 任务链开始
+    TaskChainStart(TaskChainInfo),
+    /// 任务链完成
+    TaskChainCompleted(TaskChainInfo),
+    /// 任务链额外信息
+    TaskChainExtraInfo(TaskChainExtraInfo),
+    /// 任务链手动停止
+    TaskChainStopped(TaskChainInfo),
+    /// 原子任务执行/识别错误
+    SubTaskError(SubTaskInfo),
+    /// 原子任务开始
+    SubTaskStart(SubTaskInfo),
+    /// 原子任务完成
+    SubTaskCompleted(SubTaskInfo),
+    /// 原子任务额外信息
+    SubTaskExtraInfo(SubTaskExtraInfo),
+    /// 原子任务手动停止
+    SubTaskStopped(SubTaskInfo),
+    /// 无法识别的消息，保留原始的消息 ID 和 JSON
+    Raw { msg_id: i32, details: Value },
+    /// `details` 不是合法的 UTF-8 JSON，保留原始的消息 ID 和字节
+    Malformed { msg_id: i32, bytes: Vec<u8> },
+    /// 处理该消息时回调函数发生了 panic，由 maa-sys 产生，只会发给监听器，同时会写入 MaaCore 的日志
+    CallbackPanicked { msg_id: i32, message: String }
+}
+
+fn parse_details<T: DeserializeOwned>(details: &Value) -> Option<T> {
+    T::deserialize(details).ok()
+}
+
+impl Event {
+    /// 由消息 ID 和 JSON 详情解析事件
+    pub fn parse(msg_id: i32, details: Value) -> Self {
+        let event = match Message::from_id(msg_id) {
+            Some(Message::InternalError) => Some(Event::InternalError),
+            Some(Message::InitFailed) => parse_details(&details).map(Event::InitFailed),
+            Some(Message::ConnectionInfo) => parse_details(&details).map(Event::ConnectionInfo),
+            Some(Message::AllTasksCompleted) => parse_details(&details).map(Event::AllTasksCompleted),
+            Some(Message::AsyncCallInfo) => parse_details(&details).map(Event::AsyncCallInfo),
+            Some(Message::Destroyed) => Some(Event::Destroyed),
+            Some(Message::TaskChainError) => parse_details(&details).map(Event::TaskChainError),
+            Some(Message::TaskChainStart) => parse_details(&details).map(Event::TaskChainStart),
+            Some(Message::TaskChainCompleted) => parse_details(&details).map(Event::TaskChainCompleted),
+            Some(Message::TaskChainExtraInfo) => parse_details(&details).map(Event::TaskChainExtraInfo),
+            Some(Message::TaskChainStopped) => parse_details(&details).map(Event::TaskChainStopped),
+            Some(Message::SubTaskError) => parse_details(&details).map(Event::SubTaskError),
+            Some(Message::SubTaskStart) => parse_details(&details).map(Event::SubTaskStart),
+            Some(Message::SubTaskCompleted) => parse_details(&details).map(Event::SubTaskCompleted),
+            Some(Message::SubTaskExtraInfo) => parse_details(&details).map(Event::SubTaskExtraInfo),
+            Some(Message::SubTaskStopped) => parse_details(&details).map(Event::SubTaskStopped),
+            None => None
+        };
+        event.unwrap_or(Event::Raw { msg_id, details })
+    }
+
+    /// 事件对应的消息类型
+    pub fn message(&self) -> Message {
+        match self {
+            Event::InternalError => Message::InternalError,
+            Event::InitFailed(_) => Message::InitFailed,
+            Event::ConnectionInfo(_) => Message::ConnectionInfo,
+            Event::AllTasksCompleted(_) => Message::AllTasksCompleted,
+            Event::AsyncCallInfo(_) => Message::AsyncCallInfo,
+            Event::Destroyed => Message::Destroyed,
+            Event::TaskChainError(_) => Message::TaskChainError,
+            Event::TaskChainStart(_) => Message::TaskChainStart,
+            Event::TaskChainCompleted(_) => Message::TaskChainCompleted,
+            Event::TaskChainExtraInfo(_) => Message::TaskChainExtraInfo,
+            Event::TaskChainStopped(_) => Message::TaskChainStopped,
+            Event::SubTaskError(_) => Message::SubTaskError,
+            Event::SubTaskStart(_) => Message::SubTaskStart,
+            Event::SubTaskCompleted(_) => Message::SubTaskCompleted,
+            Event::SubTaskExtraInfo(_) => Message::SubTaskExtraInfo,
+            Event::SubTaskStopped(_) => Message::SubTaskStopped,
+            Event::Raw { msg_id, .. }
+            | Event::Malformed { msg_id, .. }
+            | Event::CallbackPanicked { msg_id, .. } => Message::from(*msg_id)
+        }
+    }
+
+    /// 事件所属的任务 ID，仅任务链和原子任务相关的事件才有
+    pub fn task_id(&self) -> Option<i32> {
+        match self {
+            Event::TaskChainError(info)
+            | Event::TaskChainStart(info)
+            | Event::TaskChainCompleted(info)
+            | Event::TaskChainStopped(info) => Some(info.taskid),
+            Event::TaskChainExtraInfo(info) => info.taskid,
+            Event::SubTaskError(info)
+            | Event::SubTaskStart(info)
+            | Event::SubTaskCompleted(info)
+            | Event::SubTaskStopped(info) => info.taskid,
+            Event::SubTaskExtraInfo(info) => info.taskid,
+            _ => None
+        }
+    }
+}
+
+/// 初始化失败的详情
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitFailed {
+    /// 错误类型
+    pub what: String,
+    /// 错误原因
+    pub why: String,
+    /// 错误详情
+    #[serde(default)]
+    pub details: Value
+}
+
+/// 全部任务完成的详情
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllTasksCompleted {
+    /// 最后一个任务链
+    pub taskchain: String,
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 已经运行过的任务 ID
+    #[serde(default)]
+    pub finished_tasks: Vec<i32>
+}
+
+/// 外部异步调用信息
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncCallInfo {
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 调用类型，如 "Connect"、"Click"、"Screencap"
+    pub what: String,
+    /// 异步调用 ID
+    pub async_call_id: i32,
+    /// 调用结果
+    pub details: AsyncCallDetails
+}
+
+/// 外部异步调用的结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AsyncCallDetails {
+    /// 调用是否成功
+    pub ret: bool,
+    /// 耗时，单位毫秒
+    pub cost: i64
+}
+
+/// 任务链信息，用于开始、完成、错误和手动停止
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskChainInfo {
+    /// 任务链名，即任务类型
+    pub taskchain: String,
+    /// 任务 ID
+    pub taskid: i32,
+    /// 设备唯一码
+    pub uuid: Option<String>
+}
+
+/// 任务链额外信息
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskChainExtraInfo {
+    /// 任务链名，即任务类型
+    pub taskchain: Option<String>,
+    /// 任务 ID
+    pub taskid: Option<i32>,
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 其余字段
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>
+}
+
+/// 原子任务信息，用于开始、完成、错误和手动停止
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubTaskInfo {
+    /// 原子任务名，如 "ProcessTask"
+    pub subtask: Option<String>,
+    /// 原子任务符号名
+    pub class: Option<String>,
+    /// 所属的任务链
+    pub taskchain: String,
+    /// 所属的任务 ID
+    pub taskid: Option<i32>,
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 原子任务详情
+    #[serde(default)]
+    pub details: Value
+}
+
+impl SubTaskInfo {
+    /// `ProcessTask` 当前执行的任务名，即 `details.task`
+    pub fn task(&self) -> Option<&str> {
+        self.details.get("task").and_then(Value::as_str)
+    }
+}
+
+/// 连接相关信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawConnectionInfo", into = "RawConnectionInfo")]
+pub struct ConnectionInfo {
+    /// 信息类型
+    pub what: ConnectionEvent,
+    /// 原因
+    pub why: Option<String>,
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 原始详情
+    pub details: Value
+}
+
+impl ConnectionInfo {
+    /// 使用的 adb 路径
+    pub fn adb(&self) -> Option<&str> {
+        self.details.get("adb").and_then(Value::as_str)
+    }
+
+    /// 连接地址
+    pub fn address(&self) -> Option<&str> {
+        self.details.get("address").and_then(Value::as_str)
+    }
+
+    /// 连接配置
+    pub fn config(&self) -> Option<&str> {
+        self.details.get("config").and_then(Value::as_str)
+    }
+}
+
+/// 连接信息的类型，即 `what` 字段
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionEvent {
+    /// 已连接
+    Connected,
+    /// 已获取到设备唯一码
+    UuidGot,
+    /// 分辨率不被支持
+    UnsupportedResolution,
+    /// 分辨率获取错误
+    ResolutionError,
+    /// 已获取到分辨率
+    ResolutionGot(Resolution),
+    /// 连接断开，正在重连
+    Reconnecting(Reconnecting),
+    /// 重连成功
+    Reconnected,
+    /// 连接断开且重试失败
+    Disconnect,
+    /// 连接失败
+    ConnectFailed,
+    /// 截图失败
+    ScreencapFailed,
+    /// 不支持当前的触控模式
+    TouchModeNotAvailable,
+    /// 最快的截图方式
+    FastestWayToScreencap(ScreencapMethod),
+    /// 截图耗时统计
+    ScreencapCost(ScreencapCost),
+    /// 未知的类型
+    Other(String)
+}
+
+impl ConnectionEvent {
+    fn parse(what: &str, details: &Value) -> Self {
+        let event = match what {
+            "Connected" => Some(Self::Connected),
+            "UuidGot" => Some(Self::UuidGot),
+            "UnsupportedResolution" => Some(Self::UnsupportedResolution),
+            "ResolutionError" => Some(Self::ResolutionError),
+            "ResolutionGot" => parse_details(details).map(Self::ResolutionGot),
+            "Reconnecting" => parse_details(details).map(Self::Reconnecting),
+            "Reconnected" => Some(Self::Reconnected),
+            "Disconnect" => Some(Self::Disconnect),
+            "ConnectFailed" => Some(Self::ConnectFailed),
+            "ScreencapFailed" => Some(Self::ScreencapFailed),
+            "TouchModeNotAvailable" => Some(Self::TouchModeNotAvailable),
+            "FastestWayToScreencap" => parse_details(details).map(Self::FastestWayToScreencap),
+            "ScreencapCost" => parse_details(details).map(Self::ScreencapCost),
+            _ => None
+        };
+        event.unwrap_or_else(|| Self::Other(what.to_string()))
+    }
+
+    /// 对应的 `what` 字符串
+    pub fn what(&self) -> &str {
+        match self {
+            Self::Connected => "Connected",
+            Self::UuidGot => "UuidGot",
+            Self::UnsupportedResolution => "UnsupportedResolution",
+            Self::ResolutionError => "ResolutionError",
+            Self::ResolutionGot(_) => "ResolutionGot",
+            Self::Reconnecting(_) => "Reconnecting",
+            Self::Reconnected => "Reconnected",
+            Self::Disconnect => "Disconnect",
+            Self::ConnectFailed => "ConnectFailed",
+            Self::ScreencapFailed => "ScreencapFailed",
+            Self::TouchModeNotAvailable => "TouchModeNotAvailable",
+            Self::FastestWayToScreencap(_) => "FastestWayToScreencap",
+            Self::ScreencapCost(_) => "ScreencapCost",
+            Self::Other(what) => what
+        }
+    }
+}
+
+/// 设备分辨率
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: i32,
+    pub height: i32
+}
+
+/// 重连信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reconnecting {
+    /// 重连次数
+    pub times: i32
+}
+
+/// 截图方式及耗时
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreencapMethod {
+    /// 截图方式
+    pub method: String,
+    /// 耗时，单位毫秒
+    pub cost: i64
+}
+
+/// 截图耗时统计，单位毫秒
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreencapCost {
+    pub min: i64,
+    pub max: i64,
+    pub avg: i64
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize)]
+struct RawConnectionInfo {
+    what: String,
+    why: Option<String>,
+    uuid: Option<String>,
+    #[serde(default)]
+    details: Value
+}
+
+impl From<RawConnectionInfo> for ConnectionInfo {
+    fn from(raw: RawConnectionInfo) -> Self {
+        Self {
+            what: ConnectionEvent::parse(&raw.what, &raw.details),
+            why: raw.why,
+            uuid: raw.uuid,
+            details: raw.details
         }
     }
 }
+
+impl From<ConnectionInfo> for RawConnectionInfo {
+    fn from(info: ConnectionInfo) -> Self {
+        Self {
+            what: info.what.what().to_string(),
+            why: info.why,
+            uuid: info.uuid,
+            details: info.details
+        }
+    }
+}
+
+/// 原子任务额外信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawSubTaskExtraInfo", into = "RawSubTaskExtraInfo")]
+pub struct SubTaskExtraInfo {
+    /// 所属的任务链
+    pub taskchain: Option<String>,
+    /// 原子任务符号名
+    pub class: Option<String>,
+    /// 所属的任务 ID
+    pub taskid: Option<i32>,
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 信息类型及解析后的详情
+    pub what: ExtraInfo,
+    /// 原始详情
+    pub details: Value
+}
+
+/// 原子任务额外信息的类型，即 `what` 字段
+#[derive(Debug, Clone)]
+pub enum ExtraInfo {
+    /// 关卡掉落信息
+    StageDrops(StageDrops),
+    /// 关卡信息
+    StageInfo(StageName),
+    /// 关卡识别错误
+    StageInfoError,
+    /// 企鹅物流 ID
+    PenguinId(PenguinId),
+    /// 战斗次数
+    FightTimes(FightTimes),
+    /// 使用理智药
+    UseMedicine(UseMedicine),
+    /// 使用源石
+    UseStone(UseStone),
+    /// 不支持的关卡
+    UnsupportedLevel,
+    /// 公招识别到的 Tags
+    RecruitTagsDetected(RecruitTags),
+    /// 公招识别到的特殊 Tag
+    RecruitSpecialTag(RecruitSpecialTag),
+    /// 公招识别结果
+    RecruitResult(RecruitResult),
+    /// 公招刷新了 Tags
+    RecruitTagsRefreshed(RecruitTagsRefreshed),
+    /// 公招选择了 Tags
+    RecruitTagsSelected(RecruitTags),
+    /// 当前公招槽位完成
+    RecruitSlotCompleted,
+    /// 公招识别错误
+    RecruitError,
+    /// 进入设施
+    EnterFacility(FacilityInfo),
+    /// 可用干员不足
+    NotEnoughStaff(FacilityInfo),
+    /// 当前设施的产物
+    ProductOfFacility(ProductOfFacility),
+    /// 仓库识别结果
+    DepotInfo(DepotInfo),
+    /// 干员识别结果
+    OperBoxInfo(OperBoxInfo),
+    /// 未知或无法解析的类型
+    Other(String)
+}
+
+impl ExtraInfo {
+    fn parse(what: &str, details: &Value) -> Self {
+        let info = match what {
+            "StageDrops" => parse_details(details).map(Self::StageDrops),
+            "StageInfo" => parse_details(details).map(Self::StageInfo),
+            "StageInfoError" => Some(Self::StageInfoError),
+            "PenguinId" => parse_details(details).map(Self::PenguinId),
+            "FightTimes" => parse_details(details).map(Self::FightTimes),
+            "UseMedicine" => parse_details(details).map(Self::UseMedicine),
+            "UseStone" => parse_details(details).map(Self::UseStone),
+            "UnsupportedLevel" => Some(Self::UnsupportedLevel),
+            "RecruitTagsDetected" => parse_details(details).map(Self::RecruitTagsDetected),
+            "RecruitSpecialTag" => parse_details(details).map(Self::RecruitSpecialTag),
+            "RecruitResult" => parse_details(details).map(Self::RecruitResult),
+            "RecruitTagsRefreshed" => parse_details(details).map(Self::RecruitTagsRefreshed),
+            "RecruitTagsSelected" => parse_details(details).map(Self::RecruitTagsSelected),
+            "RecruitSlotCompleted" => Some(Self::RecruitSlotCompleted),
+            "RecruitError" => Some(Self::RecruitError),
+            "EnterFacility" => parse_details(details).map(Self::EnterFacility),
+            "NotEnoughStaff" => parse_details(details).map(Self::NotEnoughStaff),
+            "ProductOfFacility" => parse_details(details).map(Self::ProductOfFacility),
+            "DepotInfo" => parse_details(details).map(Self::DepotInfo),
+            "OperBoxInfo" => parse_details(details).map(Self::OperBoxInfo),
+            _ => None
+        };
+        info.unwrap_or_else(|| Self::Other(what.to_string()))
+    }
+
+    /// 对应的 `what` 字符串
+    pub fn what(&self) -> &str {
+        match self {
+            Self::StageDrops(_) => "StageDrops",
+            Self::StageInfo(_) => "StageInfo",
+            Self::StageInfoError => "StageInfoError",
+            Self::PenguinId(_) => "PenguinId",
+            Self::FightTimes(_) => "FightTimes",
+            Self::UseMedicine(_) => "UseMedicine",
+            Self::UseStone(_) => "UseStone",
+            Self::UnsupportedLevel => "UnsupportedLevel",
+            Self::RecruitTagsDetected(_) => "RecruitTagsDetected",
+            Self::RecruitSpecialTag(_) => "RecruitSpecialTag",
+            Self::RecruitResult(_) => "RecruitResult",
+            Self::RecruitTagsRefreshed(_) => "RecruitTagsRefreshed",
+            Self::RecruitTagsSelected(_) => "RecruitTagsSelected",
+            Self::RecruitSlotCompleted => "RecruitSlotCompleted",
+            Self::RecruitError => "RecruitError",
+            Self::EnterFacility(_) => "EnterFacility",
+            Self::NotEnoughStaff(_) => "NotEnoughStaff",
+            Self::ProductOfFacility(_) => "ProductOfFacility",
+            Self::DepotInfo(_) => "DepotInfo",
+            Self::OperBoxInfo(_) => "OperBoxInfo",
+            Self::Other(what) => what
+        }
+    }
+}
+
+/// 关卡掉落信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageDrops {
+    /// 本次掉落
+    #[serde(default)]
+    pub drops: Vec<DropItem>,
+    /// 关卡
+    pub stage: DropStage,
+    /// 行动结束星级
+    #[serde(default)]
+    pub stars: i32,
+    /// 本次任务累计掉落统计
+    #[serde(default)]
+    pub stats: Vec<DropStat>
+}
+
+/// 单个掉落物品
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropItem {
+    /// 物品 ID
+    pub item_id: String,
+    /// 物品名
+    #[serde(default)]
+    pub item_name: String,
+    /// 数量
+    pub quantity: i32
+}
+
+/// 掉落所在关卡
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropStage {
+    /// 关卡名，如 "1-7"
+    pub stage_code: String,
+    /// 关卡 ID，如 "main_01-07"
+    #[serde(default)]
+    pub stage_id: String
+}
+
+/// 累计掉落统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropStat {
+    /// 物品 ID
+    pub item_id: String,
+    /// 物品名
+    #[serde(default)]
+    pub item_name: String,
+    /// 累计数量
+    pub quantity: i32,
+    /// 本次新增数量
+    #[serde(default)]
+    pub add_quantity: i32
+}
+
+/// 关卡名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageName {
+    pub name: String
+}
+
+/// 企鹅物流 ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PenguinId {
+    pub id: String
+}
+
+/// 战斗次数
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FightTimes {
+    /// 本次消耗的理智
+    #[serde(default)]
+    pub sanity_cost: i32,
+    /// 连战次数
+    #[serde(default)]
+    pub series: i32,
+    /// 已完成的次数
+    #[serde(default)]
+    pub times_finished: i32
+}
+
+/// 使用理智药
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct UseMedicine {
+    /// 使用数量
+    pub count: i32,
+    /// 是否为即将过期的理智药
+    #[serde(default)]
+    pub is_expiring: bool
+}
+
+/// 使用源石
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct UseStone {
+    /// 使用数量
+    pub count: i32
+}
+
+/// 公招 Tags
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecruitTags {
+    pub tags: Vec<String>
+}
+
+/// 公招特殊 Tag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecruitSpecialTag {
+    pub tag: String
+}
+
+/// 公招识别结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecruitResult {
+    /// 识别到的 Tags
+    pub tags: Vec<String>,
+    /// 最高星级
+    pub level: i32,
+    /// 各 Tags 组合的结果
+    #[serde(default)]
+    pub result: Vec<RecruitCombination>
+}
+
+/// 公招 Tags 组合
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecruitCombination {
+    /// 组合的 Tags
+    pub tags: Vec<String>,
+    /// 组合的最低星级
+    pub level: i32,
+    /// 可能出现的干员
+    #[serde(default)]
+    pub opers: Vec<RecruitOper>
+}
+
+/// 公招可能出现的干员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecruitOper {
+    pub name: String,
+    pub level: i32
+}
+
+/// 公招刷新 Tags
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RecruitTagsRefreshed {
+    /// 当前槽位已刷新次数
+    pub count: i32,
+    /// 刷新次数上限
+    pub refresh_limit: i32
+}
+
+/// 基建设施
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FacilityInfo {
+    /// 设施名
+    pub facility: String,
+    /// 设施序号
+    pub index: i32
+}
+
+/// 基建设施产物
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductOfFacility {
+    /// 产物名
+    pub product: String,
+    /// 设施名
+    pub facility: String,
+    /// 设施序号
+    pub index: i32
+}
+
+/// 仓库识别结果
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepotInfo {
+    /// 识别是否已完成，未完成时为中间结果
+    #[serde(default)]
+    pub done: bool,
+    /// ArkPlanner 格式的结果
+    pub arkplanner: Option<ArkPlannerDepot>,
+    /// 一图流格式的结果
+    pub lolicon: Option<LoliconDepot>
+}
+
+/// ArkPlanner 格式的仓库识别结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArkPlannerDepot {
+    /// 解析后的结果
+    pub object: ArkPlannerObject,
+    /// 原始的 JSON 字符串
+    #[serde(default)]
+    pub data: String
+}
+
+/// ArkPlanner 格式的仓库物品列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArkPlannerObject {
+    pub items: Vec<ArkPlannerItem>
+}
+
+/// ArkPlanner 格式的仓库物品
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArkPlannerItem {
+    /// 物品 ID
+    pub id: String,
+    /// 持有数量
+    pub have: i64,
+    /// 物品名
+    #[serde(default)]
+    pub name: String
+}
+
+/// 一图流格式的仓库识别结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoliconDepot {
+    /// 物品 ID 到持有数量的映射
+    pub object: HashMap<String, i64>,
+    /// 原始的 JSON 字符串
+    #[serde(default)]
+    pub data: String
+}
+
+/// 干员识别结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperBoxInfo {
+    /// 识别是否已完成，未完成时为中间结果
+    #[serde(default)]
+    pub done: bool,
+    /// 全部干员
+    #[serde(default)]
+    pub all_oper: Vec<OperBoxEntry>,
+    /// 已拥有的干员
+    #[serde(default)]
+    pub own_opers: Vec<OwnedOper>
+}
+
+/// 干员识别结果中的干员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperBoxEntry {
+    /// 干员 ID，如 "char_002_amiya"
+    pub id: String,
+    /// 干员名
+    pub name: String,
+    /// 是否拥有
+    pub own: bool,
+    /// 稀有度，0 表示一星
+    #[serde(default)]
+    pub rarity: i32
+}
+
+/// 已拥有的干员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnedOper {
+    /// 干员 ID，如 "char_002_amiya"
+    pub id: String,
+    /// 干员名
+    pub name: String,
+    /// 是否拥有
+    #[serde(default = "default_true")]
+    pub own: bool,
+    /// 精英化阶段
+    #[serde(default)]
+    pub elite: i32,
+    /// 等级
+    #[serde(default)]
+    pub level: i32,
+    /// 潜能
+    #[serde(default)]
+    pub potential: i32,
+    /// 稀有度，0 表示一星
+    #[serde(default)]
+    pub rarity: i32
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize)]
+struct RawSubTaskExtraInfo {
+    taskchain: Option<String>,
+    class: Option<String>,
+    taskid: Option<i32>,
+    uuid: Option<String>,
+    what: String,
+    #[serde(default)]
+    details: Value
+}
+
+impl From<RawSubTaskExtraInfo> for SubTaskExtraInfo {
+    fn from(raw: RawSubTaskExtraInfo) -> Self {
+        Self {
+            taskchain: raw.taskchain,
+            class: raw.class,
+            taskid: raw.taskid,
+            uuid: raw.uuid,
+            what: ExtraInfo::parse(&raw.what, &raw.details),
+            details: raw.details
+        }
+    }
+}
+
+impl From<SubTaskExtraInfo> for RawSubTaskExtraInfo {
+    fn from(info: SubTaskExtraInfo) -> Self {
+        Self {
+            taskchain: info.taskchain,
+            class: info.class,
+            taskid: info.taskid,
+            uuid: info.uuid,
+            what: info.what.what().to_string(),
+            details: info.details
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_task_chain() {
+        let event = Event::parse(10002, json!({ "taskchain": "Fight", "taskid": 3, "uuid": "abc" }));
+        let Event::TaskChainCompleted(info) = &event else {
+            panic!("unexpected event: {event:?}");
+        };
+        assert_eq!("Fight", info.taskchain);
+        assert_eq!(3, info.taskid);
+        assert_eq!(Some(3), event.task_id());
+        assert_eq!(Message::TaskChainCompleted, event.message());
+    }
+
+    #[test]
+    fn test_parse_connection_info() {
+        let event = Event::parse(
+            2,
+            json!({
+                "what": "ResolutionGot",
+                "why": "",
+                "uuid": "abc",
+                "details": { "adb": "adb", "address": "127.0.0.1:5555", "config": "General", "width": 1280, "height": 720 }
+            })
+        );
+        let Event::ConnectionInfo(info) = event else {
+            panic!("unexpected event");
+        };
+        assert_eq!(
+            ConnectionEvent::ResolutionGot(Resolution {
+                width: 1280,
+                height: 720
+            }),
+            info.what
+        );
+        assert_eq!(Some("127.0.0.1:5555"), info.address());
+
+        let unknown = Event::parse(2, json!({ "what": "SomethingNew", "details": {} }));
+        let Event::ConnectionInfo(info) = unknown else {
+            panic!("unexpected event");
+        };
+        assert_eq!(ConnectionEvent::Other("SomethingNew".to_string()), info.what);
+    }
+
+    #[test]
+    fn test_parse_stage_drops() {
+        let event = Event::parse(
+            20003,
+            json!({
+                "taskchain": "Fight",
+                "class": "asst::StageDropsTaskPlugin",
+                "taskid": 2,
+                "what": "StageDrops",
+                "details": {
+                    "drops": [{ "itemId": "3301", "quantity": 2, "itemName": "技巧概要·卷1" }],
+                    "stage": { "stageCode": "CA-5", "stageId": "wk_fly_5" },
+                    "stars": 3,
+                    "stats": [{ "itemId": "3301", "itemName": "技巧概要·卷1", "quantity": 4, "addQuantity": 2 }]
+                }
+            })
+        );
+        let Event::SubTaskExtraInfo(info) = event else {
+            panic!("unexpected event");
+        };
+        let ExtraInfo::StageDrops(drops) = &info.what else {
+            panic!("unexpected extra info: {:?}", info.what);
+        };
+        assert_eq!("CA-5", drops.stage.stage_code);
+        assert_eq!(2, drops.drops[0].quantity);
+        assert_eq!(4, drops.stats[0].quantity);
+        assert_eq!(Some(2), info.taskid);
+    }
+
+    #[test]
+    fn test_parse_fallback_to_raw() {
+        let event = Event::parse(10001, json!({ "taskchain": "Fight" }));
+        assert!(matches!(event, Event::Raw { msg_id: 10001, .. }));
+
+        let event = Event::parse(42, json!({}));
+        assert!(matches!(event, Event::Raw { msg_id: 42, .. }));
+    }
+
+    #[test]
+    fn test_processor_malformed_and_panic() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let logs = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut processor = Processor::from_event(|event| {
+            if let Event::Malformed { .. } = event {
+                panic!("回调函数 panic");
+            }
+        })
+        .with_listener({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event.clone())
+        })
+        .with_listener(|event| {
+            if let Event::Destroyed = event {
+                panic!("监听器 panic");
+            }
+        })
+        .with_logger({
+            let logs = logs.clone();
+            move |message| logs.lock().unwrap().push(message.to_string())
+        });
+
+        let malformed = b"{\"what\": \xff}";
+        processor.dispatch_bytes(2, malformed);
+        processor.dispatch_bytes(5, b"{}");
+
+        let events = events.lock().unwrap();
+        assert!(matches!(&events[0], Event::Malformed { msg_id: 2, bytes } if bytes == malformed));
+        assert_eq!(
+            *logs.lock().unwrap(),
+            [
+                "处理消息 2 时回调函数 panic: 回调函数 panic",
+                "处理 Destroyed 事件时监听器 panic: 监听器 panic"
+            ]
+        );
+        assert!(
+            matches!(&events[1], Event::CallbackPanicked { msg_id: 2, message } if message == "回调函数 panic")
+        );
+        assert!(matches!(events[2], Event::Destroyed));
+    }
+
+    #[test]
+    fn test_event_round_trip() {
+        let details = json!({
+            "taskchain": "Recruit",
+            "what": "RecruitTagsDetected",
+            "details": { "tags": ["费用回复", "防护"] }
+        });
+        let event = Event::parse(20003, details.clone());
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json!("SubTaskExtraInfo"), json["msg"]);
+        assert_eq!(details, json["details"]);
+
+        let event: Event = serde_json::from_value(json).unwrap();
+        let Event::SubTaskExtraInfo(info) = event else {
+            panic!("unexpected event");
+        };
+        assert!(matches!(info.what, ExtraInfo::RecruitTagsDetected(_)));
+    }
+}
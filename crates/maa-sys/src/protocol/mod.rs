@@ -1,3 +1,5 @@
 pub mod connection;
+pub mod descriptor;
 pub mod message;
+pub mod params;
 pub mod task;
@@ -0,0 +1,466 @@
+//! 任务参数中有固定取值的枚举
+//!
+//! 序列化为 MaaCore 使用的取值，未知的取值会作为 `Other` 保留，以兼容新版本 MaaCore 增加的取值。
+//! 任务的 setter 接受 `impl Into<T>`，因此也可以直接传入字符串或数字。
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::descriptor::{FieldEnum, FieldKind};
+
+/// 定义序列化为字符串的枚举
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $value:literal
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+            /// 其他取值
+            Other(String)
+        }
+
+        impl $name {
+            /// MaaCore 使用的取值
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    value => $name::Other(value.to_string())
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                $name::from(value.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Other(value) => value,
+                    value => value.as_str().to_string()
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FieldEnum for $name {
+            const KIND: FieldKind = FieldKind::String;
+            const VALUES: &'static [&'static str] = &[$($value),*];
+        }
+    };
+}
+
+/// 定义序列化为整数的枚举
+macro_rules! integer_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $value:literal
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "i32", into = "i32")]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+            /// 其他取值
+            Other(i32)
+        }
+
+        impl $name {
+            /// MaaCore 使用的取值
+            pub fn value(&self) -> i32 {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => *value
+                }
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(value: i32) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    value => $name::Other(value)
+                }
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(value: $name) -> Self {
+                value.value()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.value())
+            }
+        }
+
+        impl FieldEnum for $name {
+            const KIND: FieldKind = FieldKind::Integer;
+            const VALUES: &'static [&'static str] = &[$(stringify!($value)),*];
+        }
+    };
+}
+
+string_enum! {
+    /// 客户端版本
+    pub enum ClientType {
+        /// 官服
+        Official => "Official",
+        /// B服
+        Bilibili => "Bilibili",
+        /// 腾讯服
+        Txwy => "txwy",
+        /// 国际服
+        YoStarEN => "YoStarEN",
+        /// 日服
+        YoStarJP => "YoStarJP",
+        /// 韩服
+        YoStarKR => "YoStarKR"
+    }
+}
+
+string_enum! {
+    /// 服务器，用于汇报掉落数据和识别关卡
+    pub enum Server {
+        /// 国服
+        CN => "CN",
+        /// 国际服
+        US => "US",
+        /// 日服
+        JP => "JP",
+        /// 韩服
+        KR => "KR"
+    }
+}
+
+string_enum! {
+    /// 肉鸽主题
+    pub enum RoguelikeTheme {
+        /// 傀影与猩红孤钻
+        Phantom => "Phantom",
+        /// 水月与深蓝之树
+        Mizuki => "Mizuki",
+        /// 探索者的银凇止境
+        Sami => "Sami",
+        /// 萨卡兹的无终奇语
+        Sarkaz => "Sarkaz",
+        /// 界园志异
+        JieGarden => "JieGarden"
+    }
+}
+
+string_enum! {
+    /// 肉鸽开局分队
+    pub enum RoguelikeSquad {
+        /// 指挥分队
+        Leader => "指挥分队",
+        /// 集群分队
+        Gathering => "集群分队",
+        /// 后勤分队
+        Support => "后勤分队",
+        /// 矛头分队
+        Spearhead => "矛头分队",
+        /// 突击战术分队
+        TacticalAssault => "突击战术分队",
+        /// 堡垒战术分队
+        TacticalFortification => "堡垒战术分队",
+        /// 远程战术分队
+        TacticalRanged => "远程战术分队",
+        /// 破坏战术分队
+        TacticalDestruction => "破坏战术分队",
+        /// 研究分队
+        Research => "研究分队",
+        /// 高规格分队
+        FirstClass => "高规格分队",
+        /// 心胜于物分队，水月主题
+        MindOverMatter => "心胜于物分队",
+        /// 物尽其用分队，水月主题
+        Resourceful => "物尽其用分队",
+        /// 以人为本分队，水月主题
+        PeopleOriented => "以人为本分队",
+        /// 永恒狩猎分队，萨米主题
+        EternalHunting => "永恒狩猎分队",
+        /// 生活至上分队，萨米主题
+        LifeFirst => "生活至上分队",
+        /// 科学主义分队，萨米主题
+        Scientific => "科学主义分队",
+        /// 特训分队，萨米主题
+        SpecialTraining => "特训分队",
+        /// 魂灵护送分队，萨卡兹主题
+        SoulEscort => "魂灵护送分队",
+        /// 博闻广记分队，萨卡兹主题
+        Knowledgeable => "博闻广记分队",
+        /// 蓝图测绘分队，萨卡兹主题
+        Blueprint => "蓝图测绘分队",
+        /// 因地制宜分队，萨卡兹主题
+        Adaptive => "因地制宜分队",
+        /// 异想天开分队，萨卡兹主题
+        Whimsical => "异想天开分队"
+    }
+}
+
+string_enum! {
+    /// 肉鸽开局职业组
+    pub enum RoguelikeRoles {
+        /// 先手必胜
+        FirstMoveAdvantage => "先手必胜",
+        /// 稳扎稳打
+        SlowAndSteady => "稳扎稳打",
+        /// 取长补短
+        OvercomingWeaknesses => "取长补短",
+        /// 随心所欲
+        FlexibleDeployment => "随心所欲"
+    }
+}
+
+string_enum! {
+    /// 基建设施
+    pub enum Facility {
+        /// 制造站
+        Mfg => "Mfg",
+        /// 贸易站
+        Trade => "Trade",
+        /// 发电站
+        Power => "Power",
+        /// 控制中枢
+        Control => "Control",
+        /// 会客室
+        Reception => "Reception",
+        /// 办公室
+        Office => "Office",
+        /// 宿舍
+        Dorm => "Dorm",
+        /// 加工站
+        Processing => "Processing",
+        /// 训练室
+        Training => "Training"
+    }
+}
+
+string_enum! {
+    /// 无人机用途
+    pub enum DroneUsage {
+        /// 不使用无人机
+        NotUse => "_NotUse",
+        /// 贸易站，龙门币
+        Money => "Money",
+        /// 贸易站，合成玉
+        SyntheticJade => "SyntheticJade",
+        /// 制造站，作战记录
+        CombatRecord => "CombatRecord",
+        /// 制造站，赤金
+        PureGold => "PureGold",
+        /// 制造站，源石碎片
+        OriginStone => "OriginStone",
+        /// 制造站，芯片
+        Chip => "Chip"
+    }
+}
+
+string_enum! {
+    /// 生息演算主题
+    pub enum ReclamationTheme {
+        /// 沙中之火
+        Fire => "Fire",
+        /// 沙洲遗闻
+        Tales => "Tales"
+    }
+}
+
+integer_enum! {
+    /// 基建换班工作模式
+    pub enum InfrastMode {
+        /// 默认换班模式，单设施最优解
+        Default => 0,
+        /// 自定义换班模式，读取用户配置
+        Custom => 10000,
+        /// 一键轮换模式
+        Rotation => 20000
+    }
+}
+
+integer_enum! {
+    /// 公开招募选择更多 Tags 的模式
+    pub enum ExtraTagsMode {
+        /// 默认行为
+        Default => 0,
+        /// 选满至 3 个 Tags，即使可能冲突
+        Full => 1,
+        /// 尽可能同时选择更多的高星 Tags 组合，即使可能冲突
+        Combinations => 2
+    }
+}
+
+/// 刷理智的关卡
+///
+/// 序列化为 MaaCore 使用的关卡名，如 `1-7`、`Annihilation`、`Chernobog@Annihilation`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum StageSpec {
+    /// 识别当前/上次的关卡，对应空的关卡名
+    #[default]
+    Current,
+    /// 剿灭作战，可以指定剿灭关卡，如 `Chernobog`，为空时使用当期剿灭
+    Annihilation(Option<String>),
+    /// 关卡名，如 `1-7`、`CE-6`
+    Named(String)
+}
+
+impl StageSpec {
+    /// 剿灭作战的关卡名
+    pub const ANNIHILATION: &'static str = "Annihilation";
+
+    /// MaaCore 使用的关卡名
+    pub fn as_stage(&self) -> String {
+        match self {
+            StageSpec::Current => String::new(),
+            StageSpec::Annihilation(None) => StageSpec::ANNIHILATION.to_string(),
+            StageSpec::Annihilation(Some(id)) => format!("{id}@{}", StageSpec::ANNIHILATION),
+            StageSpec::Named(code) => code.clone()
+        }
+    }
+}
+
+impl From<&str> for StageSpec {
+    fn from(stage: &str) -> Self {
+        if stage.is_empty() {
+            return StageSpec::Current;
+        }
+        if stage == StageSpec::ANNIHILATION {
+            return StageSpec::Annihilation(None);
+        }
+        match stage.split_once('@') {
+            Some((id, StageSpec::ANNIHILATION)) if !id.is_empty() => {
+                StageSpec::Annihilation(Some(id.to_string()))
+            },
+            _ => StageSpec::Named(stage.to_string())
+        }
+    }
+}
+
+impl From<String> for StageSpec {
+    fn from(stage: String) -> Self {
+        StageSpec::from(stage.as_str())
+    }
+}
+
+impl From<StageSpec> for String {
+    fn from(stage: StageSpec) -> Self {
+        match stage {
+            StageSpec::Named(code) => code,
+            stage => stage.as_stage()
+        }
+    }
+}
+
+impl fmt::Display for StageSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_stage())
+    }
+}
+
+impl FieldEnum for StageSpec {
+    const KIND: FieldKind = FieldKind::String;
+    const VALUES: &'static [&'static str] = &[];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_enum() {
+        assert_eq!(ClientType::from("txwy"), ClientType::Txwy);
+        assert_eq!(
+            ClientType::from("YoStarTW"),
+            ClientType::Other("YoStarTW".to_string())
+        );
+        assert_eq!(DroneUsage::NotUse.to_string(), "_NotUse");
+
+        assert_eq!(Facility::from("Processing"), Facility::Processing);
+        let facilities = vec![Facility::Training, Facility::Other("Workshop".to_string())];
+        let json = serde_json::to_string(&facilities).unwrap();
+        assert_eq!(json, r#"["Training","Workshop"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Facility>>(&json).unwrap(), facilities);
+
+        assert_eq!(
+            serde_json::from_str::<RoguelikeSquad>(r#""指挥分队""#).unwrap(),
+            RoguelikeSquad::Leader
+        );
+        assert_eq!(RoguelikeTheme::VALUES.len(), 5);
+    }
+
+    #[test]
+    fn test_integer_enum() {
+        assert_eq!(InfrastMode::from(10000), InfrastMode::Custom);
+        assert_eq!(InfrastMode::from(30000), InfrastMode::Other(30000));
+        assert_eq!(ExtraTagsMode::Combinations.value(), 2);
+
+        let json = serde_json::to_string(&[InfrastMode::Rotation, InfrastMode::Other(1)]).unwrap();
+        assert_eq!(json, "[20000,1]");
+        assert_eq!(
+            serde_json::from_str::<Vec<InfrastMode>>(&json).unwrap(),
+            [InfrastMode::Rotation, InfrastMode::Other(1)]
+        );
+        assert_eq!(InfrastMode::VALUES, ["0", "10000", "20000"]);
+    }
+
+    #[test]
+    fn test_stage_spec() {
+        assert_eq!(StageSpec::from(""), StageSpec::Current);
+        assert_eq!(StageSpec::from("Annihilation"), StageSpec::Annihilation(None));
+        assert_eq!(
+            StageSpec::from("Chernobog@Annihilation"),
+            StageSpec::Annihilation(Some("Chernobog".to_string()))
+        );
+        assert_eq!(StageSpec::from("1-7"), StageSpec::Named("1-7".to_string()));
+
+        let stages = vec![
+            StageSpec::Current,
+            StageSpec::Annihilation(Some("LungmenOutskirts".to_string())),
+            StageSpec::Named("CE-6".to_string()),
+        ];
+        let json = serde_json::to_string(&stages).unwrap();
+        assert_eq!(json, r#"["","LungmenOutskirts@Annihilation","CE-6"]"#);
+        assert_eq!(serde_json::from_str::<Vec<StageSpec>>(&json).unwrap(), stages);
+    }
+}
@@ -4,6 +4,42 @@ use serde::{Deserialize, Serialize};
 use serde_json;
 use serde_with::skip_serializing_none;
 
+pub use super::descriptor::{FieldDescriptor, FieldEnum, FieldKind, FieldRange, TaskDescriptor};
+pub use super::params::{
+    ClientType, DroneUsage, ExtraTagsMode, Facility, InfrastMode, ReclamationTheme, RoguelikeRoles,
+    RoguelikeSquad, RoguelikeTheme, Server, StageSpec
+};
+
+/// 构建任务时缺少的必选字段，由 `try_build` 返回
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{task_type} 任务缺少必选字段: {}", .fields.join(", "))]
+pub struct MissingFields {
+    /// 任务类型
+    pub task_type: &'static str,
+    /// 所有未设置的必选字段
+    pub fields: Vec<&'static str>
+}
+
+/// 任务参数中不合法的字段
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{field} {message}")]
+pub struct InvalidField {
+    /// 字段名
+    pub field: &'static str,
+    /// 不合法的原因
+    pub message: String
+}
+
+/// 任务参数不合法，由 [`Task::validate`] 返回
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{task_type} 任务参数不合法: {}", .fields.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+pub struct InvalidFields {
+    /// 任务类型
+    pub task_type: &'static str,
+    /// 所有不合法的字段
+    pub fields: Vec<InvalidField>
+}
+
 pub trait Task {
     fn task_type(&self) -> &'static str;
     fn task_name(&self) -> &'static str;
@@ -11,6 +47,14 @@ pub trait Task {
     fn from_json(json: &str) -> Result<Self, serde_json::Error>
     where
         Self: Sized;
+
+    /// 任务参数的描述，可以用来生成任务配置的表单
+    fn descriptor(&self) -> &'static TaskDescriptor;
+
+    /// 检查任务参数，如字段上 `#[task(range = ...)]` 声明的取值范围和 `#[task(values = [...])]` 声明的可选值
+    fn validate(&self) -> Result<(), InvalidFields> {
+        Ok(())
+    }
 }
 
 /// 开始唤醒任务的参数
@@ -53,7 +97,7 @@ pub struct StartUpTask {
     /// - "YoStarEN" - 国际服
     /// - "YoStarJP" - 日服
     /// - "YoStarKR" - 韩服
-    pub client_type: Option<String>,
+    pub client_type: Option<ClientType>,
     /// 是否自动启动客户端，默认为 `false`
     pub start_game_enabled: Option<bool>,
     /// 切换账号，仅支持切换至已登录的账号
@@ -96,7 +140,7 @@ pub struct CloseDownTask {
     /// - "YoStarEN" - 国际服
     /// - "YoStarJP" - 日服
     /// - "YoStarKR" - 韩服
-    pub client_type: Option<String>
+    pub client_type: Option<ClientType>
 }
 
 /// 刷理智任务的参数
@@ -141,16 +185,20 @@ pub struct FightTask {
     /// 是否启用本任务，默认为 `true`
     pub enable: Option<bool>,
     /// 关卡名，默认为空，识别当前/上次的关卡。不支持运行中设置
-    pub stage: Option<String>,
+    pub stage: Option<StageSpec>,
     /// 最大使用理智药数量，默认为 `0`
+    #[task(range = 0..=999)]
     pub medicine: Option<i32>,
     /// 最大使用 48 小时内过期理智药数量，默认为 `0`
+    #[task(range = 0..=999)]
     pub expiring_medicine: Option<i32>,
     /// 最大吃石头数量，默认为 `0`
+    #[task(range = 0..=999)]
     pub stone: Option<i32>,
     /// 战斗次数，默认为 `i32::MAX`
     pub times: Option<i32>,
     /// 连战次数，取值范围 `-1~6`
+    #[task(range = -1..=6)]
     pub series: Option<i32>,
     /// 指定掉落数量，默认为不指定
     pub drops: Option<HashMap<String, i32>>,
@@ -159,13 +207,31 @@ pub struct FightTask {
     /// 企鹅数据汇报 id
     pub penguin_id: Option<String>,
     /// 服务器，默认为 "CN"
-    pub server: Option<String>,
+    pub server: Option<Server>,
     /// 客户端版本
-    pub client_type: Option<String>,
+    pub client_type: Option<ClientType>,
     /// 节省理智碎石模式，默认为 `false`
     pub dr_grandet: Option<bool>
 }
 
+impl FightTaskBuilder {
+    /// 添加一个指定掉落，可以多次调用
+    ///
+    /// 物品可以是 ID 或者物品名。[`crate::Assistant::append_task`] 会通过 [`FightTask::resolve_drops`]
+    /// 把物品名转换为 ID，有找不到的物品时返回 [`crate::Error::InvalidFields`]：
+    ///
+    /// ```rust,ignore
+    /// let task = FightTask::builder().stage("1-7").drop_target("固源岩", 20).build();
+    /// assistant.append_task(task)?;
+    /// ```
+    pub fn drop_target(mut self, item: impl Into<String>, count: i32) -> Self {
+        self.drops
+            .get_or_insert_with(HashMap::new)
+            .insert(item.into(), count);
+        self
+    }
+}
+
 /// 公开招募任务的参数
 ///
 /// # 字段说明
@@ -214,7 +280,7 @@ pub struct RecruitTask {
     /// 首选 Tags，仅在 Tag 等级为 3 时有效
     pub first_tags: Option<Vec<String>>,
     /// 选择更多的 Tags，默认为 `0`
-    pub extra_tags_mode: Option<i32>,
+    pub extra_tags_mode: Option<ExtraTagsMode>,
     /// 招募多少次，默认为 `0`
     pub times: Option<i32>,
     /// 是否设置招募时限
@@ -236,7 +302,7 @@ pub struct RecruitTask {
     /// 一图流汇报 id
     pub yituliu_id: Option<String>,
     /// 服务器，默认为 "CN"
-    pub server: Option<String>
+    pub server: Option<Server>
 }
 
 // Start Generation Here
@@ -262,12 +328,12 @@ pub struct RecruitTask {
 /// # 示例
 ///
 /// ```
-/// use maa_sys::task::InfrastTask;
+/// use maa_sys::task::{Facility, InfrastMode, InfrastTask};
 ///
 /// let task = InfrastTask::builder()
 ///     .enable(true)
-///     .mode(0)
-///     .facility(vec!["制造站".to_string(), "贸易站".to_string()])
+///     .mode(InfrastMode::Default)
+///     .facility(vec![Facility::Mfg, Facility::Trade])
 ///     .threshold(0.3)
 ///     .build();
 /// ```
@@ -283,12 +349,13 @@ pub struct InfrastTask {
     /// - `0` - Default: 默认换班模式，单设施最优解
     /// - `10000` - Custom: 自定义换班模式，读取用户配置
     /// - `20000` - Rotation: 一键轮换模式
-    pub mode: Option<i32>,
+    pub mode: Option<InfrastMode>,
     /// 要换班的设施（有序），必选。不支持运行中设置
-    pub facility: Vec<String>,
+    pub facility: Vec<Facility>,
     /// 无人机用途，默认为 _NotUse
-    pub drones: Option<String>,
+    pub drones: Option<DroneUsage>,
     /// 工作心情阈值，取值范围 [0, 1.0]，默认为 0.3
+    #[task(range = 0.0..=1.0)]
     pub threshold: Option<f32>,
     /// 贸易站"源石碎片"是否自动补货，默认为 `false`
     pub replenish: Option<bool>,
@@ -447,13 +514,13 @@ pub struct RoguelikeTask {
     /// 是否启用本任务，默认为 `true`
     pub enable: Option<bool>,
     /// 主题，默认为 "Phantom"
-    pub theme: Option<String>,
+    pub theme: Option<RoguelikeTheme>,
     /// 模式，默认为 `0`
     pub mode: Option<i32>,
     /// 开局分队名，默认为 "指挥分队"
-    pub squad: Option<String>,
+    pub squad: Option<RoguelikeSquad>,
     /// 开局职业组，默认为 "取长补短"
-    pub roles: Option<String>,
+    pub roles: Option<RoguelikeRoles>,
     /// 开局干员名
     pub core_char: Option<String>,
     /// 开局干员是否为助战干员，默认为 `false`
@@ -505,7 +572,7 @@ pub struct RoguelikeTask {
     /// 烧水是否启用购物，默认为 `false`
     pub collectible_mode_shopping: Option<bool>,
     /// 烧水时使用的分队，默认与 squad 同步
-    pub collectible_mode_squad: Option<String>,
+    pub collectible_mode_squad: Option<RoguelikeSquad>,
     /// 烧水期望奖励，默认全 `false`
     pub collectible_mode_start_list: Option<HashMap<String, bool>>,
     /// 使用种子刷钱，`true` 时有效
@@ -660,13 +727,14 @@ pub struct ReclamationTask {
     /// 可选值：
     /// - "Fire" - *沙中之火*
     /// - "Tales" - *沙洲遗闻*
-    pub theme: Option<String>,
+    pub theme: Option<ReclamationTheme>,
     /// 模式，默认为 `0`
     ///
     /// 可选值：
     /// - `0` - 刷分与建造点，进入战斗直接退出
     /// - `1` - 沙中之火：刷赤金，联络员买水后基地锻造；
     ///         沙洲遗闻：自动制造物品并读档刷货币
+    #[task(values = [0, 1])]
     pub mode: Option<i32>,
     /// 自动制造的物品列表，默认为 `["荧光棒"]`
     pub tools_to_craft: Option<Vec<String>>,
@@ -675,6 +743,7 @@ pub struct ReclamationTask {
     /// 可选值：
     /// - `0` - 连点
     /// - `1` - 长按
+    #[task(values = [0, 1])]
     pub increment_mode: Option<i32>,
     /// 单次最大制造轮数，默认为 `16`
     pub num_craft_batches: Option<i32>
@@ -752,6 +821,7 @@ pub struct SingleStepTask {
     /// - "stage" 设置关卡名，需要 "details": { "stage": "xxxx" }
     /// - "start" 开始作战，无 details
     /// - "action": 单步作战操作，details 需为作战协议中的单个 action
+    #[task(values = ["stage", "start", "action"])]
     pub subtask: String,
     /// 任务详情，根据子任务类型不同而变化
     pub details: HashMap<String, serde_json::Value>
@@ -784,6 +854,161 @@ pub struct VideoRecognitionTask {
     pub filename: String
 }
 
+/// 生成 [`AnyTask`] 及其分派到具体任务的实现，变体名与 `task_type` 相同
+macro_rules! any_task {
+    ($($variant:ident($task:ident)),* $(,)?) => {
+        /// 任意任务
+        ///
+        /// 序列化为 `{"type": "Fight", "params": {...}}` 的形式，`type` 为任务类型，
+        /// `params` 为传给 MaaCore 的任务参数，可以用来在配置文件中保存任务队列。
+        ///
+        /// 注意 [`Task::to_json`] 返回的是传给 MaaCore 的任务参数，即 `params` 部分，其中不包含任务类型，
+        /// 因此 [`Task::from_json`] 总是返回错误，需要使用 [`AnyTask::from_params`] 解析。
+        ///
+        /// # 示例
+        ///
+        /// ```
+        /// use maa_sys::task::{AnyTask, FightTask, Task};
+        ///
+        /// let task = AnyTask::from(FightTask::builder().stage("1-7").build());
+        /// let json = serde_json::to_string(&task).unwrap();
+        /// assert_eq!(json, r#"{"type":"Fight","params":{"stage":"1-7"}}"#);
+        ///
+        /// let task: AnyTask = serde_json::from_str(&json).unwrap();
+        /// assert_eq!(task.task_type(), "Fight");
+        ///
+        /// let task = AnyTask::from_params(task.task_type(), &task.to_json()).unwrap();
+        /// assert_eq!(task.to_json(), r#"{"stage":"1-7"}"#);
+        /// ```
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        #[serde(tag = "type", content = "params")]
+        pub enum AnyTask {
+            $($variant($task),)*
+        }
+
+        impl AnyTask {
+            /// 所有任务类型
+            pub const TASK_TYPES: &'static [&'static str] = &[$(stringify!($variant)),*];
+
+            /// 所有任务参数的描述，顺序与 [`AnyTask::TASK_TYPES`] 一致
+            pub const DESCRIPTORS: &'static [&'static TaskDescriptor] = &[$(&$task::DESCRIPTOR),*];
+
+            /// 具体的任务
+            pub fn as_task(&self) -> &dyn Task {
+                match self {
+                    $(AnyTask::$variant(task) => task,)*
+                }
+            }
+        }
+
+        $(
+            impl From<$task> for AnyTask {
+                fn from(task: $task) -> Self {
+                    AnyTask::$variant(task)
+                }
+            }
+        )*
+    };
+}
+
+any_task! {
+    StartUp(StartUpTask),
+    CloseDown(CloseDownTask),
+    Fight(FightTask),
+    Recruit(RecruitTask),
+    Infrast(InfrastTask),
+    Mall(MallTask),
+    Award(AwardTask),
+    Roguelike(RoguelikeTask),
+    Copilot(CopilotTask),
+    SSSCopilot(SSSCopilotTask),
+    Depot(DepotTask),
+    OperBox(OperBoxTask),
+    Reclamation(ReclamationTask),
+    Custom(CustomTask),
+    SingleStep(SingleStepTask),
+    VideoRecognition(VideoRecognitionTask),
+}
+
+impl AnyTask {
+    /// 根据任务类型和 MaaCore 的任务参数创建任务
+    ///
+    /// # Arguments
+    /// * `task_type` - 任务类型，如 `Fight`
+    /// * `params` - 任务参数的 JSON
+    ///
+    /// # Returns
+    /// * `Ok(AnyTask)` - 任务
+    /// * `Err(serde_json::Error)` - 任务类型未知或者参数格式错误
+    pub fn from_params(task_type: &str, params: &str) -> Result<Self, serde_json::Error> {
+        let params: serde_json::Value = serde_json::from_str(params)?;
+        serde_json::from_value(serde_json::json!({ "type": task_type, "params": params }))
+    }
+}
+
+impl AnyTask {
+    /// 根据任务类型获取任务参数的描述
+    pub fn descriptor_of(task_type: &str) -> Option<&'static TaskDescriptor> {
+        Self::DESCRIPTORS
+            .iter()
+            .find(|descriptor| descriptor.task_type == task_type)
+            .copied()
+    }
+
+    /// 所有任务的 JSON Schema，与 [`AnyTask`] 序列化的形式一致
+    #[cfg(feature = "schema")]
+    pub fn json_schema() -> serde_json::Value {
+        let tasks: Vec<serde_json::Value> = Self::DESCRIPTORS
+            .iter()
+            .map(|descriptor| {
+                serde_json::json!({
+                    "title": descriptor.name,
+                    "type": "object",
+                    "properties": {
+                        "type": { "const": descriptor.task_type },
+                        "params": descriptor.json_schema()
+                    },
+                    "required": ["type", "params"]
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "AnyTask",
+            "oneOf": tasks
+        })
+    }
+}
+
+impl Task for AnyTask {
+    fn task_type(&self) -> &'static str {
+        self.as_task().task_type()
+    }
+
+    fn task_name(&self) -> &'static str {
+        self.as_task().task_name()
+    }
+
+    fn to_json(&self) -> String {
+        self.as_task().to_json()
+    }
+
+    /// 任务参数中没有任务类型，总是返回错误，使用 [`AnyTask::from_params`] 代替
+    fn from_json(_json: &str) -> Result<Self, serde_json::Error> {
+        Err(serde::de::Error::custom(
+            "任务参数中没有任务类型，请使用 AnyTask::from_params 解析"
+        ))
+    }
+
+    fn descriptor(&self) -> &'static TaskDescriptor {
+        self.as_task().descriptor()
+    }
+
+    fn validate(&self) -> Result<(), InvalidFields> {
+        self.as_task().validate()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -798,7 +1023,7 @@ mod tests {
             .build();
 
         assert_eq!(Some(true), task.enable, "task.enable");
-        assert_eq!(Some("Official".to_string()), task.client_type, "task.client_type");
+        assert_eq!(Some(ClientType::Official), task.client_type, "task.client_type");
         assert_eq!(Some(true), task.start_game_enabled, "task.start_game_enabled");
         assert_eq!(
             Some("123****4567".to_string()),
@@ -848,6 +1073,32 @@ mod tests {
         assert!(result.is_err(), "必选字段未设置应该 panic");
     }
 
+    #[test]
+    fn test_try_build() {
+        let err = RecruitTask::builder().enable(true).try_build().unwrap_err();
+        assert_eq!(
+            MissingFields {
+                task_type: "Recruit",
+                fields: vec!["select", "confirm"]
+            },
+            err
+        );
+        assert_eq!("Recruit 任务缺少必选字段: select, confirm", err.to_string());
+
+        let err = RecruitTask::builder().select(vec![4]).try_build().unwrap_err();
+        assert_eq!(vec!["confirm"], err.fields);
+
+        let task = RecruitTask::builder()
+            .select(vec![4])
+            .confirm(vec![3, 4])
+            .try_build()
+            .unwrap();
+        assert_eq!(vec![3, 4], task.confirm);
+
+        // 没有必选字段的任务总是可以构建
+        assert!(StartUpTask::builder().try_build().is_ok());
+    }
+
     #[test]
     fn test_from_json() {
         let json = r#"{"enable":true,"select":[1,2,3],"confirm":[4,5,6]}"#;
@@ -861,4 +1112,304 @@ mod tests {
     fn test_task_name() {
         assert_eq!("开始唤醒", StartUpTask::builder().build().task_name());
     }
+
+    /// 测试字段属性用的任务
+    #[derive(Debug, Serialize, Deserialize, GenerateTask)]
+    #[task(name = "测试", task_type = "Test")]
+    struct AttrsTask {
+        #[task(default = "Official")]
+        client_type: Option<ClientType>,
+        #[task(default = 3, range = 1..10)]
+        times: i32,
+        #[task(rename = "with_stage")]
+        stage: String,
+        #[serde(default)]
+        #[task(skip)]
+        internal: Vec<i32>,
+        #[task(range = ..=1.0)]
+        threshold: f32,
+        #[task(values = ["Official", "Bilibili"])]
+        server_client: Option<ClientType>,
+        #[task(values = [-1, 1])]
+        direction: Option<i32>
+    }
+
+    #[test]
+    fn test_field_attrs() {
+        let err = AttrsTask::builder().try_build().unwrap_err();
+        assert_eq!(vec!["stage", "threshold"], err.fields);
+
+        let task = AttrsTask::builder().with_stage("1-7").threshold(0.5).build();
+        assert_eq!(Some(ClientType::Official), task.client_type);
+        assert_eq!(3, task.times);
+        assert!(task.internal.is_empty());
+        assert!(task.validate().is_ok());
+
+        let task = AttrsTask::builder()
+            .client_type("Bilibili")
+            .times(10)
+            .with_stage("1-7")
+            .threshold(1.5)
+            .build();
+        assert_eq!(Some(ClientType::Bilibili), task.client_type);
+        let err = task.validate().unwrap_err();
+        assert_eq!("Test", err.task_type);
+        assert_eq!(
+            vec!["times", "threshold"],
+            err.fields.iter().map(|field| field.field).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "Test 任务参数不合法: times 10 超出范围 1..10, threshold 1.5 超出范围 ..=1.0",
+            err.to_string()
+        );
+
+        let task = AttrsTask::builder()
+            .with_stage("1-7")
+            .threshold(0.5)
+            .server_client("YoStarEN")
+            .direction(0)
+            .build();
+        let err = task.validate().unwrap_err();
+        assert_eq!(
+            vec!["server_client", "direction"],
+            err.fields.iter().map(|field| field.field).collect::<Vec<_>>()
+        );
+        assert_eq!("0 不是可选值 [-1, 1] 之一", err.fields[1].message);
+        assert!(AttrsTask::builder()
+            .with_stage("1-7")
+            .threshold(0.5)
+            .server_client("Bilibili")
+            .direction(-1)
+            .build()
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(FightTask::builder()
+            .medicine(999)
+            .series(-1)
+            .build()
+            .validate()
+            .is_ok());
+        let err = FightTask::builder()
+            .medicine(1000)
+            .series(7)
+            .build()
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            vec!["medicine", "series"],
+            err.fields.iter().map(|field| field.field).collect::<Vec<_>>()
+        );
+
+        let task = AnyTask::from_params("Infrast", r#"{"facility":["Mfg"],"threshold":1.3}"#).unwrap();
+        assert_eq!("threshold", task.validate().unwrap_err().fields[0].field);
+
+        // #[task(values = [...])] 声明的可选值
+        let err = ReclamationTask::builder().mode(2).build().validate().unwrap_err();
+        assert_eq!("mode", err.fields[0].field);
+        let task = AnyTask::from_params(
+            "SingleStep",
+            r#"{"enable":true,"task_type":"copilot","subtask":"stop","details":{}}"#
+        )
+        .unwrap();
+        assert_eq!(
+            r#"SingleStep 任务参数不合法: subtask "stop" 不是可选值 ["stage", "start", "action"] 之一"#,
+            task.validate().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_descriptor() {
+        let descriptor = &FightTask::DESCRIPTOR;
+        assert_eq!("Fight", descriptor.task_type);
+        assert_eq!("刷理智", descriptor.name);
+        assert_eq!(0, descriptor.required_fields().count());
+
+        let medicine = descriptor.field("medicine").unwrap();
+        assert_eq!("i32", medicine.ty);
+        assert_eq!(FieldKind::Integer, medicine.kind);
+        assert_eq!("最大使用理智药数量，默认为 `0`", medicine.doc);
+        assert_eq!(
+            Some(FieldRange {
+                start: Some(0.0),
+                end: Some(999.0),
+                inclusive: true
+            }),
+            medicine.range
+        );
+        let drops = descriptor.field("drops").unwrap();
+        assert_eq!("HashMap<String, i32>", drops.ty);
+        assert_eq!(FieldKind::Map(&FieldKind::Integer), drops.kind);
+
+        let task = RecruitTask::builder().select(vec![4]).confirm(vec![4]).build();
+        let required: Vec<_> = task
+            .descriptor()
+            .required_fields()
+            .map(|field| field.name)
+            .collect();
+        assert_eq!(vec!["select", "confirm"], required);
+        assert_eq!(
+            FieldKind::Array(&FieldKind::Integer),
+            task.descriptor().field("select").unwrap().kind
+        );
+
+        let client_type = StartUpTask::DESCRIPTOR.field("client_type").unwrap();
+        assert_eq!(6, client_type.values.len());
+        assert!(client_type.open);
+        assert!(!ReclamationTask::DESCRIPTOR.field("mode").unwrap().open);
+        assert!(client_type
+            .doc
+            .starts_with("客户端版本，可选值：\n- \"Official\" - 官服"));
+        assert_eq!(
+            &["0", "10000", "20000"],
+            InfrastTask::DESCRIPTOR.field("mode").unwrap().values
+        );
+
+        let times = AttrsTask::DESCRIPTOR.field("times").unwrap();
+        // builder 中的默认值不影响反序列化，没有 #[serde(default)] 时仍然是必选的
+        assert!(times.required);
+        assert!(AttrsTask::from_json(r#"{"stage":"1-7","threshold":0.5}"#).is_err());
+        assert!(!AttrsTask::DESCRIPTOR.field("internal").unwrap().required);
+        assert_eq!(Some("3"), times.default);
+        assert_eq!(Some("Official"), AttrsTask::DESCRIPTOR.fields[0].default);
+
+        assert_eq!(AnyTask::TASK_TYPES.len(), AnyTask::DESCRIPTORS.len());
+        assert_eq!(
+            "Roguelike",
+            AnyTask::descriptor_of("Roguelike").unwrap().task_type
+        );
+        assert!(AnyTask::descriptor_of("Unknown").is_none());
+
+        let json = serde_json::to_value(InfrastTask::DESCRIPTOR.field("facility").unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({ "kind": "array", "items": { "kind": "string" } }),
+            json["kind"]
+        );
+    }
+
+    #[test]
+    fn test_enum_fields() {
+        let task = InfrastTask::builder()
+            .mode(InfrastMode::Rotation)
+            .facility(vec![Facility::Mfg, Facility::Dorm])
+            .drones("_NotUse")
+            .build();
+        assert_eq!(Some(DroneUsage::NotUse), task.drones);
+        assert_eq!(
+            r#"{"mode":20000,"facility":["Mfg","Dorm"],"drones":"_NotUse"}"#,
+            task.to_json()
+        );
+
+        // 未知的取值会保留下来
+        let task =
+            RoguelikeTask::from_json(r#"{"theme":"Newland","squad":"指挥分队","roles":"取长补短"}"#).unwrap();
+        assert_eq!(Some(RoguelikeTheme::Other("Newland".to_string())), task.theme);
+        assert_eq!(Some(RoguelikeSquad::Leader), task.squad);
+        assert_eq!(Some(RoguelikeRoles::OvercomingWeaknesses), task.roles);
+        assert_eq!(
+            r#"{"theme":"Newland","squad":"指挥分队","roles":"取长补短"}"#,
+            task.to_json()
+        );
+
+        let facility = InfrastTask::DESCRIPTOR.field("facility").unwrap();
+        assert_eq!("Vec<Facility>", facility.ty);
+        assert_eq!(Facility::VALUES, facility.values);
+        assert_eq!(
+            FieldKind::Integer,
+            RecruitTask::DESCRIPTOR.field("extra_tags_mode").unwrap().kind
+        );
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_json_schema() {
+        let schema = FightTask::DESCRIPTOR.json_schema();
+        assert_eq!("刷理智", schema["title"]);
+        assert_eq!(
+            serde_json::json!({
+                "type": "integer",
+                "description": "连战次数，取值范围 `-1~6`",
+                "minimum": -1,
+                "maximum": 6
+            }),
+            schema["properties"]["series"]
+        );
+        assert_eq!(
+            serde_json::json!({ "type": "object", "additionalProperties": { "type": "integer" } }),
+            schema["properties"]["drops"]
+                .as_object()
+                .map(|drops| {
+                    let mut drops = drops.clone();
+                    drops.remove("description");
+                    serde_json::Value::Object(drops)
+                })
+                .unwrap()
+        );
+
+        let schema = AttrsTask::DESCRIPTOR.json_schema();
+        assert_eq!(
+            serde_json::json!(["times", "stage", "threshold"]),
+            schema["required"]
+        );
+        assert_eq!(3, schema["properties"]["times"]["default"]);
+        assert_eq!("Official", schema["properties"]["client_type"]["default"]);
+        assert_eq!(10, schema["properties"]["times"]["exclusiveMaximum"]);
+
+        // 枚举可以表示其他取值，已知的取值只作为示例
+        let schema = InfrastTask::DESCRIPTOR.json_schema();
+        assert_eq!(
+            serde_json::json!([0, 10000, 20000]),
+            schema["properties"]["mode"]["examples"]
+        );
+        assert!(schema["properties"]["mode"].get("enum").is_none());
+        let schema = StartUpTask::DESCRIPTOR.json_schema();
+        assert_eq!("string", schema["properties"]["client_type"]["type"]);
+        assert_eq!("Official", schema["properties"]["client_type"]["examples"][0]);
+
+        // #[task(values = [...])] 声明的可选值会被检查
+        let schema = ReclamationTask::DESCRIPTOR.json_schema();
+        assert_eq!(serde_json::json!([0, 1]), schema["properties"]["mode"]["enum"]);
+        assert!(schema["properties"]["mode"].get("examples").is_none());
+
+        let schema = AnyTask::json_schema();
+        let tasks = schema["oneOf"].as_array().unwrap();
+        assert_eq!(AnyTask::TASK_TYPES.len(), tasks.len());
+        assert_eq!("Fight", tasks[2]["properties"]["type"]["const"]);
+    }
+
+    #[test]
+    fn test_any_task() {
+        let tasks = vec![
+            AnyTask::from(StartUpTask::builder().client_type("Official").build()),
+            AnyTask::from(FightTask::builder().stage("1-7").medicine(3).build()),
+            AnyTask::from(RecruitTask::builder().select(vec![4]).confirm(vec![3, 4]).build()),
+        ];
+        let json = serde_json::to_string(&tasks).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"type":"StartUp","params":{"client_type":"Official"}},{"type":"Fight","params":{"stage":"1-7","medicine":3}},{"type":"Recruit","params":{"select":[4],"confirm":[3,4]}}]"#
+        );
+
+        let parsed: Vec<AnyTask> = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        assert_eq!(parsed[1].task_type(), "Fight");
+        assert_eq!(parsed[1].task_name(), "刷理智");
+        assert_eq!(parsed[1].to_json(), r#"{"stage":"1-7","medicine":3}"#);
+        assert!(matches!(&parsed[1], AnyTask::Fight(task) if task.medicine == Some(3)));
+
+        assert_eq!(AnyTask::TASK_TYPES.len(), 16);
+        assert!(AnyTask::TASK_TYPES.contains(&"SSSCopilot"));
+        let task = AnyTask::from_params("Fight", r#"{"stage":"CE-6"}"#).unwrap();
+        assert_eq!(task.to_json(), r#"{"stage":"CE-6"}"#);
+        assert!(AnyTask::from_params("Unknown", "{}").is_err());
+        // 任务参数中没有任务类型，只能通过 from_params 解析
+        assert!(AnyTask::from_json(&task.to_json()).is_err());
+        let parsed = AnyTask::from_params(parsed[1].task_type(), &parsed[1].to_json()).unwrap();
+        assert_eq!(parsed.to_json(), r#"{"stage":"1-7","medicine":3}"#);
+        // 缺少必选字段
+        assert!(AnyTask::from_params("Recruit", "{}").is_err());
+    }
 }
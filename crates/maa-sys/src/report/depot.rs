@@ -0,0 +1,380 @@
+//! 仓库物品
+//!
+//! 根据 Depot 任务的 `DepotInfo` 生成 [`Inventory`]，可以导出为 ArkPlanner、企鹅物流刷图规划器
+//! 和 CSV 格式，也可以保存为快照，与之后的识别结果比较。
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::protocol::message::{DepotInfo, ExtraInfo};
+use crate::resource::ItemIndex;
+use crate::tracker::TaskOutcome;
+use crate::Error;
+
+/// 仓库中的物品
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryItem {
+    /// 物品 ID
+    pub item_id: String,
+    /// 物品名，优先使用物品索引中的名字，都没有时为物品 ID
+    pub item_name: String,
+    /// 持有数量
+    pub count: i64
+}
+
+/// 仓库识别结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// 识别时间
+    pub timestamp: DateTime<Utc>,
+    /// 物品，按仓库中的顺序排列
+    pub items: Vec<InventoryItem>
+}
+
+impl Inventory {
+    /// 根据仓库识别结果生成，识别时间为当前时间
+    ///
+    /// 优先使用 ArkPlanner 格式的结果，没有时使用一图流格式的结果
+    ///
+    /// # Arguments
+    /// * `info` - 仓库识别结果
+    /// * `items` - 物品索引，用于补全物品名
+    ///
+    /// # Returns
+    /// * `Some(Inventory)` - 仓库中的物品
+    /// * `None` - 两种格式的结果都没有
+    pub fn from_depot(info: &DepotInfo, items: Option<&ItemIndex>) -> Option<Self> {
+        let name_of = |item_id: &str, name: &str| -> String {
+            items
+                .and_then(|items| items.name_of(item_id))
+                .or(Some(name).filter(|name| !name.is_empty()))
+                .unwrap_or(item_id)
+                .to_string()
+        };
+
+        let items = if let Some(arkplanner) = &info.arkplanner {
+            arkplanner
+                .object
+                .items
+                .iter()
+                .map(|item| InventoryItem {
+                    item_id: item.id.clone(),
+                    item_name: name_of(&item.id, &item.name),
+                    count: item.have
+                })
+                .collect()
+        } else {
+            // 一图流格式没有顺序，按物品 ID 排列
+            let mut object: Vec<_> = info.lolicon.as_ref()?.object.iter().collect();
+            object.sort_by(|a, b| a.0.cmp(b.0));
+            object
+                .into_iter()
+                .map(|(item_id, &count)| InventoryItem {
+                    item_id: item_id.clone(),
+                    item_name: name_of(item_id, ""),
+                    count
+                })
+                .collect()
+        };
+
+        Some(Self {
+            timestamp: Utc::now(),
+            items
+        })
+    }
+
+    /// 根据 [`crate::TaskHandle`] 的执行结果生成，使用最后一条仓库识别结果
+    ///
+    /// # Arguments
+    /// * `outcome` - Depot 任务的执行结果
+    /// * `items` - 物品索引，用于补全物品名
+    pub fn from_outcome(outcome: &TaskOutcome, items: Option<&ItemIndex>) -> Option<Self> {
+        outcome.extra_info.iter().rev().find_map(|info| match &info.what {
+            ExtraInfo::DepotInfo(depot) => Self::from_depot(depot, items),
+            _ => None
+        })
+    }
+
+    /// 根据物品 ID 查找物品
+    pub fn get(&self, item_id: &str) -> Option<&InventoryItem> {
+        self.items.iter().find(|item| item.item_id == item_id)
+    }
+
+    /// 物品的持有数量，没有的物品为 0
+    pub fn count(&self, item_id: &str) -> i64 {
+        self.get(item_id).map_or(0, |item| item.count)
+    }
+
+    /// 导出为 ArkPlanner 格式，与 MaaCore 回调中 `arkplanner.data` 的格式相同
+    pub fn to_arkplanner(&self) -> Value {
+        let items: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| json!({ "id": item.item_id, "have": item.count, "name": item.item_name }))
+            .collect();
+        json!({ "@type": "@penguin-statistics/depot", "items": items })
+    }
+
+    /// 导出为企鹅物流刷图规划器的配置格式，所有物品的需求数量为 0
+    pub fn to_planner(&self) -> Value {
+        let items: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| json!({ "id": item.item_id, "have": item.count, "need": 0 }))
+            .collect();
+        json!({
+            "@type": "@penguin-statistics/planner/config",
+            "items": items,
+            "options": { "byProduct": false, "requireExp": false, "requireLmb": false },
+            "excludes": []
+        })
+    }
+
+    /// 导出为 CSV 格式，包含 `item_id,item_name,count` 三列
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        super::push_csv_row(&mut csv, ["item_id", "item_name", "count"]);
+        for item in &self.items {
+            super::push_csv_row(
+                &mut csv,
+                [
+                    item.item_id.as_str(),
+                    item.item_name.as_str(),
+                    &item.count.to_string()
+                ]
+            );
+        }
+        csv
+    }
+
+    /// 从 JSON 文件读取快照
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        super::read_json(path.as_ref())
+    }
+
+    /// 以 JSON 格式保存快照
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        super::write_json(path.as_ref(), self)
+    }
+
+    /// 用当前结果替换保存的快照，并与原来的快照比较
+    ///
+    /// # Returns
+    /// * `Ok(Some(InventoryDiff))` - 与原来的快照相比的变化
+    /// * `Ok(None)` - 原来没有快照
+    /// * `Err(Error)` - 读写快照失败
+    pub fn update_snapshot(&self, path: impl AsRef<Path>) -> Result<Option<InventoryDiff>, Error> {
+        let path = path.as_ref();
+        let previous = if path.exists() {
+            Some(Self::load(path)?)
+        } else {
+            None
+        };
+        self.save(path)?;
+        Ok(previous.map(|previous| self.diff(&previous)))
+    }
+
+    /// 与之前的识别结果比较
+    ///
+    /// 变化按当前结果中的顺序排列，之前有而现在没有的物品排在最后
+    pub fn diff(&self, previous: &Inventory) -> InventoryDiff {
+        let mut changes: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| ItemChange::new(item, previous.count(&item.item_id), item.count))
+            .collect();
+        changes.extend(
+            previous
+                .items
+                .iter()
+                .filter(|item| self.get(&item.item_id).is_none())
+                .map(|item| ItemChange::new(item, item.count, 0))
+        );
+        changes.retain(|change| change.delta != 0);
+
+        InventoryDiff {
+            from: previous.timestamp,
+            to: self.timestamp,
+            changes
+        }
+    }
+}
+
+/// 单个物品数量的变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemChange {
+    /// 物品 ID
+    pub item_id: String,
+    /// 物品名
+    pub item_name: String,
+    /// 之前的数量
+    pub before: i64,
+    /// 现在的数量
+    pub after: i64,
+    /// 变化量，`after - before`
+    pub delta: i64
+}
+
+impl ItemChange {
+    fn new(item: &InventoryItem, before: i64, after: i64) -> Self {
+        Self {
+            item_id: item.item_id.clone(),
+            item_name: item.item_name.clone(),
+            before,
+            after,
+            delta: after - before
+        }
+    }
+}
+
+/// 两次仓库识别结果之间的变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryDiff {
+    /// 之前的识别时间
+    pub from: DateTime<Utc>,
+    /// 现在的识别时间
+    pub to: DateTime<Utc>,
+    /// 数量有变化的物品
+    pub changes: Vec<ItemChange>
+}
+
+impl InventoryDiff {
+    /// 是否没有任何变化
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depot(details: Value) -> DepotInfo {
+        serde_json::from_value(details).unwrap()
+    }
+
+    fn inventory(timestamp: &str, items: &[(&str, &str, i64)]) -> Inventory {
+        Inventory {
+            timestamp: timestamp.parse().unwrap(),
+            items: items
+                .iter()
+                .map(|&(item_id, item_name, count)| InventoryItem {
+                    item_id: item_id.to_string(),
+                    item_name: item_name.to_string(),
+                    count
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_from_depot() {
+        let info = depot(json!({
+            "done": true,
+            "arkplanner": {
+                "object": { "items": [{ "id": "30012", "have": 5, "name": "固源岩" }, { "id": "4001", "have": 100 }] },
+                "data": ""
+            },
+            "lolicon": { "object": { "30012": 5, "4001": 100 }, "data": "" }
+        }));
+        let index = ItemIndex::from_json(r#"{ "4001": { "name": "龙门币" } }"#).unwrap();
+        let inventory = Inventory::from_depot(&info, Some(&index)).unwrap();
+        let items: Vec<_> = inventory
+            .items
+            .iter()
+            .map(|item| (item.item_id.as_str(), item.item_name.as_str(), item.count))
+            .collect();
+        assert_eq!(items, [("30012", "固源岩", 5), ("4001", "龙门币", 100)]);
+        assert_eq!(inventory.count("4001"), 100);
+        assert_eq!(inventory.count("30011"), 0);
+
+        let info =
+            depot(json!({ "done": true, "lolicon": { "object": { "4001": 100, "30012": 5 }, "data": "" } }));
+        let inventory = Inventory::from_depot(&info, None).unwrap();
+        assert_eq!(inventory.items[0].item_id, "30012");
+        assert_eq!(inventory.items[1].item_name, "4001");
+
+        assert!(Inventory::from_depot(&depot(json!({ "done": false })), None).is_none());
+    }
+
+    #[test]
+    fn test_export() {
+        let inventory = inventory(
+            "2024-06-01T00:00:00Z",
+            &[("30012", "固源岩", 5), ("x", "a,\"b\"", 1)]
+        );
+        assert_eq!(
+            inventory.to_arkplanner(),
+            json!({
+                "@type": "@penguin-statistics/depot",
+                "items": [
+                    { "id": "30012", "have": 5, "name": "固源岩" },
+                    { "id": "x", "have": 1, "name": "a,\"b\"" }
+                ]
+            })
+        );
+        let planner = inventory.to_planner();
+        assert_eq!(planner["@type"], "@penguin-statistics/planner/config");
+        assert_eq!(
+            planner["items"][0],
+            json!({ "id": "30012", "have": 5, "need": 0 })
+        );
+        assert_eq!(
+            inventory.to_csv(),
+            "item_id,item_name,count\n30012,固源岩,5\nx,\"a,\"\"b\"\"\",1\n"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let yesterday = inventory(
+            "2024-06-01T00:00:00Z",
+            &[
+                ("30012", "固源岩", 5),
+                ("30011", "源岩", 3),
+                ("4001", "龙门币", 100)
+            ]
+        );
+        let today = inventory(
+            "2024-06-02T00:00:00Z",
+            &[
+                ("30013", "固源岩组", 1),
+                ("30012", "固源岩", 2),
+                ("4001", "龙门币", 100)
+            ]
+        );
+        let diff = today.diff(&yesterday);
+        assert_eq!(diff.from, yesterday.timestamp);
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| (change.item_id.as_str(), change.before, change.after, change.delta))
+            .collect();
+        assert_eq!(
+            changes,
+            [("30013", 0, 1, 1), ("30012", 5, 2, -3), ("30011", 3, 0, -3)]
+        );
+        assert!(today.diff(&today).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("depot.json");
+        let yesterday = inventory("2024-06-01T00:00:00Z", &[("30012", "固源岩", 5)]);
+        assert_eq!(yesterday.update_snapshot(&path).unwrap(), None);
+        assert_eq!(Inventory::load(&path).unwrap(), yesterday);
+
+        let today = inventory("2024-06-02T00:00:00Z", &[("30012", "固源岩", 7)]);
+        let diff = today.update_snapshot(&path).unwrap().unwrap();
+        assert_eq!(diff.changes[0].delta, 2);
+        assert_eq!(Inventory::load(&path).unwrap(), today);
+
+        std::fs::write(&path, "[]").unwrap();
+        let err = Inventory::load(&path).unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::ReportFormatFailed);
+    }
+}
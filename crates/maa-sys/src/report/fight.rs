@@ -0,0 +1,400 @@
+//! 刷理智报告
+//!
+//! 统计 Fight 任务链中的战斗次数、理智消耗、理智药和源石的使用情况以及各物品的累计掉落。
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::message::{Event, ExtraInfo, SubTaskExtraInfo};
+use crate::resource::ItemIndex;
+use crate::tracker::{TaskOutcome, TaskStatus};
+
+/// 统计的任务链
+const TASK_CHAIN: &str = "Fight";
+
+/// 单个物品的累计掉落
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemTotal {
+    /// 物品 ID
+    pub item_id: String,
+    /// 物品名，优先使用物品索引中的名字，都没有时为物品 ID
+    pub item_name: String,
+    /// 累计数量
+    pub quantity: i64
+}
+
+/// 一个 Fight 任务的刷理智报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FightReport {
+    /// 任务 ID
+    pub task_id: i32,
+    /// 关卡名，如 `1-7`，识别到关卡之前为 `None`
+    pub stage: Option<String>,
+    /// 完成的战斗次数，连战按一次计算
+    pub times: i32,
+    /// 消耗的理智
+    pub sanity: i32,
+    /// 使用的理智药数量，包括即将过期的理智药
+    pub medicine: i32,
+    /// 使用的即将过期的理智药数量
+    pub expiring_medicine: i32,
+    /// 使用的源石数量
+    pub stone: i32,
+    /// 各物品的累计掉落，按首次掉落的顺序排列
+    pub drops: Vec<ItemTotal>,
+    /// 最终状态，任务链结束之前为 `None`
+    pub status: Option<TaskStatus>
+}
+
+impl FightReport {
+    pub fn new(task_id: i32) -> Self {
+        Self {
+            task_id,
+            ..Self::default()
+        }
+    }
+
+    /// 根据 [`crate::TaskHandle`] 的执行结果生成报告
+    ///
+    /// # Arguments
+    /// * `outcome` - Fight 任务的执行结果
+    /// * `items` - 物品索引，用于补全物品名
+    pub fn from_outcome(outcome: &TaskOutcome, items: Option<&ItemIndex>) -> Self {
+        let mut report = Self::new(outcome.task_id);
+        for info in &outcome.extra_info {
+            report.apply(&info.what, items);
+        }
+        report.status = Some(outcome.status);
+        report
+    }
+
+    /// 任务链是否已经结束
+    pub fn is_finished(&self) -> bool {
+        self.status.is_some()
+    }
+
+    /// 物品的累计掉落数量
+    pub fn quantity_of(&self, item_id: &str) -> i64 {
+        self.drops
+            .iter()
+            .find(|total| total.item_id == item_id)
+            .map_or(0, |total| total.quantity)
+    }
+
+    /// 累计一条原子任务额外信息
+    fn apply(&mut self, info: &ExtraInfo, items: Option<&ItemIndex>) {
+        match info {
+            ExtraInfo::StageInfo(stage) => self.stage = Some(stage.name.clone()),
+            ExtraInfo::FightTimes(times) => self.sanity += times.sanity_cost,
+            ExtraInfo::UseMedicine(medicine) => {
+                self.medicine += medicine.count;
+                if medicine.is_expiring {
+                    self.expiring_medicine += medicine.count;
+                }
+            },
+            ExtraInfo::UseStone(stone) => self.stone += stone.count,
+            ExtraInfo::StageDrops(drops) => {
+                self.times += 1;
+                if !drops.stage.stage_code.is_empty() {
+                    self.stage = Some(drops.stage.stage_code.clone());
+                }
+                for item in &drops.drops {
+                    self.add_drop(&item.item_id, &item.item_name, item.quantity.into(), items);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn add_drop(&mut self, item_id: &str, item_name: &str, quantity: i64, items: Option<&ItemIndex>) {
+        if let Some(total) = self.drops.iter_mut().find(|total| total.item_id == item_id) {
+            total.quantity += quantity;
+            return;
+        }
+
+        let item_name = items
+            .and_then(|items| items.name_of(item_id))
+            .or(Some(item_name).filter(|name| !name.is_empty()))
+            .unwrap_or(item_id);
+        self.drops.push(ItemTotal {
+            item_id: item_id.to_string(),
+            item_name: item_name.to_string(),
+            quantity
+        });
+    }
+}
+
+#[derive(Default)]
+struct ReporterState {
+    /// 正在执行的任务，以任务 ID 为键
+    running: HashMap<i32, FightReport>,
+    /// 已经结束的任务，按结束顺序排列
+    finished: Vec<FightReport>
+}
+
+/// 刷理智报告收集器
+///
+/// 通过 [`crate::AssistantBuilder::with_listener`] 订阅 Assistant 的事件，
+/// 为每个 Fight 任务生成一份 [`FightReport`]，任务链结束时报告移入 [`FightReporter::finished`]。
+/// 克隆的实例共享同一份数据。
+///
+/// ```no_run
+/// use maa_sys::report::FightReporter;
+/// use maa_sys::task::FightTask;
+/// use maa_sys::Assistant;
+///
+/// let reporter = FightReporter::new();
+/// let mut assistant = Assistant::registry()
+///     .with_library("/path/to/maa")
+///     .with_resource("/path/to/maa")
+///     .with_listener(reporter.listener())
+///     .init()?;
+/// let handle = assistant.append_task_with_handle(FightTask::builder().stage("1-7").build())?;
+/// assistant.start()?;
+/// let outcome = handle.wait();
+///
+/// let report = reporter.report(outcome.task_id).unwrap();
+/// println!("{}", serde_json::to_string_pretty(&report)?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct FightReporter {
+    state: Arc<Mutex<ReporterState>>,
+    items: Option<Arc<ItemIndex>>
+}
+
+impl FightReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用物品索引补全物品名，未设置时使用回调中的物品名
+    pub fn with_items(mut self, items: ItemIndex) -> Self {
+        self.items = Some(Arc::new(items));
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReporterState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 用于 [`crate::AssistantBuilder::with_listener`] 的事件监听器
+    pub fn listener(&self) -> impl FnMut(&Event) + Send + 'static {
+        let reporter = self.clone();
+        move |event| reporter.handle(event)
+    }
+
+    /// 处理一个事件，只统计 Fight 任务链的事件
+    ///
+    /// 使用 [`crate::AssistantBuilder::with_event_callback`] 时也可以在回调中直接调用
+    pub fn handle(&self, event: &Event) {
+        match event {
+            Event::TaskChainStart(info) if info.taskchain == TASK_CHAIN => {
+                self.lock()
+                    .running
+                    .insert(info.taskid, FightReport::new(info.taskid));
+            },
+            Event::SubTaskExtraInfo(info) => self.apply(info),
+            Event::TaskChainCompleted(info) if info.taskchain == TASK_CHAIN => {
+                self.finish(info.taskid, TaskStatus::Completed)
+            },
+            Event::TaskChainError(info) if info.taskchain == TASK_CHAIN => {
+                self.finish(info.taskid, TaskStatus::Error)
+            },
+            Event::TaskChainStopped(info) if info.taskchain == TASK_CHAIN => {
+                self.finish(info.taskid, TaskStatus::Stopped)
+            },
+            Event::Destroyed => {
+                // 与 TaskHandle 一致，实例被销毁时尚未结束的任务视为停止
+                let mut state = self.lock();
+                let mut running: Vec<_> = state.running.drain().map(|(_, report)| report).collect();
+                running.sort_by_key(|report| report.task_id);
+                for mut report in running {
+                    report.status = Some(TaskStatus::Stopped);
+                    state.finished.push(report);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn apply(&self, info: &SubTaskExtraInfo) {
+        let (Some(TASK_CHAIN), Some(task_id)) = (info.taskchain.as_deref(), info.taskid) else {
+            return;
+        };
+        self.lock()
+            .running
+            .entry(task_id)
+            .or_insert_with(|| FightReport::new(task_id))
+            .apply(&info.what, self.items.as_deref());
+    }
+
+    fn finish(&self, task_id: i32, status: TaskStatus) {
+        let mut state = self.lock();
+        let mut report = state
+            .running
+            .remove(&task_id)
+            .unwrap_or_else(|| FightReport::new(task_id));
+        report.status = Some(status);
+        state.finished.push(report);
+    }
+
+    /// 任务的报告，包括正在执行和已经结束的任务
+    pub fn report(&self, task_id: i32) -> Option<FightReport> {
+        let state = self.lock();
+        state
+            .running
+            .get(&task_id)
+            .or_else(|| state.finished.iter().rfind(|report| report.task_id == task_id))
+            .cloned()
+    }
+
+    /// 正在执行的任务的报告，按任务 ID 排列
+    pub fn running(&self) -> Vec<FightReport> {
+        let mut running: Vec<_> = self.lock().running.values().cloned().collect();
+        running.sort_by_key(|report| report.task_id);
+        running
+    }
+
+    /// 已经结束的任务的报告，按结束顺序排列
+    pub fn finished(&self) -> Vec<FightReport> {
+        self.lock().finished.clone()
+    }
+
+    /// 取出已经结束的任务的报告，取出后不再保留
+    pub fn take_finished(&self) -> Vec<FightReport> {
+        std::mem::take(&mut self.lock().finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn extra_info(taskid: i32, what: &str, details: serde_json::Value) -> Event {
+        Event::parse(
+            20003,
+            json!({ "taskchain": "Fight", "taskid": taskid, "what": what, "details": details })
+        )
+    }
+
+    fn drops(taskid: i32, drops: serde_json::Value) -> Event {
+        extra_info(
+            taskid,
+            "StageDrops",
+            json!({ "drops": drops, "stage": { "stageCode": "1-7", "stageId": "main_01-07" }, "stars": 3 })
+        )
+    }
+
+    #[test]
+    fn test_fight_reporter() {
+        let items = ItemIndex::from_json(r#"{ "30012": { "name": "固源岩" } }"#).unwrap();
+        let reporter = FightReporter::new().with_items(items);
+        let events = [
+            Event::parse(10001, json!({ "taskchain": "Fight", "taskid": 1 })),
+            extra_info(1, "UseMedicine", json!({ "count": 2, "is_expiring": true })),
+            extra_info(1, "UseMedicine", json!({ "count": 1, "is_expiring": false })),
+            extra_info(1, "UseStone", json!({ "count": 1 })),
+            extra_info(
+                1,
+                "FightTimes",
+                json!({ "sanity_cost": 12, "series": 2, "times_finished": 0 })
+            ),
+            drops(
+                1,
+                json!([{ "itemId": "30012", "quantity": 2 }, { "itemId": "4001", "quantity": 24, "itemName": "龙门币" }])
+            ),
+            extra_info(
+                1,
+                "FightTimes",
+                json!({ "sanity_cost": 6, "series": 1, "times_finished": 1 })
+            ),
+            drops(
+                1,
+                json!([{ "itemId": "30012", "quantity": 1 }, { "itemId": "furni", "quantity": 1 }])
+            ),
+            // 其他任务链的事件不统计
+            Event::parse(
+                20003,
+                json!({ "taskchain": "Mall", "taskid": 2, "what": "UseStone", "details": { "count": 5 } })
+            )
+        ];
+        for event in &events {
+            reporter.handle(event);
+        }
+
+        let report = reporter.report(1).unwrap();
+        assert!(!report.is_finished());
+        assert_eq!(report.stage.as_deref(), Some("1-7"));
+        assert_eq!((report.times, report.sanity), (2, 18));
+        assert_eq!(
+            (report.medicine, report.expiring_medicine, report.stone),
+            (3, 2, 1)
+        );
+        assert_eq!(report.quantity_of("30012"), 3);
+        let names: Vec<_> = report
+            .drops
+            .iter()
+            .map(|total| total.item_name.as_str())
+            .collect();
+        assert_eq!(names, ["固源岩", "龙门币", "furni"]);
+        assert!(reporter.report(2).is_none());
+
+        reporter.handle(&Event::parse(10002, json!({ "taskchain": "Fight", "taskid": 1 })));
+        assert!(reporter.running().is_empty());
+        let finished = reporter.take_finished();
+        assert_eq!(
+            finished,
+            [FightReport {
+                status: Some(TaskStatus::Completed),
+                ..report
+            }]
+        );
+        assert!(reporter.finished().is_empty());
+
+        let json = serde_json::to_value(&finished[0]).unwrap();
+        assert_eq!(json["status"], "Completed");
+        assert_eq!(
+            json["drops"][0],
+            json!({ "item_id": "30012", "item_name": "固源岩", "quantity": 3 })
+        );
+    }
+
+    #[test]
+    fn test_fight_reporter_destroyed() {
+        let reporter = FightReporter::new();
+        reporter.handle(&drops(3, json!([{ "itemId": "30012", "quantity": 1 }])));
+        reporter.handle(&Event::Destroyed);
+
+        let finished = reporter.finished();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].status, Some(TaskStatus::Stopped));
+        assert_eq!(finished[0].drops[0].item_name, "30012");
+    }
+
+    #[test]
+    fn test_from_outcome() {
+        let outcome = TaskOutcome {
+            task_id: 4,
+            status: TaskStatus::Error,
+            extra_info: [drops(
+                4,
+                json!([{ "itemId": "30012", "quantity": 2, "itemName": "固源岩" }])
+            )]
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::SubTaskExtraInfo(info) => Some(info),
+                _ => None
+            })
+            .collect()
+        };
+        let report = FightReport::from_outcome(&outcome, None);
+        assert_eq!(report.status, Some(TaskStatus::Error));
+        assert_eq!(report.times, 1);
+        assert_eq!(report.drops[0].item_name, "固源岩");
+    }
+}
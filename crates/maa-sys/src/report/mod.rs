@@ -0,0 +1,58 @@
+//! 根据 MaaCore 回调整理任务结果
+//!
+//! 回调中的结果分散在多条 [`crate::Event`] 中，这里的类型把它们汇总为便于保存和导出的形式。
+
+mod depot;
+mod fight;
+mod roster;
+
+use std::path::Path;
+
+pub use depot::*;
+pub use fight::*;
+pub use roster::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::Error;
+
+/// 读取并解析 JSON 格式的报告文件
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let content = std::fs::read_to_string(path).map_err(|source| Error::ReportIoFailed {
+        path: path.to_path_buf(),
+        source
+    })?;
+    serde_json::from_str(&content).map_err(|source| Error::ReportFormatFailed {
+        path: path.to_path_buf(),
+        source
+    })
+}
+
+/// 以 JSON 格式保存报告文件
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(value).map_err(|source| Error::ReportFormatFailed {
+        path: path.to_path_buf(),
+        source
+    })?;
+    std::fs::write(path, content).map_err(|source| Error::ReportIoFailed {
+        path: path.to_path_buf(),
+        source
+    })
+}
+
+/// 向 `csv` 中追加一行，包含逗号、引号或换行的字段会加上引号
+fn push_csv_row<'a>(csv: &mut String, fields: impl IntoIterator<Item = &'a str>) {
+    for (index, field) in fields.into_iter().enumerate() {
+        if index > 0 {
+            csv.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(field);
+        }
+    }
+    csv.push('\n');
+}
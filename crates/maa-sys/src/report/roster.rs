@@ -0,0 +1,396 @@
+//! 干员名单
+//!
+//! 根据 OperBox 任务的 `OperBoxInfo` 生成 [`OperatorRoster`]，可以按干员名或 ID 查找，
+//! 导出为 JSON 或 CSV，也可以检查作业需要的干员是否都已拥有。
+
+use std::path::Path;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::message::{ExtraInfo, OperBoxInfo};
+use crate::tracker::TaskOutcome;
+use crate::Error;
+
+/// 干员
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operator {
+    /// 干员 ID，如 `char_002_amiya`
+    pub id: String,
+    /// 干员名
+    pub name: String,
+    /// 是否拥有
+    pub own: bool,
+    /// 精英化阶段，未拥有时为 0
+    pub elite: i32,
+    /// 等级，未拥有时为 0
+    pub level: i32,
+    /// 潜能，未拥有时为 0
+    pub potential: i32,
+    /// 稀有度，0 表示一星
+    pub rarity: i32
+}
+
+impl Operator {
+    /// 是否已拥有并且满足作业中的练度要求
+    fn satisfies(&self, requirements: &CopilotRequirements) -> bool {
+        // 精英化之后等级从 1 开始，精英化阶段更高时不需要比较等级
+        self.own
+            && (self.elite, self.level) >= (requirements.elite, requirements.level)
+            && self.potential >= requirements.potential
+    }
+}
+
+/// 干员名单，可以按干员名或 ID 查找干员
+///
+/// 序列化为按识别顺序排列的干员列表
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<Operator>", into = "Vec<Operator>")]
+pub struct OperatorRoster {
+    /// 按识别顺序排列的干员
+    operators: Vec<Operator>,
+    ids: HashMap<String, usize>,
+    names: HashMap<String, usize>
+}
+
+impl OperatorRoster {
+    pub fn new(operators: Vec<Operator>) -> Self {
+        let ids = operators
+            .iter()
+            .enumerate()
+            .map(|(index, operator)| (operator.id.clone(), index))
+            .collect();
+        // 干员名重复时（如多个阿米娅）优先使用已拥有的干员，其次使用排在前面的干员
+        let mut names: HashMap<String, usize> = HashMap::new();
+        for (index, operator) in operators.iter().enumerate() {
+            let entry = names.entry(operator.name.clone()).or_insert(index);
+            if operator.own && !operators[*entry].own {
+                *entry = index;
+            }
+        }
+        Self {
+            operators,
+            ids,
+            names
+        }
+    }
+
+    /// 根据干员识别结果生成
+    ///
+    /// 全部干员来自 `all_oper`，已拥有干员的练度来自 `own_opers`
+    pub fn from_oper_box(info: &OperBoxInfo) -> Self {
+        let mut operators: Vec<_> = info
+            .all_oper
+            .iter()
+            .map(|entry| Operator {
+                id: entry.id.clone(),
+                name: entry.name.clone(),
+                own: entry.own,
+                elite: 0,
+                level: 0,
+                potential: 0,
+                rarity: entry.rarity
+            })
+            .collect();
+        for owned in &info.own_opers {
+            let index = match operators.iter().position(|operator| operator.id == owned.id) {
+                Some(index) => index,
+                None => {
+                    // 旧版本 MaaCore 的 all_oper 中可能缺少部分干员
+                    operators.push(Operator {
+                        id: owned.id.clone(),
+                        name: owned.name.clone(),
+                        own: owned.own,
+                        elite: 0,
+                        level: 0,
+                        potential: 0,
+                        rarity: owned.rarity
+                    });
+                    operators.len() - 1
+                }
+            };
+            let operator = &mut operators[index];
+            operator.own = owned.own;
+            operator.elite = owned.elite;
+            operator.level = owned.level;
+            operator.potential = owned.potential;
+        }
+        Self::new(operators)
+    }
+
+    /// 根据 [`crate::TaskHandle`] 的执行结果生成，使用最后一条干员识别结果
+    ///
+    /// # Arguments
+    /// * `outcome` - OperBox 任务的执行结果
+    pub fn from_outcome(outcome: &TaskOutcome) -> Option<Self> {
+        outcome.extra_info.iter().rev().find_map(|info| match &info.what {
+            ExtraInfo::OperBoxInfo(info) => Some(Self::from_oper_box(info)),
+            _ => None
+        })
+    }
+
+    /// 所有干员，按识别顺序排列
+    pub fn operators(&self) -> &[Operator] {
+        &self.operators
+    }
+
+    /// 已拥有的干员
+    pub fn owned(&self) -> impl Iterator<Item = &Operator> {
+        self.operators.iter().filter(|operator| operator.own)
+    }
+
+    /// 根据干员 ID 查找干员
+    pub fn get(&self, id: &str) -> Option<&Operator> {
+        self.ids.get(id).map(|&index| &self.operators[index])
+    }
+
+    /// 根据干员名查找干员，同名的干员优先返回已拥有的
+    pub fn by_name(&self, name: &str) -> Option<&Operator> {
+        self.names.get(name).map(|&index| &self.operators[index])
+    }
+
+    /// 根据干员 ID 或干员名查找干员，优先匹配 ID
+    pub fn resolve(&self, id_or_name: &str) -> Option<&Operator> {
+        self.get(id_or_name).or_else(|| self.by_name(id_or_name))
+    }
+
+    /// 是否拥有干员，名单中没有的干员视为未拥有
+    pub fn is_owned(&self, id_or_name: &str) -> bool {
+        self.resolve(id_or_name).is_some_and(|operator| operator.own)
+    }
+
+    /// 找出未拥有的干员
+    ///
+    /// # Arguments
+    /// * `operators` - 干员 ID 或干员名
+    pub fn missing<'a>(&self, operators: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        operators
+            .into_iter()
+            .filter(|operator| !self.is_owned(operator))
+            .collect()
+    }
+
+    /// 检查作业中的干员是否都已拥有并且满足练度要求
+    ///
+    /// 作业中 `opers` 的每个干员都需要满足，`groups` 的每组中有一个干员满足即可
+    ///
+    /// # Arguments
+    /// * `plan` - 作业 JSON 文件的内容，与 [`crate::task::CopilotTask`] 的 `filename` 格式相同
+    ///
+    /// # Returns
+    /// * `Ok(Vec<String>)` - 不满足的干员名和干员组名，都满足时为空
+    /// * `Err(serde_json::Error)` - 作业格式错误
+    pub fn missing_for_copilot(&self, plan: &str) -> Result<Vec<String>, serde_json::Error> {
+        let plan: CopilotPlan = serde_json::from_str(plan)?;
+        let satisfies = |oper: &CopilotOper| {
+            self.resolve(&oper.name)
+                .is_some_and(|operator| operator.satisfies(&oper.requirements))
+        };
+
+        let opers = plan
+            .opers
+            .iter()
+            .filter(|oper| !satisfies(oper))
+            .map(|oper| oper.name.clone());
+        let groups = plan
+            .groups
+            .iter()
+            .filter(|group| !group.opers.iter().any(satisfies))
+            .map(|group| group.name.clone());
+        Ok(opers.chain(groups).collect())
+    }
+
+    /// 导出为 CSV 格式，包含 `id,name,own,elite,level,potential,rarity` 七列
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        super::push_csv_row(
+            &mut csv,
+            ["id", "name", "own", "elite", "level", "potential", "rarity"]
+        );
+        for operator in &self.operators {
+            super::push_csv_row(
+                &mut csv,
+                [
+                    operator.id.as_str(),
+                    operator.name.as_str(),
+                    &operator.own.to_string(),
+                    &operator.elite.to_string(),
+                    &operator.level.to_string(),
+                    &operator.potential.to_string(),
+                    &operator.rarity.to_string()
+                ]
+            );
+        }
+        csv
+    }
+
+    /// 从 JSON 文件读取干员名单
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        super::read_json(path.as_ref())
+    }
+
+    /// 以 JSON 格式保存干员名单
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        super::write_json(path.as_ref(), self)
+    }
+}
+
+impl From<Vec<Operator>> for OperatorRoster {
+    fn from(operators: Vec<Operator>) -> Self {
+        Self::new(operators)
+    }
+}
+
+impl From<OperatorRoster> for Vec<Operator> {
+    fn from(roster: OperatorRoster) -> Self {
+        roster.operators
+    }
+}
+
+/// 作业中与干员相关的部分
+#[derive(Deserialize)]
+struct CopilotPlan {
+    #[serde(default)]
+    opers: Vec<CopilotOper>,
+    #[serde(default)]
+    groups: Vec<CopilotGroup>
+}
+
+#[derive(Deserialize)]
+struct CopilotOper {
+    name: String,
+    #[serde(default)]
+    requirements: CopilotRequirements
+}
+
+#[derive(Deserialize)]
+struct CopilotGroup {
+    name: String,
+    #[serde(default)]
+    opers: Vec<CopilotOper>
+}
+
+/// 作业中的练度要求
+#[derive(Default, Deserialize)]
+struct CopilotRequirements {
+    #[serde(default)]
+    elite: i32,
+    #[serde(default)]
+    level: i32,
+    /// MAA 的作业中潜能要求的字段名为 `potentiality`
+    #[serde(default, rename = "potentiality")]
+    potential: i32
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn roster() -> OperatorRoster {
+        let info: OperBoxInfo = serde_json::from_value(json!({
+            "done": true,
+            "all_oper": [
+                { "id": "char_002_amiya", "name": "阿米娅", "own": true, "rarity": 4 },
+                { "id": "char_103_angel", "name": "能天使", "own": true, "rarity": 5 },
+                { "id": "char_112_siege", "name": "推进之王", "own": false, "rarity": 5 }
+            ],
+            "own_opers": [
+                { "id": "char_002_amiya", "name": "阿米娅", "elite": 2, "level": 50, "potential": 6, "rarity": 4 },
+                { "id": "char_103_angel", "name": "能天使", "elite": 1, "level": 80, "potential": 1, "rarity": 5 },
+                { "id": "char_285_medic2", "name": "Lancet-2", "elite": 0, "level": 30, "potential": 5, "rarity": 0 }
+            ]
+        }))
+        .unwrap();
+        OperatorRoster::from_oper_box(&info)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let roster = roster();
+        assert_eq!(roster.operators().len(), 4);
+        assert_eq!(roster.owned().count(), 3);
+
+        let amiya = roster.by_name("阿米娅").unwrap();
+        assert_eq!((amiya.elite, amiya.level, amiya.potential), (2, 50, 6));
+        assert_eq!(roster.get("char_285_medic2").unwrap().name, "Lancet-2");
+        assert!(roster.is_owned("char_103_angel"));
+        assert!(!roster.is_owned("推进之王"));
+        assert_eq!(
+            roster.missing(["阿米娅", "推进之王", "史尔特尔"]),
+            ["推进之王", "史尔特尔"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_name() {
+        let info: OperBoxInfo = serde_json::from_value(json!({
+            "done": true,
+            "all_oper": [
+                { "id": "char_002_amiya", "name": "阿米娅", "own": false, "rarity": 4 },
+                { "id": "char_1001_amiya2", "name": "阿米娅", "own": false, "rarity": 4 },
+                { "id": "char_1037_amiya3", "name": "阿米娅", "own": false, "rarity": 4 }
+            ],
+            "own_opers": [
+                { "id": "char_1001_amiya2", "name": "阿米娅", "elite": 2, "level": 40, "potential": 6, "rarity": 4 }
+            ]
+        }))
+        .unwrap();
+        let roster = OperatorRoster::from_oper_box(&info);
+        assert_eq!(roster.by_name("阿米娅").unwrap().id, "char_1001_amiya2");
+        assert!(roster.is_owned("阿米娅"));
+        assert!(!roster.is_owned("char_1037_amiya3"));
+        assert!(roster
+            .missing_for_copilot(r#"{ "opers": [{ "name": "阿米娅", "requirements": { "elite": 2 } }] }"#)
+            .unwrap()
+            .is_empty());
+
+        // 都没有拥有时使用排在前面的干员
+        let operators = roster.operators().iter().map(|operator| Operator {
+            own: false,
+            ..operator.clone()
+        });
+        let roster = OperatorRoster::new(operators.collect());
+        assert_eq!(roster.by_name("阿米娅").unwrap().id, "char_002_amiya");
+    }
+
+    #[test]
+    fn test_missing_for_copilot() {
+        let roster = roster();
+        let plan = json!({
+            "stage_name": "1-7",
+            "opers": [
+                { "name": "阿米娅", "skill": 1 },
+                { "name": "能天使", "skill": 3, "requirements": { "elite": 2 } },
+                { "name": "Lancet-2", "requirements": { "elite": 0, "level": 30, "potentiality": 6 } }
+            ],
+            "groups": [
+                { "name": "重装", "opers": [{ "name": "推进之王" }, { "name": "史尔特尔" }] },
+                { "name": "医疗", "opers": [{ "name": "Lancet-2", "requirements": { "level": 30, "potentiality": 5 } }] }
+            ]
+        });
+        assert_eq!(
+            roster.missing_for_copilot(&plan.to_string()).unwrap(),
+            ["能天使", "Lancet-2", "重装"]
+        );
+        assert!(roster
+            .missing_for_copilot(r#"{ "opers": [{ "skill": 1 }] }"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let roster = OperatorRoster::new(roster().operators()[..1].to_vec());
+        assert_eq!(
+            roster.to_csv(),
+            "id,name,own,elite,level,potential,rarity\nchar_002_amiya,阿米娅,true,2,50,6,4\n"
+        );
+
+        let json = serde_json::to_value(&roster).unwrap();
+        assert_eq!(json[0]["name"], "阿米娅");
+        let parsed: OperatorRoster = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, roster);
+        assert!(parsed.by_name("阿米娅").is_some());
+    }
+}
@@ -0,0 +1,321 @@
+//! 物品数据
+//!
+//! 来自资源目录中的 `item_index.json`，以物品 ID 为键，包含物品名、图标等信息。
+//! 掉落、仓库识别等回调中使用的都是物品 ID，可以通过 [`ItemIndex`] 转换为物品名。
+
+use std::path::{Path, PathBuf};
+
+use hashbrown::HashMap;
+use pinyin::ToPinyin;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::task::{FightTask, InvalidField, InvalidFields, Task};
+use crate::Error;
+
+/// 物品
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    /// 物品 ID，如 `30011`，即 `item_index.json` 中的键
+    #[serde(default)]
+    pub id: String,
+    /// 物品名，如 `源岩`
+    pub name: String,
+    /// 图标的文件名，位于资源目录的 `template/items` 中
+    pub icon: Option<String>,
+    /// 稀有度，从 `0` 开始
+    pub rarity: Option<u8>,
+    /// 分类，如 `MATERIAL`
+    pub classify_type: Option<String>,
+    /// 排序 ID，游戏内仓库中的顺序
+    pub sort_id: Option<i32>,
+    /// 描述
+    pub description: Option<String>,
+    /// 用途
+    pub usage: Option<String>
+}
+
+impl Item {
+    /// 图标文件的路径
+    ///
+    /// # Arguments
+    /// * `path` - 资源路径，与传给 [`crate::Assistant::load_resource`] 的路径相同
+    pub fn icon_path(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        let icon = self.icon.as_ref()?;
+        Some(
+            super::resource_file(path.as_ref(), "template")
+                .join("items")
+                .join(icon)
+        )
+    }
+}
+
+/// 物品名的拼音，用于搜索
+#[derive(Debug, Clone)]
+struct ItemPinyin {
+    /// 全拼，如 `guyuanyan`
+    full: String,
+    /// 首字母，如 `gyy`
+    initials: String
+}
+
+impl ItemPinyin {
+    fn new(name: &str) -> Self {
+        let mut full = String::new();
+        let mut initials = String::new();
+        for (c, pinyin) in name.chars().zip(name.to_pinyin()) {
+            match pinyin {
+                Some(pinyin) => {
+                    full.push_str(pinyin.plain());
+                    initials.push_str(pinyin.first_letter());
+                },
+                None => {
+                    full.extend(c.to_lowercase());
+                    initials.extend(c.to_lowercase());
+                }
+            }
+        }
+        Self { full, initials }
+    }
+}
+
+/// 物品索引，可以在物品 ID 和物品名之间转换，以及按物品名或拼音搜索物品
+#[derive(Debug, Clone, Default)]
+pub struct ItemIndex {
+    /// 按排序 ID 排列的物品
+    items: Vec<Item>,
+    pinyin: Vec<ItemPinyin>,
+    ids: HashMap<String, usize>,
+    names: HashMap<String, usize>
+}
+
+impl ItemIndex {
+    /// 资源目录中的文件名
+    pub const FILE: &'static str = "item_index.json";
+
+    pub fn new(mut items: Vec<Item>) -> Self {
+        items.sort_by(|a, b| {
+            (a.sort_id.unwrap_or(i32::MAX), &a.id).cmp(&(b.sort_id.unwrap_or(i32::MAX), &b.id))
+        });
+        let pinyin = items.iter().map(|item| ItemPinyin::new(&item.name)).collect();
+        let ids = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (item.id.clone(), index))
+            .collect();
+        // 物品名重复时使用排在前面的物品
+        let mut names = HashMap::new();
+        for (index, item) in items.iter().enumerate() {
+            names.entry(item.name.clone()).or_insert(index);
+        }
+        Self {
+            items,
+            pinyin,
+            ids,
+            names
+        }
+    }
+
+    /// 从资源路径读取物品数据
+    ///
+    /// # Arguments
+    /// * `path` - 资源路径，与传给 [`crate::Assistant::load_resource`] 的路径相同
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let items: HashMap<String, Item> = super::read_json(path.as_ref(), Self::FILE)?;
+        Ok(Self::from_map(items))
+    }
+
+    /// 解析 `item_index.json` 的内容
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self::from_map(serde_json::from_str(json)?))
+    }
+
+    fn from_map(items: HashMap<String, Item>) -> Self {
+        Self::new(items.into_iter().map(|(id, item)| Item { id, ..item }).collect())
+    }
+
+    /// 所有物品，按排序 ID 排列
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// 根据物品 ID 查找物品
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.ids.get(id).map(|&index| &self.items[index])
+    }
+
+    /// 根据物品名查找物品
+    pub fn by_name(&self, name: &str) -> Option<&Item> {
+        self.names.get(name).map(|&index| &self.items[index])
+    }
+
+    /// 根据物品 ID 或物品名查找物品，优先匹配 ID
+    pub fn resolve(&self, id_or_name: &str) -> Option<&Item> {
+        self.get(id_or_name).or_else(|| self.by_name(id_or_name))
+    }
+
+    /// 物品 ID 对应的物品名
+    pub fn name_of(&self, id: &str) -> Option<&str> {
+        self.get(id).map(|item| item.name.as_str())
+    }
+
+    /// 物品名对应的物品 ID
+    pub fn id_of(&self, name: &str) -> Option<&str> {
+        self.by_name(name).map(|item| item.id.as_str())
+    }
+
+    /// 按物品名、全拼或拼音首字母搜索物品，不区分大小写
+    ///
+    /// 物品名完全相同的排在最前面，其余按排序 ID 排列
+    pub fn search(&self, query: &str) -> Vec<&Item> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut items: Vec<&Item> = self
+            .items
+            .iter()
+            .zip(&self.pinyin)
+            .filter(|(item, pinyin)| {
+                item.id == query
+                    || item.name.to_lowercase().contains(&query)
+                    || pinyin.full.contains(&query)
+                    || pinyin.initials.starts_with(&query)
+            })
+            .map(|(item, _)| item)
+            .collect();
+        // sort_by_key 是稳定排序，其余物品保持原来的顺序
+        items.sort_by_key(|item| item.name.to_lowercase() != query);
+        items
+    }
+}
+
+impl FightTask {
+    /// 把 `drops` 中的物品名转换为物品 ID，已经是物品 ID 的保持不变
+    ///
+    /// 同一个物品同时以物品名和物品 ID 指定时，数量会相加
+    ///
+    /// # Returns
+    /// * `Ok(())` - 所有物品都已转换为 ID
+    /// * `Err(InvalidFields)` - 有找不到的物品，找不到的物品保持不变
+    pub fn resolve_drops(&mut self, index: &ItemIndex) -> Result<(), InvalidFields> {
+        let Some(drops) = self.drops.take() else {
+            return Ok(());
+        };
+
+        let mut fields = Vec::new();
+        let mut resolved_drops = HashMap::with_capacity(drops.len());
+        for (item, count) in drops {
+            let id = match index.resolve(&item) {
+                Some(resolved) => resolved.id.clone(),
+                None => {
+                    fields.push(InvalidField {
+                        field: "drops",
+                        message: format!("找不到物品 {item:?}")
+                    });
+                    item
+                }
+            };
+            *resolved_drops.entry(id).or_insert(0) += count;
+        }
+        self.drops = Some(resolved_drops);
+
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidFields {
+                task_type: self.task_type(),
+                fields
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITEMS: &str = r#"{
+        "30011": {
+            "classifyType": "MATERIAL",
+            "description": "一种常见的岩石。",
+            "icon": "MTL_SL_G1.png",
+            "name": "源岩",
+            "sortId": 10001,
+            "usage": "被用于基建设施的建设。"
+        },
+        "30012": { "icon": "MTL_SL_G2.png", "name": "固源岩", "sortId": 10002 },
+        "30013": { "icon": "MTL_SL_G3.png", "name": "固源岩组", "sortId": 10003, "rarity": 2 },
+        "4001": { "icon": "GOLD.png", "name": "龙门币", "sortId": 1 }
+    }"#;
+
+    #[test]
+    fn test_lookup() {
+        let index = ItemIndex::from_json(ITEMS).unwrap();
+        let ids: Vec<_> = index.items().iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, ["4001", "30011", "30012", "30013"]);
+
+        assert_eq!(index.name_of("30012"), Some("固源岩"));
+        assert_eq!(index.id_of("固源岩组"), Some("30013"));
+        assert_eq!(index.resolve("龙门币").unwrap().id, "4001");
+        assert_eq!(index.resolve("30011").unwrap().name, "源岩");
+        assert!(index.resolve("40001").is_none());
+
+        let item = index.get("30013").unwrap();
+        assert_eq!(item.rarity, Some(2));
+        assert_eq!(
+            item.icon_path("/maa"),
+            Some(PathBuf::from("/maa/resource/template/items/MTL_SL_G3.png"))
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let index = ItemIndex::from_json(ITEMS).unwrap();
+        let names = |query: &str| {
+            index
+                .search(query)
+                .into_iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("固源岩"), ["固源岩", "固源岩组"]);
+        assert_eq!(names("源岩"), ["源岩", "固源岩", "固源岩组"]);
+        assert_eq!(names("gyy"), ["固源岩", "固源岩组"]);
+        assert_eq!(names("GuYuan"), ["固源岩", "固源岩组"]);
+        assert_eq!(names("lmb"), ["龙门币"]);
+        assert_eq!(names("4001"), ["龙门币"]);
+        assert!(names(" ").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_drops() {
+        let index = ItemIndex::from_json(ITEMS).unwrap();
+        let mut task = FightTask::builder()
+            .stage("1-7")
+            .drop_target("固源岩", 20)
+            .drop_target("4001", 1000)
+            .build();
+        assert!(task.resolve_drops(&index).is_ok());
+        let drops = task.drops.as_ref().unwrap();
+        assert_eq!(drops.get("30012"), Some(&20));
+        assert_eq!(drops.get("4001"), Some(&1000));
+
+        // 同一个物品的数量相加
+        let mut task = FightTask::builder()
+            .drop_target("固源岩", 20)
+            .drop_target("30012", 10)
+            .build();
+        assert!(task.resolve_drops(&index).is_ok());
+        assert_eq!(task.drops, Some(HashMap::from_iter([("30012".to_string(), 30)])));
+
+        let mut task = FightTask::builder().drop_target("源石", 1).build();
+        let err = task.resolve_drops(&index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Fight 任务参数不合法: drops 找不到物品 "源石""#
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! 读取 MaaCore 资源目录中的数据
+//!
+//! 资源路径与传给 [`crate::Assistant::load_resource`] 的路径相同，即包含 `resource` 目录的路径。
+
+mod item;
+mod stage;
+
+use std::path::{Path, PathBuf};
+
+pub use item::*;
+use serde::de::DeserializeOwned;
+pub use stage::*;
+
+use crate::Error;
+
+/// 资源目录中文件的路径
+fn resource_file(path: &Path, name: &str) -> PathBuf {
+    path.join("resource").join(name)
+}
+
+/// 读取并解析资源目录中的 JSON 文件
+fn read_json<T: DeserializeOwned>(path: &Path, name: &str) -> Result<T, Error> {
+    let path = resource_file(path, name);
+    let content = std::fs::read_to_string(&path).map_err(|source| Error::ResourceIoFailed {
+        path: path.clone(),
+        source
+    })?;
+    serde_json::from_str(&content).map_err(|source| Error::ResourceParseFailed { path, source })
+}
@@ -0,0 +1,407 @@
+//! 关卡数据
+//!
+//! 来自资源目录中的 `stages.json`，格式与企鹅物流数据统计的关卡列表相同，包含关卡编号、类型、
+//! 各服务器的开放时间等信息。
+
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::task::{FightTask, InvalidField, InvalidFields, Server, StageSpec, Task};
+use crate::Error;
+
+/// MaaCore 支持指定的剿灭关卡，用于 `Chernobog@Annihilation` 这样的关卡名
+pub const ANNIHILATION_IDS: &[&str] = &["Chernobog", "LungmenOutskirts", "LungmenDowntown"];
+
+/// 资源关卡每周的开放日，不在列表中的关卡每天开放
+const WEEKLY_SCHEDULE: &[(&str, &[Weekday])] = &[
+    ("CE-", &[Weekday::Tue, Weekday::Thu, Weekday::Sat, Weekday::Sun]),
+    ("AP-", &[Weekday::Mon, Weekday::Thu, Weekday::Sat, Weekday::Sun]),
+    ("CA-", &[Weekday::Tue, Weekday::Wed, Weekday::Fri, Weekday::Sun]),
+    ("SK-", &[Weekday::Mon, Weekday::Wed, Weekday::Fri, Weekday::Sat]),
+    ("PR-A-", &[Weekday::Mon, Weekday::Thu, Weekday::Fri, Weekday::Sun]),
+    ("PR-B-", &[Weekday::Mon, Weekday::Tue, Weekday::Fri, Weekday::Sat]),
+    ("PR-C-", &[Weekday::Wed, Weekday::Thu, Weekday::Sat, Weekday::Sun]),
+    ("PR-D-", &[Weekday::Tue, Weekday::Wed, Weekday::Sat, Weekday::Sun])
+];
+
+/// 每天刷新的时间，服务器时区的凌晨 4 点
+const RESET_HOUR: i64 = 4;
+
+/// 服务器所在的时区
+fn server_offset(server: &Server) -> FixedOffset {
+    let hours = match server {
+        Server::US => -7,
+        Server::JP | Server::KR => 9,
+        _ => 8
+    };
+    FixedOffset::east_opt(hours * 3600).unwrap()
+}
+
+/// 某个时刻在服务器上属于哪一天，每天从凌晨 4 点开始
+pub fn game_date(server: &Server, now: DateTime<Utc>) -> NaiveDate {
+    (now.with_timezone(&server_offset(server)) - Duration::hours(RESET_HOUR)).date_naive()
+}
+
+/// 服务器上某一天开始的时刻，毫秒时间戳
+fn day_start_millis(server: &Server, date: NaiveDate) -> i64 {
+    let offset = server_offset(server);
+    let start = date.and_time(NaiveTime::MIN) + Duration::hours(RESET_HOUR);
+    (start - Duration::seconds(offset.local_minus_utc() as i64))
+        .and_utc()
+        .timestamp_millis()
+}
+
+/// 关卡类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StageType {
+    /// 主线
+    Main,
+    /// 别传、插曲等
+    Sub,
+    /// 活动
+    Activity,
+    /// 资源收集
+    Daily,
+    /// 剿灭作战
+    Campaign,
+    /// 其他类型
+    #[serde(other)]
+    Other
+}
+
+/// 关卡在某个服务器上的开放情况
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageExistence {
+    /// 是否存在
+    pub exist: bool,
+    /// 开放时间，毫秒时间戳，为空时表示一直开放
+    pub open_time: Option<i64>,
+    /// 关闭时间，毫秒时间戳，为空时表示不会关闭
+    pub close_time: Option<i64>
+}
+
+/// 关卡
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stage {
+    /// 关卡 ID，如 `main_01-07`
+    pub stage_id: String,
+    /// 关卡编号，即 MaaCore 使用的关卡名，如 `1-7`
+    pub code: String,
+    /// 关卡类型
+    pub stage_type: StageType,
+    /// 所属区域的 ID，如 `main_1`
+    pub zone_id: Option<String>,
+    /// 理智消耗
+    pub ap_cost: Option<i32>,
+    /// 各服务器上的开放情况
+    #[serde(default)]
+    pub existence: HashMap<Server, StageExistence>
+}
+
+impl Stage {
+    /// 每周的开放日，为空时表示每天开放
+    pub fn open_days(&self) -> Option<&'static [Weekday]> {
+        if self.stage_type != StageType::Daily {
+            return None;
+        }
+        WEEKLY_SCHEDULE
+            .iter()
+            .find(|(prefix, _)| self.code.starts_with(prefix))
+            .map(|(_, days)| *days)
+    }
+
+    /// 关卡在服务器上的某一天是否开放，日期按服务器的凌晨 4 点划分
+    pub fn is_open_on(&self, date: NaiveDate, server: &Server) -> bool {
+        let Some(existence) = self.existence.get(server).filter(|existence| existence.exist) else {
+            return false;
+        };
+
+        let start = day_start_millis(server, date);
+        let end = start + Duration::days(1).num_milliseconds();
+        if existence.open_time.is_some_and(|open_time| open_time >= end)
+            || existence.close_time.is_some_and(|close_time| close_time <= start)
+        {
+            return false;
+        }
+
+        self.open_days().is_none_or(|days| days.contains(&date.weekday()))
+    }
+}
+
+/// 关卡数据，可以根据关卡编号查找关卡，以及检查关卡是否开放
+#[derive(Debug, Clone, Default)]
+pub struct StageCatalog {
+    stages: Vec<Stage>,
+    /// 关卡编号对应的关卡，复刻活动的关卡编号可能重复
+    codes: HashMap<String, Vec<usize>>
+}
+
+impl StageCatalog {
+    /// 资源目录中的文件名
+    pub const FILE: &'static str = "stages.json";
+
+    pub fn new(stages: Vec<Stage>) -> Self {
+        let mut codes: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, stage) in stages.iter().enumerate() {
+            codes.entry(stage.code.clone()).or_default().push(index);
+        }
+        Self { stages, codes }
+    }
+
+    /// 从资源路径读取关卡数据
+    ///
+    /// # Arguments
+    /// * `path` - 资源路径，与传给 [`crate::Assistant::load_resource`] 的路径相同
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::new(super::read_json(path.as_ref(), Self::FILE)?))
+    }
+
+    /// 解析 `stages.json` 的内容
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+
+    /// 所有关卡
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// 根据关卡编号查找关卡，编号重复时返回第一个
+    pub fn lookup(&self, code: &str) -> Option<&Stage> {
+        self.lookup_all(code).next()
+    }
+
+    /// 根据关卡编号查找所有关卡
+    pub fn lookup_all<'a>(&'a self, code: &str) -> impl Iterator<Item = &'a Stage> {
+        self.codes
+            .get(code)
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.stages[index])
+    }
+
+    /// 关卡在服务器上的某一天是否开放
+    ///
+    /// 当前/上次的关卡和剿灭作战总是开放，其他关卡只要有一个同编号的关卡开放即可
+    pub fn is_open_on(&self, stage: &StageSpec, date: NaiveDate, server: &Server) -> bool {
+        match stage {
+            StageSpec::Current | StageSpec::Annihilation(_) => true,
+            StageSpec::Named(code) => self.lookup_all(code).any(|stage| stage.is_open_on(date, server))
+        }
+    }
+
+    /// 服务器上某一天开放的所有关卡
+    pub fn open_stages_on<'a>(
+        &'a self,
+        date: NaiveDate,
+        server: &'a Server
+    ) -> impl Iterator<Item = &'a Stage> {
+        self.stages
+            .iter()
+            .filter(move |stage| stage.is_open_on(date, server))
+    }
+
+    /// 检查关卡，返回不合法的原因
+    fn check(&self, stage: &StageSpec, date: NaiveDate, server: &Server) -> Option<String> {
+        match stage {
+            StageSpec::Current | StageSpec::Annihilation(None) => None,
+            StageSpec::Annihilation(Some(id)) if !ANNIHILATION_IDS.contains(&id.as_str()) => Some(format!(
+                "未知的剿灭关卡 {id:?}，可选值: {}",
+                ANNIHILATION_IDS.join(", ")
+            )),
+            StageSpec::Annihilation(Some(_)) => None,
+            StageSpec::Named(code) if self.lookup(code).is_none() => {
+                let trimmed = code.trim();
+                if trimmed != code && self.lookup(trimmed).is_some() {
+                    Some(format!("关卡 {code:?} 不存在，是否为 {trimmed:?}"))
+                } else {
+                    Some(format!("关卡 {code:?} 不存在"))
+                }
+            },
+            StageSpec::Named(code) if !self.is_open_on(stage, date, server) => {
+                Some(format!("关卡 {code} 在 {server} 服 {date} 未开放"))
+            },
+            StageSpec::Named(_) => None
+        }
+    }
+}
+
+impl FightTask {
+    /// 根据关卡数据检查任务参数，除了 [`Task::validate`] 的检查外，还会检查关卡是否存在、今天是否开放
+    ///
+    /// 服务器使用任务的 `server`，默认为国服
+    pub fn validate_against(&self, catalog: &StageCatalog) -> Result<(), InvalidFields> {
+        let server = self.server.clone().unwrap_or(Server::CN);
+        self.validate_on(catalog, game_date(&server, Utc::now()))
+    }
+
+    /// 根据关卡数据检查任务参数，关卡需要在指定的日期开放
+    pub fn validate_on(&self, catalog: &StageCatalog, date: NaiveDate) -> Result<(), InvalidFields> {
+        let mut fields = match self.validate() {
+            Ok(()) => Vec::new(),
+            Err(err) => err.fields
+        };
+        let server = self.server.clone().unwrap_or(Server::CN);
+        if let Some(message) = self
+            .stage
+            .as_ref()
+            .and_then(|stage| catalog.check(stage, date, &server))
+        {
+            fields.push(InvalidField {
+                field: "stage",
+                message
+            });
+        }
+
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidFields {
+                task_type: self.task_type(),
+                fields
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAGES: &str = r#"[
+        {
+            "stageType": "MAIN",
+            "stageId": "main_01-07",
+            "zoneId": "main_1",
+            "code": "1-7",
+            "apCost": 6,
+            "existence": { "CN": { "exist": true }, "US": { "exist": true } }
+        },
+        {
+            "stageType": "DAILY",
+            "stageId": "wk_melee_6",
+            "zoneId": "weekly_1",
+            "code": "CE-6",
+            "apCost": 36,
+            "existence": { "CN": { "exist": true } }
+        },
+        {
+            "stageType": "ACTIVITY",
+            "stageId": "act1_01",
+            "zoneId": "act1_zone1",
+            "code": "EV-1",
+            "apCost": 12,
+            "existence": { "CN": { "exist": true, "openTime": 1717142400000, "closeTime": 1718352000000 } },
+            "dropInfos": []
+        }
+    ]"#;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let catalog = StageCatalog::from_json(STAGES).unwrap();
+        assert_eq!(catalog.stages().len(), 3);
+
+        let stage = catalog.lookup("1-7").unwrap();
+        assert_eq!(stage.stage_id, "main_01-07");
+        assert_eq!(stage.stage_type, StageType::Main);
+        assert_eq!(stage.ap_cost, Some(6));
+        assert!(catalog.lookup("1-7 ").is_none());
+
+        let stage = catalog.lookup("CE-6").unwrap();
+        assert_eq!(
+            stage.open_days(),
+            Some(&[Weekday::Tue, Weekday::Thu, Weekday::Sat, Weekday::Sun][..])
+        );
+    }
+
+    #[test]
+    fn test_is_open_on() {
+        let catalog = StageCatalog::from_json(STAGES).unwrap();
+        let main = StageSpec::from("1-7");
+        assert!(catalog.is_open_on(&main, date(2024, 6, 3), &Server::CN));
+        assert!(!catalog.is_open_on(&main, date(2024, 6, 3), &Server::JP));
+
+        // 2024-06-04 是星期二
+        let ce = StageSpec::from("CE-6");
+        assert!(catalog.is_open_on(&ce, date(2024, 6, 4), &Server::CN));
+        assert!(!catalog.is_open_on(&ce, date(2024, 6, 3), &Server::CN));
+
+        // 活动从 2024-05-31 16:00 开放到 2024-06-14 16:00（UTC+8）
+        let event = StageSpec::from("EV-1");
+        assert!(!catalog.is_open_on(&event, date(2024, 5, 30), &Server::CN));
+        assert!(catalog.is_open_on(&event, date(2024, 5, 31), &Server::CN));
+        assert!(catalog.is_open_on(&event, date(2024, 6, 14), &Server::CN));
+        assert!(!catalog.is_open_on(&event, date(2024, 6, 15), &Server::CN));
+
+        assert!(catalog.is_open_on(&StageSpec::Current, date(2024, 6, 15), &Server::CN));
+        assert_eq!(catalog.open_stages_on(date(2024, 6, 3), &Server::CN).count(), 2);
+    }
+
+    #[test]
+    fn test_game_date() {
+        let now = DateTime::parse_from_rfc3339("2024-06-04T19:30:00Z")
+            .unwrap()
+            .to_utc();
+        // 国服 06-05 03:30，还没有到凌晨 4 点
+        assert_eq!(game_date(&Server::CN, now), date(2024, 6, 4));
+        // 日服 06-05 04:30
+        assert_eq!(game_date(&Server::JP, now), date(2024, 6, 5));
+    }
+
+    #[test]
+    fn test_validate_on() {
+        let catalog = StageCatalog::from_json(STAGES).unwrap();
+        let monday = date(2024, 6, 3);
+        assert!(FightTask::builder()
+            .stage("1-7")
+            .build()
+            .validate_on(&catalog, monday)
+            .is_ok());
+        assert!(FightTask::builder()
+            .stage(StageSpec::Annihilation(Some("Chernobog".to_string())))
+            .build()
+            .validate_on(&catalog, monday)
+            .is_ok());
+
+        let messages = |task: FightTask| {
+            task.validate_on(&catalog, monday)
+                .unwrap_err()
+                .fields
+                .into_iter()
+                .map(|field| format!("{} {}", field.field, field.message))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(FightTask::builder().stage("1-7 ").medicine(1000).build()),
+            [
+                "medicine 1000 超出范围 0..=999",
+                r#"stage 关卡 "1-7 " 不存在，是否为 "1-7""#
+            ]
+        );
+        assert_eq!(
+            messages(FightTask::builder().stage("CE-6").build()),
+            ["stage 关卡 CE-6 在 CN 服 2024-06-03 未开放"]
+        );
+        assert_eq!(
+            messages(FightTask::builder().stage("1-7").server(Server::KR).build()),
+            ["stage 关卡 1-7 在 KR 服 2024-06-03 未开放"]
+        );
+        assert_eq!(
+            messages(FightTask::builder().stage("Gallifrey@Annihilation").build()),
+            [r#"stage 未知的剿灭关卡 "Gallifrey"，可选值: Chernobog, LungmenOutskirts, LungmenDowntown"#]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::Stream;
+
+use crate::protocol::message::Event;
+
+/// 事件流默认的缓冲区大小
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// 异步事件流
+///
+/// 通过 [`crate::Assistant::events`] 订阅，每个订阅者拥有独立的缓冲区，
+/// 只能收到订阅之后产生的事件。
+///
+/// # 背压
+///
+/// 回调运行在 MaaCore 的线程上，为了不阻塞 MaaCore，发送端永远不会等待订阅者。
+/// 当订阅者消费过慢、缓冲区（见 [`crate::AssistantBuilder::with_event_stream`]）被写满时，
+/// 最旧的事件会被覆盖并跳过，流会继续从仍在缓冲区中的最旧事件开始产出，
+/// 被跳过的事件数可以通过 [`EventStream::lagged`] 获取。
+///
+/// 收到 [`Event::Destroyed`] 或者实例被释放后，流会结束。
+///
+/// # Example
+/// ```rust,ignore
+/// use tokio_stream::StreamExt;
+///
+/// let mut events = assistant.events();
+/// assistant.start()?;
+/// while let Some(event) = events.next().await {
+///     println!("{:?}", event);
+/// }
+/// ```
+pub struct EventStream {
+    inner: BroadcastStream<Event>,
+    lagged: u64,
+    finished: bool
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: broadcast::Receiver<Event>) -> Self {
+        Self {
+            inner: BroadcastStream::new(receiver),
+            lagged: 0,
+            finished: false
+        }
+    }
+
+    /// 因消费过慢而被跳过的事件总数
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    if matches!(event, Event::Destroyed) {
+                        this.finished = true;
+                    }
+                    return Poll::Ready(Some(event));
+                },
+                Poll::Ready(Some(Err(BroadcastStreamRecvError::Lagged(skipped)))) => {
+                    this.lagged += skipped;
+                },
+                Poll::Ready(None) => {
+                    this.finished = true;
+                    return Poll::Ready(None);
+                },
+                Poll::Pending => return Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_event_stream() {
+        let (sender, receiver) = broadcast::channel(16);
+        let mut events = EventStream::new(receiver);
+
+        sender
+            .send(Event::parse(10001, json!({ "taskchain": "Fight", "taskid": 1 })))
+            .unwrap();
+        sender.send(Event::Destroyed).unwrap();
+        sender.send(Event::InternalError).unwrap();
+
+        assert!(matches!(events.next().await, Some(Event::TaskChainStart(_))));
+        assert!(matches!(events.next().await, Some(Event::Destroyed)));
+        assert!(events.next().await.is_none(), "收到 Destroyed 后流应该结束");
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_lagged() {
+        let (sender, receiver) = broadcast::channel(2);
+        let mut events = EventStream::new(receiver);
+
+        for _ in 0..5 {
+            sender.send(Event::InternalError).unwrap();
+        }
+        drop(sender);
+
+        let mut received = 0;
+        while events.next().await.is_some() {
+            received += 1;
+        }
+        assert_eq!(2, received);
+        assert_eq!(3, events.lagged());
+    }
+}
@@ -0,0 +1,691 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::message::{
+    AsyncCallDetails, ConnectionEvent, Event, Resolution, ScreencapCost, ScreencapMethod, SubTaskExtraInfo
+};
+
+/// 任务的最终状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    /// 任务链完成
+    Completed,
+    /// 任务链执行/识别错误
+    Error,
+    /// 任务链手动停止，或者实例被销毁时任务尚未结束
+    Stopped,
+    /// 全部任务完成时任务没有被执行，例如 `enable` 为 `false`
+    Skipped
+}
+
+/// 任务的执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskOutcome {
+    /// 任务 ID
+    pub task_id: i32,
+    /// 最终状态
+    pub status: TaskStatus,
+    /// 任务执行期间收集到的原子任务额外信息
+    pub extra_info: Vec<SubTaskExtraInfo>
+}
+
+#[derive(Default)]
+struct TaskState {
+    status: Option<TaskStatus>,
+    extra_info: Vec<SubTaskExtraInfo>,
+    waker: Option<Waker>
+}
+
+#[derive(Default)]
+struct TaskSlot {
+    state: Mutex<TaskState>,
+    cond: Condvar
+}
+
+impl TaskSlot {
+    fn lock(&self) -> MutexGuard<'_, TaskState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn resolve(&self, status: TaskStatus) {
+        let mut state = self.lock();
+        state.status = Some(status);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.cond.notify_all();
+    }
+}
+
+/// 任务完成句柄，由 [`crate::Assistant::append_task_with_handle`] 返回
+///
+/// 根据 MaaCore 回调中的 `TaskChainCompleted`、`TaskChainError`、`TaskChainStopped`
+/// 消息确定任务的最终状态，并收集该任务的 `SubTaskExtraInfo`。
+///
+/// 可以通过 [`TaskHandle::wait`] 阻塞等待，也可以直接 `.await`。
+///
+/// 未被执行的任务（例如 `enable` 为 `false`）不会收到任何任务链消息，
+/// 在收到 `AllTasksCompleted` 时以 [`TaskStatus::Skipped`] 结束；
+/// 调用 [`crate::Assistant::stop`] 或者实例被销毁时尚未结束的任务以 [`TaskStatus::Stopped`] 结束。
+pub struct TaskHandle {
+    task_id: i32,
+    slot: Arc<TaskSlot>
+}
+
+impl TaskHandle {
+    /// 任务 ID
+    pub fn id(&self) -> i32 {
+        self.task_id
+    }
+
+    /// 当前状态，任务尚未结束时返回 `None`
+    pub fn status(&self) -> Option<TaskStatus> {
+        self.slot.lock().status
+    }
+
+    /// 阻塞等待任务结束
+    pub fn wait(self) -> TaskOutcome {
+        let mut state = self.slot.lock();
+        while state.status.is_none() {
+            state = self.slot.cond.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        self.outcome(&mut state)
+    }
+
+    /// 阻塞等待任务结束，超时则返回句柄本身
+    pub fn wait_timeout(self, timeout: Duration) -> Result<TaskOutcome, Self> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.slot.lock();
+        while state.status.is_none() {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                drop(state);
+                return Err(self);
+            };
+            state = self
+                .slot
+                .cond
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        Ok(self.outcome(&mut state))
+    }
+
+    fn outcome(&self, state: &mut TaskState) -> TaskOutcome {
+        TaskOutcome {
+            task_id: self.task_id,
+            status: state.status.unwrap_or(TaskStatus::Stopped),
+            extra_info: std::mem::take(&mut state.extra_info)
+        }
+    }
+}
+
+impl Future for TaskHandle {
+    type Output = TaskOutcome;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.lock();
+        if state.status.is_some() {
+            Poll::Ready(self.outcome(&mut state))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// 可以被回调事件结束的句柄状态
+trait Slot: Default {
+    /// 是否已经结束
+    fn is_resolved(&self) -> bool;
+}
+
+impl Slot for TaskSlot {
+    fn is_resolved(&self) -> bool {
+        self.lock().status.is_some()
+    }
+}
+
+/// 以 ID 为键的尚未结束的句柄
+///
+/// 添加任务、发起调用时不持有锁，自定义的 [`crate::CoreApi`] 可以在调用期间同步发出回调。
+/// 调用返回之前 ID 还未知，此时收到的未知 ID 的回调先记录在 `early` 中，调用返回后再取出
+struct Registry<S> {
+    pending: HashMap<i32, Arc<S>>,
+    /// 正在进行的调用数量
+    registering: usize,
+    /// 调用返回之前收到的回调对应的句柄状态
+    early: HashMap<i32, Arc<S>>
+}
+
+impl<S> Default for Registry<S> {
+    fn default() -> Self {
+        Self {
+            pending: HashMap::new(),
+            registering: 0,
+            early: HashMap::new()
+        }
+    }
+}
+
+impl<S: Slot> Registry<S> {
+    /// 回调事件对应的句柄状态
+    fn get(&mut self, id: i32) -> Option<Arc<S>> {
+        match self.pending.get(&id) {
+            Some(slot) => Some(slot.clone()),
+            None if self.registering > 0 => Some(self.early.entry(id).or_default().clone()),
+            None => None
+        }
+    }
+
+    /// 结束回调事件对应的句柄，返回需要结束的句柄状态
+    fn remove(&mut self, id: i32) -> Option<Arc<S>> {
+        match self.pending.remove(&id) {
+            Some(slot) => Some(slot),
+            // 保留在 early 中，调用返回时会得到已经结束的句柄
+            None if self.registering > 0 => Some(self.early.entry(id).or_default().clone()),
+            None => None
+        }
+    }
+
+    /// 取出所有句柄状态
+    fn drain(&mut self) -> Vec<Arc<S>> {
+        self.pending
+            .drain()
+            .chain(self.early.drain())
+            .map(|(_, slot)| slot)
+            .collect()
+    }
+
+    /// 在不持有锁的情况下调用 `call`，返回新的句柄状态
+    ///
+    /// `call` 返回 `None` 时表示调用失败，不会创建句柄
+    fn register<E>(
+        registry: &Mutex<Self>,
+        call: impl FnOnce() -> Result<Option<i32>, E>
+    ) -> Result<Option<(i32, Arc<S>)>, E> {
+        let lock = || registry.lock().unwrap_or_else(PoisonError::into_inner);
+        lock().registering += 1;
+        let result = call();
+
+        let mut registry = lock();
+        registry.registering -= 1;
+        let slot = match result {
+            Ok(Some(id)) => {
+                let slot = registry.early.remove(&id).unwrap_or_default();
+                if !slot.is_resolved() {
+                    registry.pending.insert(id, slot.clone());
+                }
+                Some((id, slot))
+            },
+            _ => None
+        };
+        if registry.registering == 0 {
+            registry.early.clear();
+        }
+        result.map(|_| slot)
+    }
+}
+
+/// 跟踪尚未结束的任务，将回调事件关联到对应的 [`TaskHandle`]
+#[derive(Clone, Default)]
+pub(crate) struct TaskTracker {
+    pending: Arc<Mutex<Registry<TaskSlot>>>
+}
+
+impl TaskTracker {
+    fn lock(&self) -> MutexGuard<'_, Registry<TaskSlot>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 添加任务并跟踪
+    ///
+    /// 添加期间收到的该任务的回调事件同样会关联到返回的句柄
+    pub(crate) fn track<E>(&self, append: impl FnOnce() -> Result<i32, E>) -> Result<TaskHandle, E> {
+        let (task_id, slot) =
+            Registry::register(&self.pending, || append().map(Some))?.expect("添加成功时一定会创建句柄");
+        Ok(TaskHandle { task_id, slot })
+    }
+
+    /// 将所有尚未结束的任务标记为已停止
+    pub(crate) fn stop_all(&self) {
+        for slot in self.lock().drain() {
+            slot.resolve(TaskStatus::Stopped);
+        }
+    }
+
+    /// 将没有被执行的任务标记为已跳过
+    fn skip_unfinished(&self, finished_tasks: &[i32]) {
+        let skipped: Vec<_> = self
+            .lock()
+            .pending
+            .extract_if(|id, _| !finished_tasks.contains(id))
+            .map(|(_, slot)| slot)
+            .collect();
+        for slot in skipped {
+            slot.resolve(TaskStatus::Skipped);
+        }
+    }
+
+    /// 处理一条回调事件
+    pub(crate) fn handle(&self, event: &Event) {
+        let status = match event {
+            Event::TaskChainCompleted(_) => TaskStatus::Completed,
+            Event::TaskChainError(_) => TaskStatus::Error,
+            Event::TaskChainStopped(_) => TaskStatus::Stopped,
+            Event::SubTaskExtraInfo(info) => {
+                if let Some(slot) = info.taskid.and_then(|id| self.lock().get(id)) {
+                    slot.lock().extra_info.push(info.clone());
+                }
+                return;
+            },
+            Event::AllTasksCompleted(info) => return self.skip_unfinished(&info.finished_tasks),
+            Event::Destroyed => return self.stop_all(),
+            _ => return
+        };
+
+        let slot = event.task_id().and_then(|id| self.lock().remove(id));
+        if let Some(slot) = slot {
+            slot.resolve(status);
+        }
+    }
+}
+
+/// 外部异步调用句柄，由 [`crate::Assistant::click`]、[`crate::Assistant::capture_screenshot`]
+/// 和 [`crate::Assistant::connect`] 返回
+///
+/// 根据 MaaCore 回调中 `async_call_id` 相同的 `AsyncCallInfo` 消息得到调用结果，
+/// 可以通过 [`AsyncCall::wait`] 阻塞等待，也可以直接 `.await`。
+/// 实例被销毁时尚未结束的调用以失败结束。
+pub struct AsyncCall {
+    call_id: i32,
+    slot: Arc<CallSlot>
+}
+
+#[derive(Default)]
+struct CallState {
+    result: Option<AsyncCallDetails>,
+    waker: Option<Waker>
+}
+
+#[derive(Default)]
+struct CallSlot {
+    state: Mutex<CallState>,
+    cond: Condvar
+}
+
+impl CallSlot {
+    fn lock(&self) -> MutexGuard<'_, CallState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn resolve(&self, result: AsyncCallDetails) {
+        let mut state = self.lock();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.cond.notify_all();
+    }
+}
+
+impl AsyncCall {
+    /// 异步调用 ID
+    pub fn id(&self) -> i32 {
+        self.call_id
+    }
+
+    /// 调用结果，尚未结束时返回 `None`
+    pub fn result(&self) -> Option<AsyncCallDetails> {
+        self.slot.lock().result
+    }
+
+    /// 阻塞等待调用结束
+    pub fn wait(self) -> AsyncCallDetails {
+        let mut state = self.slot.lock();
+        loop {
+            if let Some(result) = state.result {
+                return result;
+            }
+            state = self.slot.cond.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// 阻塞等待调用结束，超时则返回句柄本身
+    pub fn wait_timeout(self, timeout: Duration) -> Result<AsyncCallDetails, Self> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.slot.lock();
+        loop {
+            if let Some(result) = state.result {
+                return Ok(result);
+            }
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                drop(state);
+                return Err(self);
+            };
+            state = self
+                .slot
+                .cond
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+}
+
+impl std::fmt::Debug for AsyncCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncCall")
+            .field("call_id", &self.call_id)
+            .field("result", &self.result())
+            .finish()
+    }
+}
+
+impl Future for AsyncCall {
+    type Output = AsyncCallDetails;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.lock();
+        match state.result {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// 跟踪尚未结束的外部异步调用，将 `AsyncCallInfo` 关联到对应的 [`AsyncCall`]
+#[derive(Clone, Default)]
+pub(crate) struct AsyncCallTracker {
+    pending: Arc<Mutex<Registry<CallSlot>>>
+}
+
+impl Slot for CallSlot {
+    fn is_resolved(&self) -> bool {
+        self.lock().result.is_some()
+    }
+}
+
+impl AsyncCallTracker {
+    fn lock(&self) -> MutexGuard<'_, Registry<CallSlot>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 发起调用并跟踪，调用失败（返回的 ID 为 `0`）时返回 `None`
+    ///
+    /// 调用期间收到的该调用的 `AsyncCallInfo` 同样会关联到返回的句柄，
+    /// 所以阻塞的调用和同步发出回调的 [`crate::CoreApi`] 实现也可以使用
+    pub(crate) fn track<E>(&self, call: impl FnOnce() -> Result<i32, E>) -> Result<Option<AsyncCall>, E> {
+        let call = || call().map(|call_id| Some(call_id).filter(|&call_id| call_id != 0));
+        let slot = Registry::register(&self.pending, call)?;
+        Ok(slot.map(|(call_id, slot)| AsyncCall { call_id, slot }))
+    }
+
+    /// 处理一条回调事件
+    pub(crate) fn handle(&self, event: &Event) {
+        match event {
+            Event::AsyncCallInfo(info) => {
+                let slot = self.lock().remove(info.async_call_id);
+                if let Some(slot) = slot {
+                    slot.resolve(info.details);
+                }
+            },
+            Event::Destroyed => {
+                for slot in self.lock().drain() {
+                    slot.resolve(AsyncCallDetails { ret: false, cost: 0 });
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// 连接结果，由 [`crate::Assistant::connect_and_wait`] 返回
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConnectionReport {
+    /// 设备唯一码
+    pub uuid: Option<String>,
+    /// 设备分辨率
+    pub resolution: Option<Resolution>,
+    /// 最快的截图方式及其耗时
+    pub screencap: Option<ScreencapMethod>,
+    /// 截图耗时统计，MaaCore 在连接期间不一定会发出
+    pub screencap_cost: Option<ScreencapCost>
+}
+
+#[derive(Default)]
+struct ConnectionState {
+    /// 是否正在等待连接
+    active: bool,
+    report: ConnectionReport,
+    /// 导致连接失败的 `what` 和 `why`
+    failure: Option<(String, Option<String>)>,
+    /// 已经结束的连接调用，值为调用是否成功
+    calls: HashMap<i32, bool>
+}
+
+/// 收集连接期间的 `ConnectionInfo`，并等待连接的异步调用结束
+#[derive(Clone, Default)]
+pub(crate) struct ConnectionTracker {
+    state: Arc<(Mutex<ConnectionState>, Condvar)>
+}
+
+/// 连接等待的结果
+pub(crate) enum ConnectionOutcome {
+    Connected(ConnectionReport),
+    /// 连接失败，包含 `ConnectionInfo` 中的 `what` 和 `why`
+    Aborted(String, Option<String>),
+    /// 异步调用失败，但没有收到具体原因
+    Failed,
+    Timeout
+}
+
+impl ConnectionTracker {
+    fn lock(&self) -> MutexGuard<'_, ConnectionState> {
+        self.state.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 开始收集，需要在调用连接之前调用，避免错过连接期间的消息
+    pub(crate) fn begin(&self) {
+        *self.lock() = ConnectionState {
+            active: true,
+            ..Default::default()
+        };
+    }
+
+    /// 结束收集，用于连接调用本身失败、不会有异步调用结果的情况
+    pub(crate) fn cancel(&self) {
+        self.lock().active = false;
+    }
+
+    /// 等待指定的连接调用结束，无论结果如何都会结束收集
+    pub(crate) fn wait(&self, call_id: i32, timeout: Duration) -> ConnectionOutcome {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        let outcome = loop {
+            if let Some((what, why)) = state.failure.take() {
+                break ConnectionOutcome::Aborted(what, why);
+            }
+            match state.calls.get(&call_id) {
+                Some(true) => break ConnectionOutcome::Connected(std::mem::take(&mut state.report)),
+                Some(false) => break ConnectionOutcome::Failed,
+                None => {}
+            }
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break ConnectionOutcome::Timeout;
+            };
+            state = self
+                .state
+                .1
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        };
+        state.active = false;
+        outcome
+    }
+
+    /// 处理一条回调事件
+    pub(crate) fn handle(&self, event: &Event) {
+        let mut state = self.lock();
+        if !state.active {
+            return;
+        }
+        match event {
+            Event::ConnectionInfo(info) => match &info.what {
+                ConnectionEvent::UuidGot => {
+                    state.report.uuid = info.uuid.clone().filter(|uuid| !uuid.is_empty());
+                },
+                ConnectionEvent::ResolutionGot(resolution) => state.report.resolution = Some(*resolution),
+                ConnectionEvent::FastestWayToScreencap(method) => {
+                    state.report.screencap = Some(method.clone());
+                },
+                ConnectionEvent::ScreencapCost(cost) => state.report.screencap_cost = Some(*cost),
+                ConnectionEvent::ConnectFailed | ConnectionEvent::Disconnect => {
+                    let why = info.why.clone().filter(|why| !why.is_empty());
+                    state.failure = Some((info.what.what().to_string(), why));
+                },
+                _ => return
+            },
+            Event::AsyncCallInfo(info) if info.what == "Connect" => {
+                state.calls.insert(info.async_call_id, info.details.ret);
+            },
+            Event::Destroyed => state.failure = Some(("Destroyed".to_string(), None)),
+            _ => return
+        }
+        self.state.1.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn track(tracker: &TaskTracker, task_id: i32) -> TaskHandle {
+        tracker.track(|| Ok::<_, ()>(task_id)).unwrap()
+    }
+
+    #[test]
+    fn test_task_handle_wait() {
+        let tracker = TaskTracker::default();
+        let handle = track(&tracker, 1);
+        let other = track(&tracker, 2);
+
+        tracker.handle(&Event::parse(10001, json!({ "taskchain": "Fight", "taskid": 1 })));
+        tracker.handle(&Event::parse(
+            20003,
+            json!({ "taskchain": "Fight", "taskid": 1, "what": "PenguinId", "details": { "id": "123" } })
+        ));
+        tracker.handle(&Event::parse(
+            20003,
+            json!({ "taskchain": "Fight", "taskid": 2, "what": "PenguinId", "details": { "id": "456" } })
+        ));
+        assert_eq!(None, handle.status());
+
+        let waiter = std::thread::spawn(move || handle.wait());
+        tracker.handle(&Event::parse(10002, json!({ "taskchain": "Fight", "taskid": 1 })));
+
+        let outcome = waiter.join().unwrap();
+        assert_eq!(1, outcome.task_id);
+        assert_eq!(TaskStatus::Completed, outcome.status);
+        assert_eq!(1, outcome.extra_info.len());
+
+        let other = other.wait_timeout(Duration::from_millis(10)).unwrap_err();
+        tracker.handle(&Event::Destroyed);
+        assert_eq!(TaskStatus::Stopped, other.wait().status);
+    }
+
+    #[tokio::test]
+    async fn test_task_handle_await() {
+        let tracker = TaskTracker::default();
+        let handle = track(&tracker, 3);
+
+        let events = tracker.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            events.handle(&Event::parse(
+                10000,
+                json!({ "taskchain": "Recruit", "taskid": 3 })
+            ));
+        });
+
+        let outcome = handle.await;
+        assert_eq!(TaskStatus::Error, outcome.status);
+    }
+
+    #[test]
+    fn test_connection_tracker() {
+        let tracker = ConnectionTracker::default();
+        // 开始收集之前的消息会被忽略
+        tracker.handle(&Event::parse(
+            2,
+            json!({ "what": "ResolutionGot", "details": { "width": 1920, "height": 1080 } })
+        ));
+
+        tracker.begin();
+        tracker.handle(&Event::parse(
+            2,
+            json!({ "what": "ScreencapCost", "details": { "min": 10, "max": 30, "avg": 20 } })
+        ));
+        tracker.handle(&Event::parse(
+            4,
+            json!({ "what": "Connect", "async_call_id": 7, "details": { "ret": true, "cost": 100 } })
+        ));
+        let ConnectionOutcome::Connected(report) = tracker.wait(7, Duration::from_secs(1)) else {
+            panic!("连接应该成功");
+        };
+        assert!(report.resolution.is_none());
+        assert_eq!(report.screencap_cost.map(|cost| cost.avg), Some(20));
+
+        tracker.begin();
+        assert!(matches!(
+            tracker.wait(8, Duration::from_millis(10)),
+            ConnectionOutcome::Timeout
+        ));
+
+        tracker.begin();
+        tracker.handle(&Event::parse(
+            2,
+            json!({ "what": "Disconnect", "why": "Reconnect failed", "details": {} })
+        ));
+        assert!(matches!(
+            tracker.wait(9, Duration::from_secs(1)),
+            ConnectionOutcome::Aborted(what, Some(why)) if what == "Disconnect" && why == "Reconnect failed"
+        ));
+    }
+
+    #[test]
+    fn test_async_call_tracker() {
+        let tracker = AsyncCallTracker::default();
+        assert!(tracker.track(|| Ok::<_, ()>(0)).unwrap().is_none());
+        assert!(tracker.track(|| Err::<i32, _>(())).is_err());
+
+        let click = tracker.track(|| Ok::<_, ()>(1)).unwrap().unwrap();
+        let screencap = tracker.track(|| Ok::<_, ()>(2)).unwrap().unwrap();
+        assert_eq!(click.id(), 1);
+        assert!(click.result().is_none());
+
+        tracker.handle(&Event::parse(
+            4,
+            json!({ "what": "Click", "async_call_id": 1, "details": { "ret": true, "cost": 15 } })
+        ));
+        let result = click.wait_timeout(Duration::from_secs(1)).unwrap();
+        assert!(result.ret);
+        assert_eq!(result.cost, 15);
+
+        let screencap = screencap.wait_timeout(Duration::from_millis(10)).unwrap_err();
+        tracker.handle(&Event::Destroyed);
+        assert!(!screencap.wait().ret);
+    }
+}
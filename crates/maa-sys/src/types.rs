@@ -1,19 +1,58 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 use thiserror::Error;
 
+use crate::profile::ProfileFormat;
+use crate::task::{InvalidFields, MissingFields};
+
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("运行库加载失败")]
-    LibraryLoadFailed,
-    #[error("资源加载失败")]
-    ResourceLoadFailed,
+    #[error("运行库加载失败: {}", .path.display())]
+    LibraryLoadFailed {
+        /// 运行库文件的路径
+        path: PathBuf,
+        /// 加载动态链接库时的错误，平台不支持时为 `None`
+        source: Option<libloading::Error>
+    },
+    #[error("资源加载失败: {}", .path.display())]
+    ResourceLoadFailed {
+        /// 资源目录的路径
+        path: PathBuf
+    },
+    #[error("未设置 {0} 路径")]
+    PathNotSet(&'static str),
     #[error("创建实例失败")]
     CreateFailed,
-    #[error("连接失败")]
-    ConnectFailed,
-    #[error("任务添加失败")]
-    TaskAppendFailed,
-    #[error("任务参数设置失败")]
-    TaskParamsSetFailed,
+    #[error("执行 adb 失败: {message}，adb 路径: {adb_path}")]
+    AdbFailed { adb_path: String, message: String },
+    #[error("连接 {address} 失败，adb 路径: {adb_path}")]
+    ConnectFailed { adb_path: String, address: String },
+    #[error("连接 {address} 失败: {what}{}", .why.as_ref().map(|why| format!("，原因: {why}")).unwrap_or_default())]
+    ConnectAborted {
+        address: String,
+        /// 连接信息的类型，如 `ConnectFailed`、`Disconnect`
+        what: String,
+        /// MaaCore 给出的原因
+        why: Option<String>
+    },
+    #[error("连接 {address} 超时（{timeout:?}）")]
+    ConnectTimeout { address: String, timeout: Duration },
+    #[error(transparent)]
+    MissingFields(#[from] MissingFields),
+    #[error(transparent)]
+    InvalidFields(#[from] InvalidFields),
+    #[error("添加 {task_type} 任务失败，参数: {params}")]
+    TaskAppendFailed { task_type: String, params: String },
+    #[error("设置任务 {task_id}（{task_type}）参数失败，参数: {params}")]
+    TaskParamsSetFailed {
+        task_id: i32,
+        task_type: String,
+        params: String
+    },
     #[error("启动失败")]
     StartFailed,
     #[error("停止失败")]
@@ -24,18 +63,203 @@ pub enum Error {
     ClickFailed,
     #[error("截图失败")]
     CaptureFailed,
-    #[error("设置实例选项失败")]
+    #[error("设置实例选项 {key:?} 为 {value} 失败")]
+    SetInstanceOptionFailed { key: InstanceOptionKey, value: String },
+    #[error("设置选项 {key:?} 为 {value} 失败")]
+    SetStaticOptionFailed { key: StaticOptionKey, value: String },
+    #[cfg(feature = "image")]
+    #[error("图像解码失败: {0}")]
+    ImageDecodeFailed(#[source] image::ImageError),
+    #[cfg(feature = "image")]
+    #[error("图像保存到 {} 失败: {source}", .path.display())]
+    ImageSaveFailed {
+        /// 保存的文件路径
+        path: PathBuf,
+        source: image::ImageError
+    },
+    #[cfg(feature = "image")]
+    #[error("图像数据与尺寸不一致: {width}x{height}，每行 {stride} 字节，实际 {len} 字节")]
+    InvalidFrame {
+        width: u32,
+        height: u32,
+        stride: usize,
+        /// 实际的数据长度
+        len: usize
+    },
+    #[error("无法根据扩展名判断配置文件 {} 的格式，支持 .toml 和 .json", .0.display())]
+    ProfileFormatUnknown(PathBuf),
+    #[error("读写配置文件 {} 失败: {source}", .path.display())]
+    ProfileIoFailed { path: PathBuf, source: std::io::Error },
+    #[error("解析 {format} 配置失败: {message}")]
+    ProfileParseFailed { format: ProfileFormat, message: String },
+    #[error("序列化 {format} 配置失败: {message}")]
+    ProfileSerializeFailed { format: ProfileFormat, message: String },
+    #[error("配置无效: {}", .problems.join("；"))]
+    ProfileInvalid {
+        /// 发现的所有问题
+        problems: Vec<String>
+    },
+    #[error("读取资源文件 {} 失败: {source}", .path.display())]
+    ResourceIoFailed { path: PathBuf, source: std::io::Error },
+    #[error("解析资源文件 {} 失败: {source}", .path.display())]
+    ResourceParseFailed {
+        path: PathBuf,
+        source: serde_json::Error
+    },
+    #[error("读写报告文件 {} 失败: {source}", .path.display())]
+    ReportIoFailed { path: PathBuf, source: std::io::Error },
+    #[error("报告文件 {} 的 JSON 格式错误: {source}", .path.display())]
+    ReportFormatFailed {
+        path: PathBuf,
+        source: serde_json::Error
+    },
+    #[error("字符串中包含 NUL 字符")]
+    InvalidString(#[from] std::ffi::NulError),
+    #[error("实例句柄无效")]
+    InvalidHandle,
+    #[error("运行库中缺少函数 {0}")]
+    SymbolNotFound(&'static str),
+    #[error("未知错误")]
+    Unknown
+}
+
+impl Error {
+    /// 错误码，不随显示语言变化，可用于匹配错误类型或者本地化
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::LibraryLoadFailed { .. } => ErrorCode::LibraryLoadFailed,
+            Error::ResourceLoadFailed { .. } => ErrorCode::ResourceLoadFailed,
+            Error::PathNotSet(_) => ErrorCode::PathNotSet,
+            Error::CreateFailed => ErrorCode::CreateFailed,
+            Error::AdbFailed { .. } => ErrorCode::AdbFailed,
+            Error::ConnectFailed { .. } => ErrorCode::ConnectFailed,
+            Error::ConnectAborted { .. } => ErrorCode::ConnectAborted,
+            Error::ConnectTimeout { .. } => ErrorCode::ConnectTimeout,
+            Error::MissingFields(_) => ErrorCode::MissingFields,
+            Error::InvalidFields(_) => ErrorCode::InvalidFields,
+            Error::TaskAppendFailed { .. } => ErrorCode::TaskAppendFailed,
+            Error::TaskParamsSetFailed { .. } => ErrorCode::TaskParamsSetFailed,
+            Error::StartFailed => ErrorCode::StartFailed,
+            Error::StopFailed => ErrorCode::StopFailed,
+            Error::BackToHomeFailed => ErrorCode::BackToHomeFailed,
+            Error::ClickFailed => ErrorCode::ClickFailed,
+            Error::CaptureFailed => ErrorCode::CaptureFailed,
+            Error::SetInstanceOptionFailed { .. } => ErrorCode::SetInstanceOptionFailed,
+            Error::SetStaticOptionFailed { .. } => ErrorCode::SetStaticOptionFailed,
+            #[cfg(feature = "image")]
+            Error::ImageDecodeFailed(_) => ErrorCode::ImageDecodeFailed,
+            #[cfg(feature = "image")]
+            Error::ImageSaveFailed { .. } => ErrorCode::ImageSaveFailed,
+            #[cfg(feature = "image")]
+            Error::InvalidFrame { .. } => ErrorCode::InvalidFrame,
+            Error::ProfileFormatUnknown(_) => ErrorCode::ProfileFormatUnknown,
+            Error::ProfileIoFailed { .. } => ErrorCode::ProfileIoFailed,
+            Error::ProfileParseFailed { .. } => ErrorCode::ProfileParseFailed,
+            Error::ProfileSerializeFailed { .. } => ErrorCode::ProfileSerializeFailed,
+            Error::ProfileInvalid { .. } => ErrorCode::ProfileInvalid,
+            Error::ResourceIoFailed { .. } => ErrorCode::ResourceIoFailed,
+            Error::ResourceParseFailed { .. } => ErrorCode::ResourceParseFailed,
+            Error::ReportIoFailed { .. } => ErrorCode::ReportIoFailed,
+            Error::ReportFormatFailed { .. } => ErrorCode::ReportFormatFailed,
+            Error::InvalidString(_) => ErrorCode::InvalidString,
+            Error::InvalidHandle => ErrorCode::InvalidHandle,
+            Error::SymbolNotFound(_) => ErrorCode::SymbolNotFound,
+            Error::Unknown => ErrorCode::Unknown
+        }
+    }
+}
+
+/// [`Error`] 对应的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    LibraryLoadFailed,
+    ResourceLoadFailed,
+    PathNotSet,
+    CreateFailed,
+    AdbFailed,
+    ConnectFailed,
+    ConnectAborted,
+    ConnectTimeout,
+    MissingFields,
+    InvalidFields,
+    TaskAppendFailed,
+    TaskParamsSetFailed,
+    StartFailed,
+    StopFailed,
+    BackToHomeFailed,
+    ClickFailed,
+    CaptureFailed,
     SetInstanceOptionFailed,
-    #[error("设置选项失败")]
     SetStaticOptionFailed,
-    #[error("内容太大")]
-    ContentTooLarge(usize),
-    #[error("未知错误")]
+    ImageDecodeFailed,
+    ImageSaveFailed,
+    InvalidFrame,
+    ProfileFormatUnknown,
+    ProfileIoFailed,
+    ProfileParseFailed,
+    ProfileSerializeFailed,
+    ProfileInvalid,
+    ResourceIoFailed,
+    ResourceParseFailed,
+    ReportIoFailed,
+    ReportFormatFailed,
+    InvalidString,
+    InvalidHandle,
+    SymbolNotFound,
     Unknown
 }
 
+impl ErrorCode {
+    /// 错误码的字符串形式，如 `connect_failed`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::LibraryLoadFailed => "library_load_failed",
+            ErrorCode::ResourceLoadFailed => "resource_load_failed",
+            ErrorCode::PathNotSet => "path_not_set",
+            ErrorCode::CreateFailed => "create_failed",
+            ErrorCode::AdbFailed => "adb_failed",
+            ErrorCode::ConnectFailed => "connect_failed",
+            ErrorCode::ConnectAborted => "connect_aborted",
+            ErrorCode::ConnectTimeout => "connect_timeout",
+            ErrorCode::MissingFields => "missing_fields",
+            ErrorCode::InvalidFields => "invalid_fields",
+            ErrorCode::TaskAppendFailed => "task_append_failed",
+            ErrorCode::TaskParamsSetFailed => "task_params_set_failed",
+            ErrorCode::StartFailed => "start_failed",
+            ErrorCode::StopFailed => "stop_failed",
+            ErrorCode::BackToHomeFailed => "back_to_home_failed",
+            ErrorCode::ClickFailed => "click_failed",
+            ErrorCode::CaptureFailed => "capture_failed",
+            ErrorCode::SetInstanceOptionFailed => "set_instance_option_failed",
+            ErrorCode::SetStaticOptionFailed => "set_static_option_failed",
+            ErrorCode::ImageDecodeFailed => "image_decode_failed",
+            ErrorCode::ImageSaveFailed => "image_save_failed",
+            ErrorCode::InvalidFrame => "invalid_frame",
+            ErrorCode::ProfileFormatUnknown => "profile_format_unknown",
+            ErrorCode::ProfileIoFailed => "profile_io_failed",
+            ErrorCode::ProfileParseFailed => "profile_parse_failed",
+            ErrorCode::ProfileSerializeFailed => "profile_serialize_failed",
+            ErrorCode::ProfileInvalid => "profile_invalid",
+            ErrorCode::ResourceIoFailed => "resource_io_failed",
+            ErrorCode::ResourceParseFailed => "resource_parse_failed",
+            ErrorCode::ReportIoFailed => "report_io_failed",
+            ErrorCode::ReportFormatFailed => "report_format_failed",
+            ErrorCode::InvalidString => "invalid_string",
+            ErrorCode::InvalidHandle => "invalid_handle",
+            ErrorCode::SymbolNotFound => "symbol_not_found",
+            ErrorCode::Unknown => "unknown"
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub enum StaticOptionKey {
     /// 无效
     Invalid,
@@ -46,7 +270,7 @@ pub enum StaticOptionKey {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub enum InstanceOptionKey {
     /// 已弃用
     Invalid = 0,
@@ -65,3 +289,190 @@ pub enum InstanceOptionKey {
     /// 退出时是否杀掉 Adb 进程， "0" | "1"
     KillAdbOnExit = 5
 }
+
+/// 触控模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TouchMode {
+    /// 使用 adb 命令模拟触控，兼容性最好但速度较慢
+    #[serde(rename = "adb")]
+    Adb,
+    /// 使用 minitouch，需要设备支持
+    #[serde(rename = "minitouch")]
+    Minitouch,
+    /// 使用 maatouch，minitouch 的替代实现
+    #[serde(rename = "maatouch")]
+    MaaTouch,
+    /// PlayCover 原生运行时使用
+    MacPlayTools
+}
+
+impl TouchMode {
+    /// MaaCore 使用的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TouchMode::Adb => "adb",
+            TouchMode::Minitouch => "minitouch",
+            TouchMode::MaaTouch => "maatouch",
+            TouchMode::MacPlayTools => "MacPlayTools"
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "adb" => Some(TouchMode::Adb),
+            "minitouch" => Some(TouchMode::Minitouch),
+            "maatouch" => Some(TouchMode::MaaTouch),
+            "MacPlayTools" => Some(TouchMode::MacPlayTools),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for TouchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 带有类型的实例选项，通过 [`crate::Assistant::set_option`] 设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InstanceOption {
+    /// 触控模式
+    TouchMode(TouchMode),
+    /// 是否暂停下干员，同时影响抄作业、肉鸽、保全
+    DeploymentWithPause(bool),
+    /// 是否使用 AdbLite
+    AdbLiteEnabled(bool),
+    /// 退出时是否杀掉 Adb 进程
+    KillAdbOnExit(bool)
+}
+
+impl InstanceOption {
+    /// 选项对应的键
+    pub fn key(&self) -> InstanceOptionKey {
+        match self {
+            InstanceOption::TouchMode(_) => InstanceOptionKey::TouchMode,
+            InstanceOption::DeploymentWithPause(_) => InstanceOptionKey::DeploymentWithPause,
+            InstanceOption::AdbLiteEnabled(_) => InstanceOptionKey::AdbLiteEnabled,
+            InstanceOption::KillAdbOnExit(_) => InstanceOptionKey::KillAdbOnExit
+        }
+    }
+
+    /// 传给 MaaCore 的选项值，布尔值为 `"1"` 或 `"0"`
+    pub fn value(&self) -> String {
+        let flag = |value: bool| if value { "1" } else { "0" }.to_string();
+        match self {
+            InstanceOption::TouchMode(mode) => mode.as_str().to_string(),
+            InstanceOption::DeploymentWithPause(value)
+            | InstanceOption::AdbLiteEnabled(value)
+            | InstanceOption::KillAdbOnExit(value) => flag(*value)
+        }
+    }
+
+    /// 从键和 MaaCore 的选项值解析，无法识别时返回 `None`
+    pub fn parse(key: InstanceOptionKey, value: &str) -> Option<Self> {
+        let flag = || match value {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None
+        };
+        match key {
+            InstanceOptionKey::TouchMode => TouchMode::parse(value).map(InstanceOption::TouchMode),
+            InstanceOptionKey::DeploymentWithPause => flag().map(InstanceOption::DeploymentWithPause),
+            InstanceOptionKey::AdbLiteEnabled => flag().map(InstanceOption::AdbLiteEnabled),
+            InstanceOptionKey::KillAdbOnExit => flag().map(InstanceOption::KillAdbOnExit),
+            InstanceOptionKey::Invalid | InstanceOptionKey::MinitouchEnabled => None
+        }
+    }
+}
+
+/// 已经设置到实例上的选项，没有设置过的选项为 `None`，此时使用 MaaCore 的默认值
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceOptions {
+    pub touch_mode: Option<TouchMode>,
+    pub deployment_with_pause: Option<bool>,
+    pub adb_lite_enabled: Option<bool>,
+    pub kill_adb_on_exit: Option<bool>
+}
+
+impl InstanceOptions {
+    /// 记录设置成功的选项
+    pub fn apply(&mut self, option: InstanceOption) {
+        match option {
+            InstanceOption::TouchMode(mode) => self.touch_mode = Some(mode),
+            InstanceOption::DeploymentWithPause(value) => self.deployment_with_pause = Some(value),
+            InstanceOption::AdbLiteEnabled(value) => self.adb_lite_enabled = Some(value),
+            InstanceOption::KillAdbOnExit(value) => self.kill_adb_on_exit = Some(value)
+        }
+    }
+
+    /// 所有设置过的选项
+    pub fn to_vec(&self) -> Vec<InstanceOption> {
+        [
+            self.touch_mode.map(InstanceOption::TouchMode),
+            self.deployment_with_pause
+                .map(InstanceOption::DeploymentWithPause),
+            self.adb_lite_enabled.map(InstanceOption::AdbLiteEnabled),
+            self.kill_adb_on_exit.map(InstanceOption::KillAdbOnExit)
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_option() {
+        let option = InstanceOption::TouchMode(TouchMode::MaaTouch);
+        assert!(matches!(option.key(), InstanceOptionKey::TouchMode));
+        assert_eq!(option.value(), "maatouch");
+        assert_eq!(InstanceOption::KillAdbOnExit(true).value(), "1");
+
+        assert_eq!(
+            InstanceOption::parse(InstanceOptionKey::TouchMode, "adb"),
+            Some(InstanceOption::TouchMode(TouchMode::Adb))
+        );
+        assert_eq!(InstanceOption::parse(InstanceOptionKey::TouchMode, "abd"), None);
+        assert_eq!(
+            InstanceOption::parse(InstanceOptionKey::AdbLiteEnabled, "0"),
+            Some(InstanceOption::AdbLiteEnabled(false))
+        );
+
+        let options = vec![
+            InstanceOption::TouchMode(TouchMode::MacPlayTools),
+            InstanceOption::DeploymentWithPause(true),
+        ];
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"TouchMode":"MacPlayTools"},{"DeploymentWithPause":true}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<InstanceOption>>(&json).unwrap(),
+            options
+        );
+    }
+
+    #[test]
+    fn test_instance_options() {
+        let mut options = InstanceOptions::default();
+        options.apply(InstanceOption::TouchMode(TouchMode::Adb));
+        options.apply(InstanceOption::KillAdbOnExit(false));
+        options.apply(InstanceOption::TouchMode(TouchMode::Minitouch));
+
+        assert_eq!(options.touch_mode, Some(TouchMode::Minitouch));
+        assert_eq!(options.deployment_with_pause, None);
+        assert_eq!(
+            options.to_vec(),
+            [
+                InstanceOption::TouchMode(TouchMode::Minitouch),
+                InstanceOption::KillAdbOnExit(false)
+            ]
+        );
+    }
+}
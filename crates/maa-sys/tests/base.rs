@@ -1,25 +1,57 @@
-use maa_sys::{task, Assistant, Connection, InstanceOptionKey};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use maa_mock_core::{Callback, Script, TaskResult, TaskScript};
+use maa_sys::task::{AnyTask, Task};
+use maa_sys::{
+    task, Assistant, Connection, ConnectionConfig, Error, ErrorCode, Event, ExtraInfo, InstanceOption,
+    InstanceOptionKey, Resolution, TaskStatus, TouchMode
+};
+use serde_json::json;
+
+mod common;
+
+#[test]
+fn test_send_sync() {
+    // Assistant 的所有字段都是线程安全的，不需要手动实现 Send 和 Sync
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Assistant>();
+}
 
 #[test]
 fn test_version() {
-    let version = Assistant::init(env!("MAA_RESOURCE_PATH"))
-        .unwrap()
-        .version()
-        .unwrap();
-    assert_ne!(version, "");
+    let (assistant, _dir) = common::create_test_assistant(Script::new().version("v5.16.10"));
+    let version = assistant.version().unwrap();
+    assert_eq!(version, "v5.16.10");
 }
 
 #[test]
-#[ignore = "MaaCore 这个功能好像有点问题，暂时跳过"]
 fn test_get_uuid() {
-    let assistant = Assistant::init(env!("MAA_RESOURCE_PATH")).unwrap();
+    let (assistant, _dir) = common::create_test_assistant(Script::new().uuid("a1b2c3"));
     let uuid = assistant.get_uuid().unwrap();
-    assert_ne!(uuid, "");
+    assert_eq!(uuid, "a1b2c3");
+}
+
+#[test]
+fn test_load_library_failed() {
+    let dir = tempfile::tempdir().unwrap();
+    let Err(err) = Assistant::load_library(dir.path()) else {
+        panic!("加载不存在的运行库应该失败");
+    };
+    assert_eq!(err.code(), ErrorCode::LibraryLoadFailed);
+    assert_eq!(err.code().to_string(), "library_load_failed");
+    match err {
+        Error::LibraryLoadFailed { path, source } => {
+            assert!(path.starts_with(dir.path()));
+            assert!(source.is_some());
+        },
+        err => panic!("unexpected error: {err}")
+    }
 }
 
 #[test]
 fn test_get_tasks_list() {
-    let mut assistant = Assistant::init(env!("MAA_RESOURCE_PATH")).unwrap();
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
     assistant
         .append_task(
             task::StartUpTask::builder()
@@ -38,28 +70,75 @@ fn test_get_tasks_list() {
 }
 
 #[test]
-#[ignore = "不太好测试，单独跑能跑通就行"]
-fn test_connect_device() {
-    let mut assistant = Assistant::init_with_callback(env!("MAA_RESOURCE_PATH"), |msg_id, details| {
-        let details_json = details.as_object().unwrap();
-        println!("收到回调: msg_id={:?}\n details={:?}", msg_id, details_json);
-    })
-    .unwrap();
-
+fn test_task_queue_round_trip() {
+    let (mut assistant, dir) = common::create_test_assistant(Script::new());
+    assistant
+        .append_task(task::StartUpTask::builder().client_type("Official").build())
+        .unwrap();
     assistant
-        .set_instance_option(InstanceOptionKey::TouchMode, "adb")
+        .append_task(task::FightTask::builder().stage("1-7").medicine(3).build())
         .unwrap();
+    let custom = AnyTask::from_params("Custom", r#"{"enable":true,"task_names":["AwardBegin"]}"#).unwrap();
+    let custom_id = assistant.append_task(custom).unwrap();
     assistant
-        .connect(Connection::adb("adb", "192.168.20.29:44847"), None)
+        .set_task_params(
+            custom_id,
+            task::CustomTask::builder()
+                .enable(true)
+                .task_names(vec!["MallBegin".to_string()])
+                .build()
+        )
         .unwrap();
 
-    if !assistant.is_connected() {
-        println!("connect failed");
-        return;
+    let path = dir.path().join("queue.json");
+    let saved = serde_json::to_string(&assistant.get_tasks_list().unwrap()).unwrap();
+    std::fs::write(&path, &saved).unwrap();
+
+    let tasks: Vec<AnyTask> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(tasks[2].task_type(), "Custom");
+    assert_eq!(
+        tasks[2].to_json(),
+        r#"{"enable":true,"task_names":["MallBegin"]}"#
+    );
+
+    let (mut restored, _dir) = common::create_test_assistant(Script::new());
+    for task in tasks {
+        restored.append_task(task).unwrap();
     }
+    assert_eq!(
+        serde_json::to_string(&restored.get_tasks_list().unwrap()).unwrap(),
+        saved
+    );
+}
+
+#[test]
+fn test_connect_device() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
 
     assistant
-        .append_task(
+        .set_option(InstanceOption::TouchMode(TouchMode::Adb))
+        .unwrap();
+    assistant
+        .set_instance_option(InstanceOptionKey::KillAdbOnExit, "1")
+        .unwrap();
+    assert_eq!(
+        assistant.instance_options().to_vec(),
+        [
+            InstanceOption::TouchMode(TouchMode::Adb),
+            InstanceOption::KillAdbOnExit(true)
+        ]
+    );
+    assistant
+        .connect(
+            Connection::adb("adb", "127.0.0.1:5555"),
+            Some(ConnectionConfig::General)
+        )
+        .unwrap()
+        .wait();
+    assert!(assistant.is_connected());
+
+    let handle = assistant
+        .append_task_with_handle(
             task::StartUpTask::builder()
                 .enable(true)
                 .client_type("Official")
@@ -69,9 +148,226 @@ fn test_connect_device() {
         .unwrap();
 
     assistant.start().unwrap();
+    assert_eq!(handle.wait().status, TaskStatus::Completed);
+}
+
+#[test]
+fn test_skipped_task() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
+    assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap()
+        .wait();
+
+    let skipped = assistant
+        .append_task_with_handle(task::StartUpTask::builder().enable(false).build())
+        .unwrap();
+    let handle = assistant
+        .append_task_with_handle(task::FightTask::builder().stage("1-7").build())
+        .unwrap();
+    assistant.start().unwrap();
+
+    // 未启用的任务不会收到任务链消息，在全部任务完成时结束
+    assert_eq!(handle.wait().status, TaskStatus::Completed);
+    let outcome = skipped
+        .wait_timeout(Duration::from_secs(5))
+        .unwrap_or_else(|_| panic!("未启用的任务没有结束"));
+    assert_eq!(outcome.status, TaskStatus::Skipped);
+    assert!(outcome.extra_info.is_empty());
+}
+
+#[test]
+fn test_failed_calls() {
+    let script = Script::new()
+        .fail("AsstAppendTask")
+        .fail("AsstStart")
+        .fail("AsstAsyncConnect")
+        .fail("AsstSetInstanceOption");
+    let (mut assistant, _dir) = common::create_test_assistant(script);
+
+    match assistant.append_task(task::CloseDownTask::builder().build()) {
+        Err(Error::TaskAppendFailed { task_type, params }) => {
+            assert_eq!(task_type, "CloseDown");
+            assert!(params.starts_with('{'));
+        },
+        other => panic!("unexpected result: {other:?}")
+    }
+    assert!(matches!(assistant.start(), Err(Error::StartFailed)));
+    let err = assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ConnectFailed);
+    assert!(matches!(
+        &err,
+        Error::ConnectFailed { adb_path, address } if adb_path == "adb" && address == "127.0.0.1:5555"
+    ));
+    assert!(err.to_string().contains("127.0.0.1:5555"));
+    assert!(matches!(
+        assistant.set_instance_option(InstanceOptionKey::TouchMode, "maatouch"),
+        Err(Error::SetInstanceOptionFailed {
+            key: InstanceOptionKey::TouchMode,
+            value
+        }) if value == "maatouch"
+    ));
+    assert_eq!(assistant.instance_options().touch_mode, None);
+    assert!(!assistant.is_connected());
+}
+
+#[test]
+fn test_callback_sequence() {
+    let script = Script::new()
+        .on_connect(Callback::new(
+            2,
+            json!({ "what": "Connected", "why": "", "details": { "adb": "adb", "address": "127.0.0.1:5555" } })
+        ))
+        .on_connect(Callback::raw(2, "not json"))
+        .task(
+            "Fight",
+            TaskScript::new()
+                .event(Callback::new(
+                    20003,
+                    json!({ "class": "asst::StageDropsTaskPlugin", "what": "PenguinId", "details": { "id": "42" } })
+                ))
+                .result(TaskResult::Error)
+        );
+    let dir = common::install_mock_core(script);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut assistant = Assistant::init_with_event_callback(dir.path(), {
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    })
+    .unwrap();
+
+    assert!(
+        assistant
+            .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+            .unwrap()
+            .wait()
+            .ret
+    );
+    let handle = assistant
+        .append_task_with_handle(task::FightTask::builder().stage("1-7").build())
+        .unwrap();
+    assistant.start().unwrap();
 
-    println!("should be running");
-    std::thread::sleep(std::time::Duration::from_secs(60)); // 等待60秒
+    let outcome = handle.wait();
+    assert_eq!(outcome.status, TaskStatus::Error);
+    assert!(matches!(&outcome.extra_info[0].what, ExtraInfo::PenguinId(id) if id.id == "42"));
 
-    assistant.stop().unwrap();
+    drop(assistant);
+    let events = events.lock().unwrap();
+    assert!(matches!(&events[0], Event::ConnectionInfo(info) if info.address() == Some("127.0.0.1:5555")));
+    assert!(matches!(events[1], Event::Malformed { msg_id: 2, .. }));
+    assert!(matches!(&events[2], Event::AsyncCallInfo(info) if info.details.ret));
+    assert!(matches!(events[3], Event::TaskChainStart(_)));
+    assert!(matches!(events.last(), Some(Event::Destroyed)));
+}
+
+#[test]
+fn test_connect_and_wait() {
+    let info = |what: &str, details: serde_json::Value| {
+        Callback::new(2, json!({ "what": what, "why": "", "details": details }))
+    };
+    let script = Script::new()
+        .uuid("a1b2c3")
+        .on_connect(info("Connected", json!({})))
+        .on_connect(info("UuidGot", json!({ "uuid": "a1b2c3" })))
+        .on_connect(info("ResolutionGot", json!({ "width": 1280, "height": 720 })))
+        .on_connect(info(
+            "FastestWayToScreencap",
+            json!({ "method": "RawWithGzip", "cost": 42 })
+        ));
+    let (mut assistant, _dir) = common::create_test_assistant(script);
+
+    let report = assistant
+        .connect_and_wait(
+            Connection::adb("adb", "127.0.0.1:16384"),
+            Some(ConnectionConfig::MuMuEmulator12),
+            Duration::from_secs(5)
+        )
+        .unwrap();
+    assert_eq!(report.uuid.as_deref(), Some("a1b2c3"));
+    assert_eq!(
+        report.resolution,
+        Some(Resolution {
+            width: 1280,
+            height: 720
+        })
+    );
+    let screencap = report.screencap.unwrap();
+    assert_eq!(screencap.method, "RawWithGzip");
+    assert_eq!(screencap.cost, 42);
+    assert!(assistant.is_connected());
+}
+
+#[test]
+fn test_connect_and_wait_failed() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new().disconnected());
+
+    let err = assistant
+        .connect_and_wait(
+            Connection::adb("adb", "127.0.0.1:5555"),
+            None,
+            Duration::from_secs(5)
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ConnectAborted);
+    assert!(matches!(
+        &err,
+        Error::ConnectAborted { address, what, why }
+            if address == "127.0.0.1:5555"
+                && what == "ConnectFailed"
+                && why.as_deref() == Some("Connection command failed to exec")
+    ));
+    assert!(err.to_string().contains("ConnectFailed"));
+}
+
+#[test]
+fn test_async_call() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
+
+    let connect = assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap();
+    let click = assistant.click(100, 200).unwrap();
+    assert_ne!(connect.id(), click.id());
+    assert!(click.wait().ret);
+    assert!(connect.result().unwrap().ret);
+
+    // 没有设置图片时截图失败
+    let screencap = assistant.capture_screenshot().unwrap();
+    let result = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(screencap);
+    assert!(!result.ret);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_event_stream_zero_capacity() {
+    use tokio_stream::StreamExt;
+
+    let dir = common::install_mock_core(Script::new());
+    let mut assistant = Assistant::registry()
+        .with_library(dir.path())
+        .with_resource(dir.path())
+        .with_event_stream(0)
+        .init()
+        .unwrap();
+
+    // 容量为 0 时按 1 处理
+    let mut events = assistant.events();
+    assert!(
+        assistant
+            .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+            .unwrap()
+            .wait()
+            .ret
+    );
+    let event = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(events.next());
+    assert!(matches!(event, Some(Event::AsyncCallInfo(_))));
 }
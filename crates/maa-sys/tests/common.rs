@@ -1,13 +1,34 @@
-use std::env;
-use std::path::PathBuf;
+#![allow(dead_code)]
 
-/// 获取测试资源路径
-pub fn get_test_resource_path() -> PathBuf {
-    let resource_path = env::var("MAA_RESOURCE_PATH").expect("MAA_RESOURCE_PATH 环境变量未设置");
-    PathBuf::from(resource_path)
+use std::path::{Path, PathBuf};
+
+use maa_mock_core::Script;
+use tempfile::TempDir;
+
+/// 测试用的图片等资源所在目录
+pub fn fixtures_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+/// 安装模拟运行库，返回的目录同时作为 library 和 resource 路径使用
+///
+/// 脚本中的图片路径会基于 [`fixtures_path`]
+pub fn install_mock_core(mut script: Script) -> TempDir {
+    let dir = tempfile::tempdir().expect("创建临时目录失败");
+    if let Some(image) = script.image.as_mut() {
+        *image = fixtures_path().join(&image);
+    }
+    maa_mock_core::install(dir.path(), &script).expect("安装模拟运行库失败");
+    dir
 }
 
-/// 创建测试用的 Assistant 实例
-pub fn create_test_assistant() -> maa_sys::Assistant {
-    maa_sys::Assistant::init(get_test_resource_path()).expect("创建 Assistant 实例失败")
+/// 创建使用模拟运行库的 Assistant 实例
+///
+/// 返回的目录需要在 Assistant 释放之前保持存在
+pub fn create_test_assistant(script: Script) -> (maa_sys::Assistant, TempDir) {
+    let dir = install_mock_core(script);
+    let assistant = maa_sys::Assistant::init(dir.path()).expect("创建 Assistant 实例失败");
+    (assistant, dir)
 }
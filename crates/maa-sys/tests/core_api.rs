@@ -0,0 +1,314 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use maa_sys::task::Task;
+use maa_sys::{
+    task, Assistant, Connection, CoreApi, Error, Event, InstanceHandle, InstanceOptionKey, Processor,
+    StaticOptionKey, TaskStatus
+};
+use serde_json::json;
+
+/// 进程内的假实现，记录所有调用，`start` 时同步发出任务完成的消息
+#[derive(Default)]
+struct FakeCore {
+    calls: Mutex<Vec<String>>,
+    processors: Mutex<HashMap<InstanceHandle, Processor>>,
+    tasks: Mutex<Vec<(i32, String)>>,
+    /// 在 `append_task` 中直接同步执行任务，不等待 `start`
+    run_on_append: bool
+}
+
+impl FakeCore {
+    fn record(&self, call: impl Into<String>) {
+        self.calls.lock().unwrap().push(call.into());
+    }
+
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn emit(&self, handle: InstanceHandle, msg_id: i32, details: serde_json::Value) {
+        if let Some(processor) = self.processors.lock().unwrap().get_mut(&handle) {
+            processor.dispatch(msg_id, details);
+        }
+    }
+
+    /// 同步发出任务开始和完成的消息
+    fn run_task(&self, handle: InstanceHandle, id: i32, task_type: &str) {
+        let details = json!({ "taskchain": task_type, "taskid": id, "uuid": "fake" });
+        self.emit(handle, 10001, details.clone());
+        self.emit(handle, 10002, details);
+    }
+}
+
+impl CoreApi for FakeCore {
+    fn load_resource(&self, path: &Path) -> Result<bool, Error> {
+        self.record(format!("load_resource {}", path.display()));
+        Ok(true)
+    }
+
+    fn set_static_option(&self, key: StaticOptionKey, value: &str) -> Result<bool, Error> {
+        self.record(format!("set_static_option {} {value}", key as i32));
+        Ok(true)
+    }
+
+    fn create(&self, processor: Processor) -> Result<Option<InstanceHandle>, Error> {
+        self.record("create");
+        let handle = InstanceHandle::new(1);
+        self.processors.lock().unwrap().insert(handle, processor);
+        Ok(Some(handle))
+    }
+
+    fn destroy(&self, handle: InstanceHandle) -> Result<(), Error> {
+        self.record("destroy");
+        self.processors
+            .lock()
+            .unwrap()
+            .remove(&handle)
+            .map(drop)
+            .ok_or(Error::InvalidHandle)
+    }
+
+    fn set_instance_option(
+        &self,
+        _handle: InstanceHandle,
+        key: InstanceOptionKey,
+        value: &str
+    ) -> Result<bool, Error> {
+        self.record(format!("set_instance_option {} {value}", key as i32));
+        Ok(true)
+    }
+
+    fn connect(
+        &self,
+        _handle: InstanceHandle,
+        adb_path: &str,
+        address: &str,
+        _config: Option<&str>,
+        _block: bool
+    ) -> Result<i32, Error> {
+        self.record(format!("connect {adb_path} {address}"));
+        Ok(1)
+    }
+
+    fn append_task(&self, handle: InstanceHandle, task_type: &str, _params: &str) -> Result<i32, Error> {
+        self.record(format!("append_task {task_type}"));
+        let mut tasks = self.tasks.lock().unwrap();
+        let id = tasks.len() as i32 + 1;
+        if self.run_on_append {
+            drop(tasks);
+            self.run_task(handle, id, task_type);
+        } else {
+            tasks.push((id, task_type.to_string()));
+        }
+        Ok(id)
+    }
+
+    fn set_task_params(&self, _handle: InstanceHandle, task_id: i32, _params: &str) -> Result<bool, Error> {
+        self.record(format!("set_task_params {task_id}"));
+        Ok(true)
+    }
+
+    fn start(&self, handle: InstanceHandle) -> Result<bool, Error> {
+        self.record("start");
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for (id, task_type) in tasks {
+            self.run_task(handle, id, &task_type);
+        }
+        Ok(true)
+    }
+
+    fn stop(&self, _handle: InstanceHandle) -> Result<bool, Error> {
+        self.record("stop");
+        Ok(true)
+    }
+
+    fn running(&self, _handle: InstanceHandle) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn connected(&self, _handle: InstanceHandle) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn back_to_home(&self, _handle: InstanceHandle) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn click(&self, handle: InstanceHandle, x: i32, y: i32, _block: bool) -> Result<i32, Error> {
+        self.record(format!("click {x} {y}"));
+        // 和阻塞调用一样，在返回之前发出调用结果
+        self.emit(
+            handle,
+            4,
+            json!({ "uuid": "fake", "what": "Click", "async_call_id": 7, "details": { "ret": true, "cost": 5 } })
+        );
+        Ok(7)
+    }
+
+    fn screencap(&self, _handle: InstanceHandle, _block: bool) -> Result<i32, Error> {
+        Ok(1)
+    }
+
+    fn image(&self, _handle: InstanceHandle) -> Result<Option<Vec<u8>>, Error> {
+        Ok(None)
+    }
+
+    fn uuid(&self, _handle: InstanceHandle) -> Result<Option<String>, Error> {
+        Ok(Some("fake".to_string()))
+    }
+
+    fn tasks_list(&self, _handle: InstanceHandle) -> Result<Vec<i32>, Error> {
+        Ok(self.tasks.lock().unwrap().iter().map(|(id, _)| *id).collect())
+    }
+
+    fn version(&self) -> Result<String, Error> {
+        Ok("v0.0.0-fake".to_string())
+    }
+
+    fn log(&self, level: &str, message: &str) -> Result<(), Error> {
+        self.record(format!("log {level} {message}"));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_core() {
+    let core = Arc::new(FakeCore::default());
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut assistant = Assistant::registry()
+        .with_core(core.clone())
+        .with_event_callback({
+            let events = events.clone();
+            move |event: Event| events.lock().unwrap().push(event.message())
+        })
+        .init()
+        .unwrap();
+
+    assert_eq!(assistant.version().unwrap(), "v0.0.0-fake");
+    assert_eq!(assistant.get_uuid().unwrap(), "fake");
+    assert!(assistant.is_connected());
+    assert!(matches!(assistant.get_image(), Err(Error::CaptureFailed)));
+
+    assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap();
+    let handle = assistant
+        .append_task_with_handle(task::StartUpTask::builder().client_type("Official").build())
+        .unwrap();
+    assert_eq!(assistant.get_tasks_list().unwrap()[0].task_type(), "StartUp");
+
+    assistant.start().unwrap();
+    assert_eq!(handle.wait().status, TaskStatus::Completed);
+    assert_eq!(events.lock().unwrap().len(), 2);
+
+    drop(assistant);
+    assert_eq!(
+        core.calls(),
+        [
+            "create",
+            "connect adb 127.0.0.1:5555",
+            "append_task StartUp",
+            "start",
+            "destroy"
+        ]
+    );
+    assert!(core.processors.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_custom_core_dispatch_in_append() {
+    let core = Arc::new(FakeCore {
+        run_on_append: true,
+        ..Default::default()
+    });
+    let mut assistant = Assistant::registry().with_core(core.clone()).init().unwrap();
+
+    // 任务链的消息在 append_task 返回之前就已经发出
+    let handle = assistant
+        .append_task_with_handle(task::StartUpTask::builder().client_type("Official").build())
+        .unwrap();
+    let outcome = handle
+        .wait_timeout(Duration::from_secs(1))
+        .unwrap_or_else(|_| panic!("任务句柄没有结束"));
+    assert_eq!(outcome.status, TaskStatus::Completed);
+}
+
+#[test]
+fn test_custom_core_async_call_in_click() {
+    let core = Arc::new(FakeCore::default());
+    let mut assistant = Assistant::registry().with_core(core.clone()).init().unwrap();
+
+    let call = assistant.click(100, 200).unwrap();
+    assert_eq!(call.id(), 7);
+    let details = call
+        .wait_timeout(Duration::from_secs(1))
+        .expect("异步调用句柄没有结束");
+    assert!(details.ret);
+    assert_eq!(details.cost, 5);
+}
+
+#[test]
+fn test_custom_core_with_resource() {
+    let core = Arc::new(FakeCore::default());
+    let assistant = Assistant::registry()
+        .with_core(core.clone())
+        .with_resource("/path/to/resource")
+        .init()
+        .unwrap();
+
+    assistant.log("INFO", "hello").unwrap();
+    assert_eq!(
+        core.calls(),
+        ["load_resource /path/to/resource", "create", "log INFO hello"]
+    );
+}
+
+#[test]
+fn test_custom_core_callback_panic() {
+    let core = Arc::new(FakeCore::default());
+    let mut assistant = Assistant::registry()
+        .with_core(core.clone())
+        .with_event_callback(|event: Event| {
+            if let Event::TaskChainStart(_) = event {
+                panic!("回调函数 panic");
+            }
+        })
+        .init()
+        .unwrap();
+
+    let handle = assistant
+        .append_task_with_handle(task::StartUpTask::builder().client_type("Official").build())
+        .unwrap();
+    assistant.start().unwrap();
+    assert_eq!(handle.wait().status, TaskStatus::Completed);
+
+    // 没有用户添加的监听器时 panic 也会写入 MaaCore 的日志
+    assert!(core
+        .calls()
+        .contains(&"log ERR 处理消息 10001 时回调函数 panic: 回调函数 panic".to_string()));
+}
+
+#[test]
+fn test_custom_core_drop_without_destroyed() {
+    // FakeCore 销毁实例时不会发出 Destroyed 消息
+    let core = Arc::new(FakeCore::default());
+    let mut assistant = Assistant::registry().with_core(core.clone()).init().unwrap();
+
+    let handle = assistant
+        .append_task_with_handle(task::StartUpTask::builder().client_type("Official").build())
+        .unwrap();
+    let call = assistant.capture_screenshot().unwrap();
+    drop(assistant);
+
+    let outcome = handle
+        .wait_timeout(Duration::from_secs(1))
+        .unwrap_or_else(|_| panic!("任务句柄没有结束"));
+    assert_eq!(outcome.status, TaskStatus::Stopped);
+    let details = call
+        .wait_timeout(Duration::from_secs(1))
+        .expect("异步调用句柄没有结束");
+    assert!(!details.ret);
+}
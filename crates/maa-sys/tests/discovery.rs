@@ -0,0 +1,99 @@
+#![cfg(unix)]
+
+use std::net::TcpListener;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use maa_sys::discovery::{DeviceState, Discovery};
+use maa_sys::{Connection, Error, ErrorCode};
+use tempfile::TempDir;
+
+/// 写入一个输出固定内容的 adb 脚本
+fn stub_adb(dir: &Path, script: &str) -> String {
+    let path = dir.join("adb");
+    std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+/// 获取一个当前没有监听的端口
+fn closed_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[test]
+fn test_discover() {
+    let dir = TempDir::new().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let known = TcpListener::bind("127.0.0.1:0").unwrap();
+    let known_port = known.local_addr().unwrap().port();
+
+    let adb = stub_adb(
+        dir.path(),
+        &format!(
+            r#"[ "$1 $2" = "devices -l" ] || exit 1
+echo "List of devices attached"
+echo "emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64x transport_id:1"
+echo "localhost:{known_port}        offline transport_id:2""#
+        )
+    );
+
+    let discovered = Discovery::new()
+        .with_adb(&adb)
+        .with_ports([port, known_port, closed_port()])
+        .discover();
+    assert!(discovered.adb_error.is_none());
+    let devices = discovered.devices;
+
+    assert_eq!(devices.len(), 3);
+    assert_eq!(devices[0].serial, "emulator-5554");
+    assert_eq!(devices[0].model.as_deref(), Some("sdk_gphone64_x86_64"));
+    assert_eq!(devices[0].connection, Connection::adb(&adb, "emulator-5554"));
+    // adb 已知的地址不会重复出现
+    assert_eq!(devices[1].state, DeviceState::Offline);
+    assert_eq!(devices[2].serial, format!("127.0.0.1:{port}"));
+    assert_eq!(devices[2].state, DeviceState::PortOpen);
+    assert!(devices[2].is_available());
+}
+
+#[test]
+fn test_adb_failed() {
+    let dir = TempDir::new().unwrap();
+    let adb = stub_adb(dir.path(), "echo 'adb server version mismatch' >&2\nexit 1");
+
+    let err = Discovery::new().with_adb(&adb).adb_devices().unwrap_err();
+    assert_eq!(err.code(), ErrorCode::AdbFailed);
+    assert!(matches!(err, Error::AdbFailed { message, .. } if message == "adb server version mismatch"));
+
+    let missing = dir.path().join("missing");
+    let err = Discovery::new()
+        .with_adb(missing.to_string_lossy())
+        .adb_devices()
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::AdbFailed);
+}
+
+#[test]
+fn test_discover_without_adb() {
+    let dir = TempDir::new().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // adb 不存在时仍然返回探测到的端口
+    let discovered = Discovery::new()
+        .with_adb(dir.path().join("missing").to_string_lossy())
+        .with_ports([port, closed_port()])
+        .discover();
+    assert_eq!(
+        discovered.adb_error.map(|err| err.code()),
+        Some(ErrorCode::AdbFailed)
+    );
+    assert_eq!(discovered.devices.len(), 1);
+    assert_eq!(discovered.devices[0].serial, format!("127.0.0.1:{port}"));
+    assert_eq!(discovered.devices[0].state, DeviceState::PortOpen);
+}
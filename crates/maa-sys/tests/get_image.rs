@@ -1,22 +1,91 @@
-use maa_sys::{Connection, InstanceOptionKey};
+use maa_mock_core::Script;
+use maa_sys::{Connection, Error, InstanceOptionKey};
 
 mod common;
 
 #[test]
 fn test_get_image() {
-    let mut assistant = common::create_test_assistant();
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new().image("screen.png"));
 
     assistant
         .set_instance_option(InstanceOptionKey::TouchMode, "adb")
         .unwrap();
 
     assistant
-        .connect(Connection::adb("adb", "192.168.20.29:44847"), None)
-        .unwrap();
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap()
+        .wait();
 
-    assistant.capture_screenshot().unwrap();
+    assert!(assistant.capture_screenshot().unwrap().wait().ret);
 
     let image = assistant.get_image().unwrap();
 
-    assert!(!image.is_empty());
+    let fixture = std::fs::read(common::fixtures_path().join("screen.png")).unwrap();
+    assert_eq!(image, fixture);
+}
+
+#[test]
+fn test_get_image_without_image() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
+
+    assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap()
+        .wait();
+
+    // 缓冲区扩大到上限仍然取不到图像时视为没有图像，返回截图失败
+    assert!(matches!(assistant.get_image(), Err(Error::CaptureFailed)));
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_get_frame() {
+    let (mut assistant, dir) = common::create_test_assistant(Script::new().image("screen.png"));
+
+    assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap()
+        .wait();
+    assert!(assistant.capture_screenshot().unwrap().wait().ret);
+
+    let frame = assistant.get_frame().unwrap();
+    assert_eq!((frame.width(), frame.height()), (8, 6));
+    assert_eq!(frame.stride(), 8 * 3);
+    assert_eq!(frame.data().len(), 8 * 6 * 3);
+
+    let path = dir.path().join("frame.png");
+    frame.save_png(&path).unwrap();
+    let saved = maa_sys::frame::Frame::decode(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(saved, frame);
+
+    let path = dir.path().join("frame.jpg");
+    frame
+        .save_jpeg(&path, maa_sys::frame::DEFAULT_JPEG_QUALITY)
+        .unwrap();
+    let saved = maa_sys::frame::Frame::decode(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!((saved.width(), saved.height()), (8, 6));
+
+    let thumbnail = frame.thumbnail(4, 4);
+    assert_eq!((thumbnail.width(), thumbnail.height()), (4, 3));
+
+    let err = frame
+        .save_png(dir.path().join("missing").join("frame.png"))
+        .unwrap_err();
+    assert_eq!(err.code(), maa_sys::ErrorCode::ImageSaveFailed);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_get_frame_decode_failed() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new().image("corrupt.png"));
+
+    assistant
+        .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+        .unwrap()
+        .wait();
+    assert!(assistant.capture_screenshot().unwrap().wait().ret);
+
+    let err = assistant.get_frame().unwrap_err();
+    assert!(matches!(err, maa_sys::Error::ImageDecodeFailed(_)));
+    assert_eq!(err.code(), maa_sys::ErrorCode::ImageDecodeFailed);
 }
@@ -0,0 +1,81 @@
+use maa_mock_core::Script;
+use maa_sys::profile::{ConnectionProfile, Profile, ProfileFormat};
+use maa_sys::task::{FightTask, Task};
+use maa_sys::{Error, ErrorCode, InstanceOptions, TouchMode};
+
+mod common;
+
+#[test]
+fn test_apply_profile() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
+    let profile = Profile::load(common::fixtures_path().join("daily.toml")).unwrap();
+
+    let task_ids = assistant.apply_profile(&profile).unwrap();
+    assert_eq!(task_ids.len(), 4);
+    assert!(assistant.is_connected());
+    assert_eq!(
+        assistant.instance_options(),
+        &InstanceOptions {
+            touch_mode: Some(TouchMode::MaaTouch),
+            deployment_with_pause: Some(false),
+            ..Default::default()
+        }
+    );
+
+    let tasks = assistant.get_tasks_list().unwrap();
+    let task_types: Vec<_> = tasks.iter().map(|task| task.task_type()).collect();
+    assert_eq!(task_types, ["StartUp", "Fight", "Recruit", "Award"]);
+    assert_eq!(tasks[1].to_json(), r#"{"stage":"1-7","medicine":3,"times":5}"#);
+}
+
+#[test]
+fn test_apply_profile_failed() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new().disconnected());
+
+    let profile = Profile::new()
+        .with_connection(ConnectionProfile::new("adb", ""))
+        .with_task(FightTask::builder().stage("1-7").build());
+    let err = assistant.apply_profile(&profile).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ProfileInvalid);
+    assert!(assistant.get_tasks_list().unwrap().is_empty());
+
+    let profile = Profile::new()
+        .with_connection(ConnectionProfile::new("adb", "127.0.0.1:5555"))
+        .with_task(FightTask::builder().stage("1-7").build());
+    // 连接失败时保留 MaaCore 给出的原因
+    assert!(matches!(
+        assistant.apply_profile(&profile),
+        Err(Error::ConnectAborted { address, what, why })
+            if address == "127.0.0.1:5555" && what == "ConnectFailed" && why.is_some()
+    ));
+    assert!(assistant.get_tasks_list().unwrap().is_empty());
+}
+
+#[test]
+fn test_save_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let profile = Profile::load(common::fixtures_path().join("daily.toml")).unwrap();
+
+    for name in ["daily.json", "daily.toml"] {
+        let path = dir.path().join(name);
+        profile.save(&path).unwrap();
+        let saved = Profile::load(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&saved).unwrap(),
+            serde_json::to_value(&profile).unwrap()
+        );
+    }
+    let json = std::fs::read_to_string(dir.path().join("daily.json")).unwrap();
+    assert_eq!(
+        Profile::parse(&json, ProfileFormat::Json)
+            .unwrap()
+            .name
+            .as_deref(),
+        Some("daily")
+    );
+
+    let err = profile.save(dir.path().join("daily.yaml")).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ProfileFormatUnknown);
+    let err = Profile::load(dir.path().join("missing.toml")).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ProfileIoFailed);
+}
@@ -0,0 +1,71 @@
+use maa_mock_core::{Callback, Script, TaskResult, TaskScript};
+use maa_sys::report::FightReporter;
+use maa_sys::task::FightTask;
+use maa_sys::{Assistant, Connection, TaskStatus};
+use serde_json::json;
+
+mod common;
+
+fn stage_drops(item_id: &str, quantity: i32) -> Callback {
+    Callback::new(
+        20003,
+        json!({
+            "class": "asst::StageDropsTaskPlugin",
+            "what": "StageDrops",
+            "details": {
+                "drops": [{ "itemId": item_id, "itemName": "固源岩", "quantity": quantity }],
+                "stage": { "stageCode": "1-7", "stageId": "main_01-07" },
+                "stars": 3
+            }
+        })
+    )
+}
+
+#[test]
+fn test_fight_report() {
+    let script = Script::new().task(
+        "Fight",
+        TaskScript::new()
+            .event(Callback::new(
+                20003,
+                json!({ "what": "FightTimes", "details": { "sanity_cost": 6, "series": 1, "times_finished": 0 } })
+            ))
+            .event(stage_drops("30012", 2))
+            .event(Callback::new(
+                20003,
+                json!({ "what": "UseMedicine", "details": { "count": 1, "is_expiring": false } })
+            ))
+            .event(stage_drops("30012", 1))
+            .result(TaskResult::Completed)
+    );
+    let dir = common::install_mock_core(script);
+    let reporter = FightReporter::new();
+    let mut assistant = Assistant::registry()
+        .with_library(dir.path())
+        .with_resource(dir.path())
+        .with_listener(reporter.listener())
+        .init()
+        .unwrap();
+
+    assert!(
+        assistant
+            .connect(Connection::adb("adb", "127.0.0.1:5555"), None)
+            .unwrap()
+            .wait()
+            .ret
+    );
+    let handle = assistant
+        .append_task_with_handle(FightTask::builder().stage("1-7").build())
+        .unwrap();
+    assistant.start().unwrap();
+    let outcome = handle.wait();
+    assert_eq!(outcome.status, TaskStatus::Completed);
+
+    // 监听器先于 TaskHandle 收到任务链结束的事件
+    let report = reporter.report(outcome.task_id).unwrap();
+    assert_eq!(report.status, Some(TaskStatus::Completed));
+    assert_eq!(report.stage.as_deref(), Some("1-7"));
+    assert_eq!((report.times, report.sanity, report.medicine), (2, 6, 1));
+    assert_eq!(report.quantity_of("30012"), 3);
+    assert_eq!(reporter.finished(), [report]);
+}
@@ -0,0 +1,106 @@
+use maa_mock_core::Script;
+use maa_sys::resource::{ItemIndex, StageCatalog, StageType};
+use maa_sys::task::{FightTask, Task};
+use maa_sys::{Error, ErrorCode};
+
+mod common;
+
+/// 把 fixtures 中的文件复制到资源目录
+fn install_resource(dir: &std::path::Path, name: &str) {
+    let resource = dir.join("resource");
+    std::fs::create_dir_all(&resource).unwrap();
+    std::fs::copy(common::fixtures_path().join(name), resource.join(name)).unwrap();
+}
+
+#[test]
+fn test_stage_catalog() {
+    let (assistant, dir) = common::create_test_assistant(Script::new());
+    assert_eq!(assistant.resource_path(), Some(dir.path()));
+
+    let err = assistant.stage_catalog().unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ResourceIoFailed);
+
+    install_resource(dir.path(), StageCatalog::FILE);
+    let catalog = assistant.stage_catalog().unwrap();
+    assert_eq!(catalog.stages().len(), 3);
+    assert_eq!(catalog.lookup("LS-6").unwrap().stage_type, StageType::Daily);
+    assert!(FightTask::builder()
+        .stage("LS-6")
+        .build()
+        .validate_against(&catalog)
+        .is_ok());
+    assert!(FightTask::builder()
+        .stage("LS-6 ")
+        .build()
+        .validate_against(&catalog)
+        .is_err());
+}
+
+#[test]
+fn test_stage_catalog_parse_failed() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("resource")).unwrap();
+    std::fs::write(dir.path().join("resource").join(StageCatalog::FILE), "{}").unwrap();
+
+    let err = StageCatalog::load(dir.path()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ResourceParseFailed);
+}
+
+#[test]
+fn test_item_index() {
+    let (assistant, dir) = common::create_test_assistant(Script::new());
+    install_resource(dir.path(), ItemIndex::FILE);
+
+    let index = assistant.item_index().unwrap();
+    assert_eq!(index.items().len(), 5);
+    assert_eq!(index.name_of("30061"), Some("破损装置"));
+
+    let mut task = FightTask::builder()
+        .stage("1-7")
+        .drop_target("固源岩", 20)
+        .build();
+    task.resolve_drops(&index).unwrap();
+    assert_eq!(task.to_json(), r#"{"stage":"1-7","drops":{"30012":20}}"#);
+}
+
+#[test]
+fn test_append_task_resolves_drops() {
+    let (mut assistant, dir) = common::create_test_assistant(Script::new());
+    install_resource(dir.path(), ItemIndex::FILE);
+
+    assert!(assistant
+        .append_task(
+            FightTask::builder()
+                .stage("1-7")
+                .drop_target("固源岩", 20)
+                .build()
+        )
+        .is_ok());
+
+    // 找不到的物品不会原样传给 MaaCore
+    let err = assistant
+        .append_task(FightTask::builder().stage("1-7").drop_target("源石", 1).build())
+        .unwrap_err();
+    assert!(matches!(&err, Error::InvalidFields(fields) if fields.fields[0].field == "drops"));
+
+    // 物品数据只读取一次
+    std::fs::remove_file(dir.path().join("resource").join(ItemIndex::FILE)).unwrap();
+    assert!(assistant
+        .append_task(FightTask::builder().stage("1-7").drop_target("固源岩", 1).build())
+        .is_ok());
+}
+
+#[test]
+fn test_append_task_with_drop_ids_without_item_index() {
+    let (mut assistant, _dir) = common::create_test_assistant(Script::new());
+
+    // 掉落全部是物品 ID 时不需要 item_index.json
+    assert!(assistant
+        .append_task(FightTask::builder().stage("1-7").drop_target("30011", 10).build())
+        .is_ok());
+
+    // 没有 item_index.json 时物品名原样传给 MaaCore
+    assert!(assistant
+        .append_task(FightTask::builder().stage("1-7").drop_target("源岩", 10).build())
+        .is_ok());
+}